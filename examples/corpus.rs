@@ -0,0 +1,28 @@
+//! Write the adversarial test corpus to a directory, as pairs of `CSV`
+//! fixtures: `<case>.csv` with the input, and `<case>.expected.csv` with
+//! the account summary that processing it is expected to produce.
+//!
+//! `cargo run --example corpus --features test-util -- <dir> [<seeds>]`
+
+use giant_squid::test_util::corpus::Case;
+use giant_squid::AppResult;
+use std::path::PathBuf;
+
+#[tokio::main]
+async fn main() -> AppResult<()> {
+    let mut args = std::env::args().skip(1);
+    let dir = PathBuf::from(args.next().unwrap_or_else(|| String::from("corpus")));
+    let seeds: u64 = args.next().map_or(Ok(10), |seeds| seeds.parse())?;
+    tokio::fs::create_dir_all(&dir).await?;
+    for seed in 1..=seeds {
+        for case in Case::generate_all(seed) {
+            case.write_fixture(&dir).await?;
+            println!(
+                "{}: {} rejections expected",
+                case.name(),
+                case.expected_rejections
+            );
+        }
+    }
+    Ok(())
+}
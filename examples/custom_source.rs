@@ -0,0 +1,56 @@
+//! Feed the engine from a source other than a `CSV` file, here a channel
+//! that another task sends transactions into, like a consumer of a message
+//! queue would.
+//!
+//! `cargo run --example custom_source`
+
+use giant_squid::{
+    AppError, AppResult, ClientId, Currency, Transaction, TransactionId, Transactor,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+/// Send `count` deposits of 1.0 for each of 3 clients, followed by
+/// a withdrawal of 1.5 for each of them.
+async fn produce(sender: mpsc::Sender<Transaction>, count: u32) -> AppResult<()> {
+    let mut tid = 0;
+    let mut send =
+        |transaction: fn(ClientId, TransactionId, Currency) -> Transaction, cid: u16, amount| {
+            tid += 1;
+            sender.send(transaction(
+                ClientId::from(cid),
+                TransactionId::from(tid),
+                amount,
+            ))
+        };
+    for _ in 0..count {
+        for cid in 1..=3 {
+            let amount = Currency::from_str("1.0")?;
+            if send(Transaction::deposit, cid, amount).await.is_err() {
+                return Ok(()); // NOTE: The consumer has stopped
+            }
+        }
+    }
+    for cid in 1..=3 {
+        let amount = Currency::from_str("1.5")?;
+        if send(Transaction::withdrawal, cid, amount).await.is_err() {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> AppResult<()> {
+    let (sender, receiver) = mpsc::channel(64);
+    let producer = tokio::spawn(produce(sender, 2));
+    let mut transactor = Transactor::new();
+    // NOTE: The stream ends once the producer drops its `Sender`. A source
+    //       that can fail would yield its errors instead of `Ok`s.
+    let transactions = ReceiverStream::new(receiver).map(Ok::<_, AppError>);
+    transactor.process_stream(transactions).await?;
+    producer.await.expect("the producer panicked")?;
+    transactor.write_output(tokio::io::stdout()).await?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Process transactions that are already in memory, rather than in a `CSV`
+//! file, and print the account summary and the rejected transactions.
+//!
+//! `cargo run --example in_memory`
+
+use giant_squid::{AppResult, ClientId, Currency, Transaction, TransactionId, Transactor};
+
+#[tokio::main]
+async fn main() -> AppResult<()> {
+    let (cid, tid) = (ClientId::from, TransactionId::from);
+    let amount = Currency::from_str;
+    let transactions = [
+        Transaction::deposit(cid(1), tid(1), amount("10.0")?),
+        Transaction::deposit(cid(2), tid(2), amount("2.5")?),
+        Transaction::withdrawal(cid(1), tid(3), amount("4.25")?),
+        Transaction::withdrawal(cid(2), tid(4), amount("3.0")?),
+        Transaction::dispute(cid(2), tid(2)),
+        Transaction::resolve(cid(2), tid(2)),
+    ];
+    let mut transactor = Transactor::new();
+    for t in transactions {
+        // NOTE: A rejected transaction isn't fatal, it's recorded instead.
+        let _ = transactor.process_row(t).await;
+    }
+    transactor.write_output(tokio::io::stdout()).await?;
+    for rejected in transactor.rejected() {
+        eprintln!("{}", rejected);
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Check transactions against rules of the embedding application before
+//! they reach the engine. The engine itself enforces only the policies in
+//! its `Config`, e.g. the `AmountScalePolicy` configured below.
+//!
+//! `cargo run --example validators`
+
+use giant_squid::config::{AmountScalePolicy, ScaleViolation};
+use giant_squid::{AppResult, ClientId, Config, Currency, Transaction, TransactionId, Transactor};
+
+/// A check that a transaction must pass before it's processed.
+type Validator = Box<dyn Fn(&Transaction) -> Result<(), String>>;
+
+/// Reject deposits and withdrawals above `max`.
+fn max_amount(max: Currency) -> Validator {
+    Box::new(move |t| match t.amount() {
+        Some(amount) if amount > max => Err(format!("amount {} exceeds {}", amount, max)),
+        _ => Ok(()),
+    })
+}
+
+/// Reject transactions of clients that are blocked.
+fn blocked_clients(blocked: Vec<ClientId>) -> Validator {
+    Box::new(move |t| {
+        if blocked.contains(&t.cid()) {
+            Err(format!("client {} is blocked", t.cid()))
+        } else {
+            Ok(())
+        }
+    })
+}
+
+#[tokio::main]
+async fn main() -> AppResult<()> {
+    let validators = [
+        max_amount(Currency::from_str("1000")?),
+        blocked_clients(vec![ClientId::from(3)]),
+    ];
+    let mut transactor = Transactor::with_config(Config {
+        amount_scale: AmountScalePolicy::four_decimal_places(ScaleViolation::Reject),
+        ..Config::default()
+    });
+    let deposit = |cid: u16, tid: u32, amount| -> AppResult<_> {
+        Ok(Transaction::deposit(
+            ClientId::from(cid),
+            TransactionId::from(tid),
+            Currency::from_str(amount)?,
+        ))
+    };
+    let transactions = vec![
+        deposit(1, 1, "10.0")?,
+        deposit(2, 2, "5000.0")?,
+        deposit(3, 3, "1.0")?,
+        deposit(1, 4, "0.12345")?,
+    ];
+    'transactions: for t in transactions {
+        for validator in validators.iter() {
+            if let Err(reason) = validator(&t) {
+                eprintln!("transaction {} rejected: {}", t.tid(), reason);
+                continue 'transactions;
+            }
+        }
+        if let Err(e) = transactor.process_row(t).await {
+            eprintln!("transaction {} rejected by the engine: {:?}", t.tid(), e);
+        }
+    }
+    transactor.write_output(tokio::io::stdout()).await?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! This module defines a consistency audit over the state of a `Transactor`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Transaction, TransactionId, TransactionState, Transactor};
+use crate::store::AccountStore;
+use std::collections::HashMap;
+
+/// An inconsistency in the state of a `Transactor`, found by
+/// `Transactor::audit()`. Any `Discrepancy` indicates a bug in the engine.
+/// NOTE: Transaction ids are only unique per client, so the same id in the
+/// histories of 2 accounts isn't a `Discrepancy`, and deposits and
+/// withdrawals that reuse the id of a disputed transaction are rejected.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Discrepancy {
+    /// The transaction with id `tid` is in 2 histories of the same account.
+    TransactionInMultipleStates {
+        tid: TransactionId,
+        cid: ClientId,
+        states: (TransactionState, TransactionState),
+    },
+    /// The history of the account of client `cid` contains a `transaction`
+    /// under the key `tid` that either has another id, or belongs to
+    /// another client.
+    MisfiledTransaction {
+        tid: TransactionId,
+        cid: ClientId,
+        transaction: Transaction,
+    },
+    /// The available and held funds of the account of client `cid` don't
+    /// add up to its total funds.
+    AccountBalanceInvariantViolated { cid: ClientId },
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Cross-check the transaction histories of all accounts for mutual
+    /// consistency, and return every discrepancy that was found.
+    /// This is meant to be run once all transactions have been processed.
+    pub fn audit(&self) -> Vec<Discrepancy> {
+        let mut discrepancies = vec![];
+        for account in self.accounts.values() {
+            let cid = account.id;
+            let mut seen: HashMap<TransactionId, TransactionState> = HashMap::new();
+            if account.available.checked_add(account.held) != Some(account.total) {
+                discrepancies.push(Discrepancy::AccountBalanceInvariantViolated { cid });
+            }
+            for (state, history) in account.histories().iter() {
+                for (&tid, transaction) in history.iter() {
+                    if transaction.tid != tid || transaction.cid != cid {
+                        discrepancies.push(Discrepancy::MisfiledTransaction {
+                            tid,
+                            cid,
+                            transaction: *transaction,
+                        });
+                    }
+                    if let Some(other_state) = seen.insert(tid, *state) {
+                        discrepancies.push(Discrepancy::TransactionInMultipleStates {
+                            tid,
+                            cid,
+                            states: (other_state, *state),
+                        });
+                    }
+                }
+            }
+        }
+        discrepancies
+    }
+}
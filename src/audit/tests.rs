@@ -0,0 +1,124 @@
+use super::*;
+use crate::core::{Account, Currency, TransactionType};
+use crate::error::{AppResult, TransactionError};
+
+#[tokio::test]
+async fn audit_consistent_state() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(2),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("5.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    assert_eq!(transactor.audit(), vec![]);
+    Ok(())
+}
+
+#[test]
+fn audit_inconsistent_state() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(1),
+        tid: TransactionId(1),
+        amount: Some(Currency::from_str("10.0000")?),
+    };
+    let mut account1 = Account::new(ClientId(1));
+    account1.available = Currency::from_str("10.0000")?;
+    account1.total = Currency::from_str("10.0000")?;
+    account1
+        .processed_transactions
+        .insert(TransactionId(1), deposit);
+    account1
+        .disputed_transactions
+        .insert(TransactionId(1), deposit);
+    let mut account2 = Account::new(ClientId(2));
+    account2.available = Currency::from_str("10.0000")?;
+    account2
+        .processed_transactions
+        .insert(TransactionId(1), deposit);
+    transactor.accounts.insert(ClientId(1), account1);
+    transactor.accounts.insert(ClientId(2), account2);
+    assert_eq!(
+        transactor.audit(),
+        vec![
+            Discrepancy::TransactionInMultipleStates {
+                tid: TransactionId(1),
+                cid: ClientId(1),
+                states: (TransactionState::Processed, TransactionState::Disputed),
+            },
+            Discrepancy::AccountBalanceInvariantViolated { cid: ClientId(2) },
+            Discrepancy::MisfiledTransaction {
+                tid: TransactionId(1),
+                cid: ClientId(2),
+                transaction: deposit,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn audit_transaction_ids_shared_by_clients() -> AppResult<()> {
+    let mut harness = crate::test_util::TestHarness::new();
+    harness
+        .deposit(1, 1, "10.0")
+        .await
+        .expect_accepted()
+        .deposit(2, 1, "5.0")
+        .await
+        .expect_accepted()
+        .dispute(2, 1)
+        .await
+        .expect_accepted();
+    assert_eq!(harness.transactor().audit(), vec![]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_reused_ids_of_disputed_transactions() -> AppResult<()> {
+    let mut harness = crate::test_util::TestHarness::new();
+    harness
+        .deposit(1, 1, "10.0")
+        .await
+        .expect_accepted()
+        .dispute(1, 1)
+        .await
+        .expect_accepted()
+        .deposit(1, 1, "3.0")
+        .await
+        .expect_rejected(TransactionError::TransactionIdReused {
+            tid: TransactionId(1),
+            cid: ClientId(1),
+        })
+        .resolve(1, 1)
+        .await
+        .expect_accepted()
+        .withdraw(1, 1, "3.0")
+        .await
+        .expect_rejected(TransactionError::TransactionIdReused {
+            tid: TransactionId(1),
+            cid: ClientId(1),
+        })
+        .expect_total(1, "10.0");
+    assert_eq!(harness.transactor().audit(), vec![]);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Run the same input through this version of the engine and through an
+//! older `giant-squid` binary, then report the semantic differences between
+//! the account summaries they produce:
+//!
+//! `compare-versions <old-binary> <transactions.csv>`
+//!
+//! The process exits with status 1 if any differences were found.
+//!
+//! As with the main binary, the `main` functions are feature-wise
+//! overloaded to start the appropriate runtime.
+
+use clap::Parser;
+use giant_squid::compare::{compare, read_account_rows};
+use giant_squid::{AppError, AppResult, Transactor};
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Compare the account summaries of this version of the engine with those
+/// of an older `giant-squid` binary, for the same input.
+#[derive(Parser)]
+#[command(name = "compare-versions", version)]
+struct Cli {
+    /// The older `giant-squid` binary
+    #[arg(value_name = "OLD_BINARY")]
+    old_binary: PathBuf,
+    /// The transactions file that both versions process
+    #[arg(value_name = "TRANSACTIONS")]
+    filepath: PathBuf,
+}
+
+#[cfg(not(feature = "async_file_reads"))]
+#[tokio::main]
+async fn main() -> AppResult<()> {
+    tokio::spawn(compare_versions_future()).await?
+}
+
+#[cfg(feature = "async_file_reads")]
+fn main() -> AppResult<()> {
+    tokio_uring::start(compare_versions_future())
+}
+
+async fn compare_versions_future() -> AppResult<()> {
+    let Cli {
+        old_binary,
+        filepath,
+    } = Cli::parse();
+    let output = Command::new(&old_binary).arg(&filepath).output().await?;
+    if !output.status.success() {
+        return Err(AppError::OldBinaryFailed {
+            status: output.status,
+        });
+    }
+    let old = read_account_rows(&output.stdout[..]).await?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_file(filepath).await?;
+    let new = transactor.account_rows();
+    let differences = compare(&old, &new);
+    for difference in differences.iter() {
+        println!("{}", difference);
+    }
+    if !differences.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
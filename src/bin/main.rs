@@ -11,38 +11,916 @@
 //! the `tokio` crate.
 //! Writing separate `main` functions is a reasonable
 //! way of papering over the different code paths.
+//!
+//! Usage: `giant-squid [process] [OPTIONS] <transactions.csv>...`, or
+//!        `giant-squid stats|statement|query|snapshot|validate [OPTIONS] <transactions.csv>...`, or
+//!        `giant-squid rebalance --input <shard.snapshot>... --output <shard.snapshot>...`
+//!
+//! Each subcommand processes the transactions files, and then writes its
+//! own output to the file given with `--output`, or to `stdout`:
+//! * `process` writes the account summary. It's the default, so the
+//!   subcommand name can be left out.
+//! * `stats` writes counters that describe the engine's state as `JSON`.
+//! * `statement --client <id>` writes the statement of a single client.
+//! * `query` writes the transactions in the histories of all clients that
+//!   match its filters.
+//! * `snapshot` writes a snapshot of the engine's state.
+//! * `validate` writes the issues found in the transactions files, without
+//!   processing them. It exits unsuccessfully if there are any.
+//!
+//! `rebalance` doesn't process any transactions. It redistributes the
+//! accounts in the snapshots of the shards of a sharded deployment over a
+//! new number of shards, and writes the totals it verified as `JSON`.
+//!
+//! Run `giant-squid help <subcommand>` for the options of each.
+//!
+//! Multiple transactions files are processed one after the other, in the
+//! order in which they are given. When the path of a transactions file is
+//! `-`, the transactions are read from `stdin` instead. With `--merge-by`,
+//! the files are read concurrently and merged into a single stream ordered
+//! by the given column instead.
+//!
+//! With `--load-state`, processing continues from a snapshot saved by an
+//! earlier run with `--save-state`. `--snapshot-format json` saves the
+//! snapshot as indented `JSON`, for reading and editing it by hand.
 
-use giant_squid::core::*;
-use giant_squid::error::{AppError, AppResult};
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "object-store")]
+use giant_squid::bucket::{is_object_url, ObjectLocation};
+use giant_squid::compression::Compression;
+use giant_squid::config::{
+    read_client_id_map, read_watchlist, BalanceAlert, DisputeSemantics, RepaymentPolicy,
+    RetentionPolicy,
+};
+use giant_squid::core::{TransactionQuery, TransactionState};
+use giant_squid::digest::{DigestFormat, DigestTemplate};
+#[cfg(feature = "http")]
+use giant_squid::http::is_url;
+use giant_squid::ledger::LedgerFormat;
+use giant_squid::legacy::{LegacySink, LegacyTemplate};
+use giant_squid::merge::DEFAULT_MERGE_KEY;
+use giant_squid::partition::{HashAssignment, TableAssignment};
+use giant_squid::rebalance::rebalance_snapshots;
+use giant_squid::rolling::{RollingFile, RollingPolicy};
+use giant_squid::run_log::RunLog;
+use giant_squid::snapshot::SnapshotFormat;
+use giant_squid::validate::Validator;
+use giant_squid::{
+    AppError, AppResult, ClientId, Config, Currency, TransactionId, TransactionType, Transactor,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 #[cfg(not(feature = "async_file_reads"))]
 #[tokio::main]
 async fn main() -> AppResult<()> {
-    tokio::spawn(process_transactions_future()).await?
+    let cli = Cli::parse();
+    tokio::spawn(process_transactions_future(cli)).await?
 }
 
 #[cfg(feature = "async_file_reads")]
 // Note the absence of the `#[tokio::main]` attribute.
 // This fn is also not async.
 fn main() -> AppResult<()> {
-    tokio_uring::start(process_transactions_future())
+    let cli = Cli::parse();
+    tokio_uring::start(process_transactions_future(cli))
 }
 
-async fn process_transactions_future() -> AppResult<()> {
-    let filepath = get_filepath_from_cli_arg()?;
-    let mut transactor = Transactor::new();
-    transactor.process_csv_file(filepath).await?;
+/// Process CSV files of transactions, and write the resulting account
+/// balances or other reports.
+#[derive(Parser)]
+#[command(
+    name = "giant-squid",
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The arguments of `process`, when no subcommand is given
+    #[command(flatten)]
+    process: ProcessArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write the account summary. This is the default.
+    Process(ProcessArgs),
+    /// Write counters that describe the engine's state as `JSON`.
+    Stats {
+        /// Where to write the counters, instead of `stdout`
+        #[arg(long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Write the statement of a single client as `CSV`.
+    Statement {
+        /// The id of the client
+        #[arg(long)]
+        client: u16,
+        /// Where to write the statement, instead of `stdout`
+        #[arg(long, visible_alias = "out")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Write the transactions in the histories of all clients that match
+    /// the filters, ordered by client and transaction id.
+    Query {
+        /// Only transactions of this type
+        #[arg(long = "type", value_name = "TYPE", value_parser = parse_transaction_type)]
+        ttype: Option<TransactionType>,
+        /// Only transactions in this dispute state
+        #[arg(long, value_enum)]
+        state: Option<TransactionStateArg>,
+        /// Only transactions of at least this amount
+        #[arg(long, value_name = "AMOUNT", value_parser = parse_amount)]
+        min_amount: Option<Currency>,
+        /// Only transactions of at most this amount
+        #[arg(long, value_name = "AMOUNT", value_parser = parse_amount)]
+        max_amount: Option<Currency>,
+        /// Only transactions with at least this id
+        #[arg(long, value_name = "TX")]
+        min_tx: Option<u32>,
+        /// Only transactions with at most this id
+        #[arg(long, value_name = "TX")]
+        max_tx: Option<u32>,
+        /// The format of the matching transactions
+        #[arg(long, value_enum, default_value_t = LedgerFormatArg::Csv)]
+        format: LedgerFormatArg,
+        /// Where to write the matching transactions, instead of `stdout`
+        #[arg(long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Write a snapshot of the engine's state, to continue from later with
+    /// `--load-state`.
+    Snapshot {
+        #[arg(long, value_enum, default_value_t = SnapshotFormatArg::Compact)]
+        format: SnapshotFormatArg,
+        /// Where to write the snapshot
+        #[arg(long)]
+        output: PathBuf,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Write a bundle with a snapshot of the engine's state, its stats and
+    /// the command line, to hand the state off with `import-bundle`.
+    ExportBundle {
+        /// Where to write the bundle
+        #[arg(long)]
+        output: PathBuf,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Restore the state in a bundle written by `export-bundle`, and write
+    /// it as a snapshot to continue from with `--load-state`.
+    ImportBundle {
+        /// The bundle to restore
+        #[arg(value_name = "BUNDLE")]
+        bundle: PathBuf,
+        #[arg(long, value_enum, default_value_t = SnapshotFormatArg::Compact)]
+        format: SnapshotFormatArg,
+        /// Where to write the snapshot
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Redistribute the accounts in the snapshots of N shards over M new
+    /// shards, and write a snapshot of each, without reprocessing the
+    /// transactions.
+    Rebalance {
+        /// A snapshot of one of the current shards
+        #[arg(long = "input", required = true, value_name = "SNAPSHOT")]
+        inputs: Vec<PathBuf>,
+        /// Where to write the snapshot of one of the new shards. There are
+        /// as many new shards as outputs.
+        #[arg(long = "output", required = true, value_name = "SNAPSHOT")]
+        outputs: Vec<PathBuf>,
+        /// A `client,shard` table of the new shards that own the clients.
+        /// Clients that aren't in it are spread over the shards by their id.
+        #[arg(long, value_name = "TABLE")]
+        shard_table: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = SnapshotFormatArg::Compact)]
+        format: SnapshotFormatArg,
+        /// Where to write the verified totals, instead of `stdout`
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Check the transactions files for malformed rows, unknown types,
+    /// missing amounts and duplicate transaction ids, without processing
+    /// them.
+    Validate {
+        /// The transactions files, validated in order. `-` reads `stdin`.
+        #[arg(required = true, value_name = "TRANSACTIONS")]
+        filepaths: Vec<PathBuf>,
+        /// An alternative name for a transaction type, as `<alias>=<type>`
+        #[arg(long, value_name = "ALIAS=TYPE", value_parser = parse_type_alias)]
+        type_alias: Vec<(String, TransactionType)>,
+        /// Where to write the issues found, instead of `stdout`
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// The arguments of the `process` subcommand.
+#[derive(Args)]
+struct ProcessArgs {
+    /// The format of the account summary
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv, visible_alias = "output-format")]
+    format: OutputFormat,
+    /// End the account summary with a trailer line
+    #[arg(long)]
+    trailer: bool,
+    /// Where to write the account summary, instead of `stdout`
+    #[arg(long)]
+    output: Option<PathBuf>,
+    #[command(flatten)]
+    input: InputArgs,
+}
+
+/// The arguments that determine what is processed and how, and which side
+/// reports are written. These are shared by all subcommands.
+#[derive(Args)]
+struct InputArgs {
+    /// The transactions files, processed in order. `-` reads `stdin`. With
+    /// the `http` feature, `HTTP(S)` URLs are downloaded.
+    #[arg(required = true, value_name = "TRANSACTIONS")]
+    filepaths: Vec<PathBuf>,
+    /// How disputes, resolves and chargebacks of withdrawals affect funds
+    #[arg(long, value_enum, default_value_t = DisputeSemanticsArg::Legacy)]
+    dispute_semantics: DisputeSemanticsArg,
+    /// Sweep deposits against negative held funds
+    #[arg(long)]
+    sweep_deposits: bool,
+    /// The `old,new` client id pairs to remap the input with
+    #[arg(long, value_name = "MAP")]
+    client_id_map: Option<PathBuf>,
+    /// An alternative name for a transaction type, as `<alias>=<type>`
+    #[arg(long, value_name = "ALIAS=TYPE", value_parser = parse_type_alias)]
+    type_alias: Vec<(String, TransactionType)>,
+    /// Merge the transactions files, each ordered by this column, into a
+    /// single stream ordered by it, instead of processing them in turn
+    #[arg(long, value_name = "COLUMN", num_args = 0..=1, default_missing_value = DEFAULT_MERGE_KEY)]
+    merge_by: Option<String>,
+    /// Spread the clients over this many tasks, to process them on more
+    /// than one core
+    #[arg(long, value_name = "COUNT", conflicts_with = "merge_by")]
+    shards: Option<usize>,
+    /// A `client,shard` table of the shards that own the clients. Clients
+    /// that aren't in it are spread over the shards by their id.
+    #[arg(long, value_name = "TABLE", requires = "shards")]
+    shard_table: Option<PathBuf>,
+    /// Parse up to this many rows ahead of processing, on a task of their
+    /// own, so that reading and parsing overlap with processing
+    #[arg(long, value_name = "ROWS", default_value_t = 0)]
+    pipeline_depth: usize,
+    /// Raise an alert in the metrics when the total funds of an account
+    /// leave a range, as `<name>=<floor>..<ceiling>`. Either bound can be
+    /// left out.
+    #[arg(long, value_name = "NAME=FLOOR..CEILING", value_parser = parse_balance_alert)]
+    balance_alert: Vec<BalanceAlert>,
+    /// Keep at most this many processed, undisputed transactions per
+    /// account, forgetting the oldest ones, which can't be disputed anymore
+    #[arg(long, value_name = "COUNT")]
+    max_history: Option<usize>,
+    /// Keep no transaction history, and reject every dispute, resolve and
+    /// chargeback, for feeds of only deposits and withdrawals
+    #[arg(long, conflicts_with = "max_history")]
+    no_dispute_support: bool,
+    /// Skip malformed rows and rows of unknown types, instead of stopping
+    /// at the first one. They still count against the data quality score.
+    #[arg(long)]
+    skip_malformed: bool,
+    /// The compression of the transactions files, instead of detecting it
+    /// from their extensions: `.gz` for gzip and `.zst` for Zstandard.
+    /// `stdin` is read uncompressed unless this is given.
+    #[arg(long, value_enum)]
+    compression: Option<CompressionArg>,
+    /// The clients whose accounts are to be frozen on sight
+    #[arg(long)]
+    watchlist: Option<PathBuf>,
+    /// The snapshot to continue from
+    #[arg(long, value_name = "STATE")]
+    load_state: Option<PathBuf>,
+    /// Where to save a snapshot of the state after processing
+    #[arg(long, value_name = "STATE")]
+    save_state: Option<PathBuf>,
+    /// The format of the snapshot saved with `--save-state`
+    #[arg(long, value_enum, default_value_t = SnapshotFormatArg::Compact)]
+    snapshot_format: SnapshotFormatArg,
+    /// Where to mirror accepted transactions to
+    #[arg(long)]
+    legacy_output: Option<PathBuf>,
+    /// The template of the lines written to `--legacy-output`
+    #[arg(long, requires = "legacy_output")]
+    legacy_template: Option<String>,
+    /// Where to append a `JSON` line per processed row to
+    #[arg(long)]
+    run_log: Option<PathBuf>,
+    /// Roll the run log, legacy output and rejects files over once they
+    /// hold this many bytes
+    #[arg(long, value_name = "BYTES")]
+    rotate_bytes: Option<u64>,
+    /// Roll the run log, legacy output and rejects files over once they
+    /// have been open for this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    rotate_every: Option<u64>,
+    /// The number of rolled over files to keep
+    #[arg(long, value_name = "COUNT", default_value_t = RollingPolicy::default().keep)]
+    rotate_keep: usize,
+    /// Where to write the per-client processing statistics
+    #[arg(long)]
+    stats_per_client: Option<PathBuf>,
+    /// Where to write the frozen accounts and their triggers
+    #[arg(long)]
+    freeze_report: Option<PathBuf>,
+    /// Where to write the amounts owed by clients
+    #[arg(long)]
+    owed_report: Option<PathBuf>,
+    /// Where to write the metrics in the Prometheus text format
+    #[arg(long)]
+    metrics: Option<PathBuf>,
+    /// Close the accounting period after processing, and archive its
+    /// transactions to this file
+    #[arg(long, value_name = "ARCHIVE")]
+    close_period: Option<PathBuf>,
+    /// Only seal the transactions with at most this id when closing the
+    /// accounting period
+    #[arg(long, value_name = "TX", requires = "close_period")]
+    close_period_through: Option<u32>,
+    /// Where to export the transactions of all clients to
+    #[arg(long, value_name = "LEDGER")]
+    export_ledger: Option<PathBuf>,
+    /// The format of the ledger written with `--export-ledger`
+    #[arg(long, value_enum, default_value_t = LedgerFormatArg::Csv)]
+    ledger_format: LedgerFormatArg,
+    /// Where to write a notification digest of the run per client, one
+    /// file each
+    #[arg(long, value_name = "DIR")]
+    digests: Option<PathBuf>,
+    /// The format of the digests written with `--digests`
+    #[arg(long, value_enum, default_value_t = DigestFormatArg::Text)]
+    digest_format: DigestFormatArg,
+    /// The template of the text digests, with placeholders such as
+    /// `{client}`, `{accepted}` and `{account_changes}`
+    #[arg(long, requires = "digests")]
+    digest_template: Option<String>,
+    /// Print the rejected rows, with their line numbers, to `stderr`
+    #[arg(long)]
+    show_rejected: bool,
+    /// Where to write the rejected rows, with their line numbers
+    #[arg(long)]
+    rejects: Option<PathBuf>,
+    /// Exit unsuccessfully if any row was rejected, after writing all output
+    #[arg(long)]
+    strict: bool,
+    /// Cross-check the state of the engine after processing, print the
+    /// discrepancies to `stderr`, and exit unsuccessfully if there are any
+    #[arg(long)]
+    audit: bool,
+}
+
+/// The format of the account summary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// `CSV`, optionally followed by a trailer line
+    Csv,
+    /// One `JSON` object per account. `--trailer` doesn't apply.
+    Jsonl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DisputeSemanticsArg {
+    Legacy,
+    DirectionAware,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TransactionStateArg {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+    Voided,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SnapshotFormatArg {
+    Compact,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LedgerFormatArg {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DigestFormatArg {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum CompressionArg {
+    None,
+    Gzip,
+    Zstd,
+}
+
+#[rustfmt::skip]
+impl From<CompressionArg> for Compression {
+    fn from(arg: CompressionArg) -> Self {
+        match arg {
+            CompressionArg::None => Self::Uncompressed,
+            CompressionArg::Gzip => Self::Gzip,
+            CompressionArg::Zstd => Self::Zstd,
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl From<DisputeSemanticsArg> for DisputeSemantics {
+    fn from(arg: DisputeSemanticsArg) -> Self {
+        match arg {
+            DisputeSemanticsArg::Legacy         => Self::Legacy,
+            DisputeSemanticsArg::DirectionAware => Self::DirectionAware,
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl From<SnapshotFormatArg> for SnapshotFormat {
+    fn from(arg: SnapshotFormatArg) -> Self {
+        match arg {
+            SnapshotFormatArg::Compact => Self::Compact,
+            SnapshotFormatArg::Json    => Self::Json,
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl From<TransactionStateArg> for TransactionState {
+    fn from(arg: TransactionStateArg) -> Self {
+        match arg {
+            TransactionStateArg::Processed   => Self::Processed,
+            TransactionStateArg::Disputed    => Self::Disputed,
+            TransactionStateArg::Resolved    => Self::Resolved,
+            TransactionStateArg::ChargedBack => Self::ChargedBack,
+            TransactionStateArg::Voided      => Self::Voided,
+        }
+    }
+}
+
+#[rustfmt::skip]
+impl From<LedgerFormatArg> for LedgerFormat {
+    fn from(arg: LedgerFormatArg) -> Self {
+        match arg {
+            LedgerFormatArg::Csv   => Self::Csv,
+            LedgerFormatArg::Jsonl => Self::JsonLines,
+        }
+    }
+}
+
+/// Parse an `<alias>=<type>` pair given with `--type-alias`.
+fn parse_type_alias(alias: &str) -> Result<(String, TransactionType), String> {
+    let (name, ttype) = alias
+        .split_once('=')
+        .ok_or_else(|| format!("expected <alias>=<type>, got `{}`", alias))?;
+    let ttype = TransactionType::from_name(ttype)
+        .ok_or_else(|| format!("unknown transaction type `{}`", ttype))?;
+    Ok((name.to_string(), ttype))
+}
+
+/// Parse a transaction type given with `--type`.
+fn parse_transaction_type(ttype: &str) -> Result<TransactionType, String> {
+    TransactionType::from_name(ttype).ok_or_else(|| format!("unknown transaction type `{}`", ttype))
+}
+
+/// Parse an amount given with `--min-amount` or `--max-amount`.
+fn parse_amount(amount: &str) -> Result<Currency, String> {
+    Currency::from_str(amount).map_err(|_| format!("invalid amount `{}`", amount))
+}
+
+/// Parse a `<name>=<floor>..<ceiling>` rule given with `--balance-alert`.
+fn parse_balance_alert(rule: &str) -> Result<BalanceAlert, String> {
+    let expected = || format!("expected <name>=<floor>..<ceiling>, got `{}`", rule);
+    let (name, range) = rule.split_once('=').ok_or_else(expected)?;
+    let (floor, ceiling) = range.split_once("..").ok_or_else(expected)?;
+    let bound = |amount: &str| match amount.trim() {
+        "" => Ok(None),
+        amount => Currency::from_str(amount)
+            .map(Some)
+            .map_err(|_| format!("invalid amount `{}`", amount)),
+    };
+    Ok(BalanceAlert {
+        name: name.to_string(),
+        floor: bound(floor)?,
+        ceiling: bound(ceiling)?,
+    })
+}
+
+async fn process_transactions_future(cli: Cli) -> AppResult<()> {
+    let command = cli.command.unwrap_or(Command::Process(cli.process));
+    let (input, keep_statements, index_histories) = match &command {
+        Command::Process(args) => (&args.input, false, false),
+        Command::Stats { input, .. } => (input, false, false),
+        Command::Statement { input, .. } => (input, true, false),
+        Command::Query { input, .. } => (input, false, true),
+        Command::Snapshot { input, .. } => (input, false, false),
+        Command::ExportBundle { input, .. } => (input, false, false),
+        Command::ImportBundle {
+            bundle,
+            format,
+            output,
+        } => return import_bundle(bundle.clone(), *format, output.clone()).await,
+        Command::Rebalance {
+            inputs,
+            outputs,
+            shard_table,
+            format,
+            report,
+        } => {
+            return rebalance(
+                inputs,
+                outputs,
+                shard_table.clone(),
+                *format,
+                report.clone(),
+            )
+            .await
+        }
+        Command::Validate {
+            filepaths,
+            type_alias,
+            output,
+        } => return validate_input(filepaths, type_alias, output.clone()).await,
+    };
+    let transactor = process_input(input, keep_statements, index_histories).await?;
+    let strict = input.strict;
+    let discrepancies = if input.audit {
+        transactor.audit()
+    } else {
+        vec![]
+    };
+    for discrepancy in &discrepancies {
+        eprintln!("{:?}", discrepancy);
+    }
+    match command {
+        Command::Process(args) => {
+            let mut output = open_output(args.output).await?;
+            match args.format {
+                OutputFormat::Jsonl => transactor.write_output_jsonl(&mut output).await?,
+                OutputFormat::Csv if args.trailer => {
+                    transactor.write_output_with_trailer(&mut output).await?
+                }
+                OutputFormat::Csv => transactor.write_output(&mut output).await?,
+            }
+            output.shutdown().await?;
+        }
+        Command::Stats { output, .. } => {
+            let mut stats = serde_json::to_vec(&transactor.stats()?)?;
+            stats.push(b'\n');
+            let mut output = open_output(output).await?;
+            output.write_all(&stats).await?;
+            output.shutdown().await?;
+        }
+        Command::Statement { client, output, .. } => {
+            let mut output = open_output(output).await?;
+            transactor
+                .write_statement(ClientId::from(client), &mut output)
+                .await?;
+            output.shutdown().await?;
+        }
+        Command::Query {
+            ttype,
+            state,
+            min_amount,
+            max_amount,
+            min_tx,
+            max_tx,
+            format,
+            output,
+            ..
+        } => {
+            let query = TransactionQuery {
+                ttype,
+                state: state.map(TransactionState::from),
+                min_amount,
+                max_amount,
+                min_tid: min_tx.map(TransactionId::from),
+                max_tid: max_tx.map(TransactionId::from),
+            };
+            let mut output = open_output(output).await?;
+            transactor
+                .write_search_results(&query, &mut output, format.into())
+                .await?;
+        }
+        Command::Snapshot { format, output, .. } => {
+            transactor
+                .snapshot_to_with_format(output, format.into())
+                .await?;
+        }
+        Command::ExportBundle { output, .. } => {
+            let command_line = std::env::args().collect();
+            transactor.export_bundle(output, command_line).await?;
+        }
+        Command::ImportBundle { .. } => unreachable!("importing doesn't process any input"),
+        Command::Rebalance { .. } => unreachable!("rebalancing doesn't process any input"),
+        Command::Validate { .. } => unreachable!("validation doesn't process the input"),
+    }
+    let rejected = transactor.rejected().count();
+    if strict && rejected > 0 {
+        return Err(AppError::RowsRejected { count: rejected });
+    }
+    if !discrepancies.is_empty() {
+        let count = discrepancies.len();
+        return Err(AppError::AuditFailed { count });
+    }
+    Ok(())
+}
+
+/// Process the transactions files given in `input`, and write the side
+/// reports that it asks for.
+async fn process_input(
+    input: &InputArgs,
+    keep_statements: bool,
+    index_histories: bool,
+) -> AppResult<Transactor> {
+    let mut config = Config {
+        dispute_semantics: input.dispute_semantics.into(),
+        type_aliases: input.type_alias.iter().cloned().collect(),
+        repayment: RepaymentPolicy {
+            sweep_deposits: input.sweep_deposits,
+        },
+        retention: RetentionPolicy {
+            max_settled_per_account: input.max_history,
+            history_free: input.no_dispute_support,
+        },
+        keep_statements,
+        balance_alerts: input.balance_alert.clone(),
+        skip_malformed_rows: input.skip_malformed,
+        compression: input.compression.map(Compression::from),
+        pipeline_depth: input.pipeline_depth,
+        ..Config::default()
+    };
+    if let Some(watchlist_path) = &input.watchlist {
+        config.freeze.watchlist = read_watchlist(watchlist_path.clone()).await?;
+    }
+    if let Some(map_path) = &input.client_id_map {
+        config.client_ids = read_client_id_map(map_path.clone()).await?;
+    }
+    let rolling = RollingPolicy {
+        max_bytes: input.rotate_bytes,
+        max_age: input.rotate_every.map(Duration::from_secs),
+        keep: input.rotate_keep,
+    };
+    let mut transactor = Transactor::with_config(config);
+    if index_histories {
+        transactor.index_histories();
+    }
+    if let Some(state_path) = &input.load_state {
+        transactor.restore_from(state_path.clone()).await?;
+    }
+    if let Some(legacy_path) = &input.legacy_output {
+        let template = input
+            .legacy_template
+            .clone()
+            .map_or_else(LegacyTemplate::default, LegacyTemplate::new);
+        let file = RollingFile::create(legacy_path, rolling)?;
+        transactor.set_legacy_sink(LegacySink::new(template, Box::new(file)));
+    }
+    if let Some(run_log_path) = &input.run_log {
+        let file = RollingFile::append(run_log_path, rolling)?;
+        transactor.set_run_log(RunLog::new(Box::new(file)));
+    }
+    if let Some(table_path) = &input.shard_table {
+        let table = TableAssignment::read_from(table_path.clone()).await?;
+        transactor.set_shard_assignment(table);
+    }
+    // NOTE: clap rejects `--merge-by` along with `--shards`, and neither
+    //       of them reads URLs.
+    if let Some(key_column) = &input.merge_by {
+        ensure_no_urls(&input.filepaths, "--merge-by")?;
+        transactor
+            .process_merged_csv_files(&input.filepaths, key_column)
+            .await?;
+    } else if let Some(shards) = input.shards {
+        ensure_no_urls(&input.filepaths, "--shards")?;
+        for filepath in &input.filepaths {
+            if filepath == Path::new("-") {
+                transactor.process_csv_stdin_sharded(shards).await?;
+            } else {
+                let filepath = filepath.clone();
+                transactor
+                    .process_csv_file_sharded(filepath, shards)
+                    .await?;
+            }
+        }
+    } else {
+        for filepath in &input.filepaths {
+            #[cfg(feature = "http")]
+            if let Some(url) = filepath.to_str().filter(|input| is_url(input)) {
+                transactor.process_csv_url(url).await?;
+                continue;
+            }
+            #[cfg(feature = "object-store")]
+            if let Some(url) = filepath.to_str().filter(|input| is_object_url(input)) {
+                let object = ObjectLocation::parse(url)?;
+                transactor.process_csv_object(&object).await?;
+                continue;
+            }
+            if filepath == Path::new("-") {
+                transactor.process_csv_stdin().await?;
+            } else {
+                transactor.process_csv_file(filepath.clone()).await?;
+            }
+        }
+    }
+    transactor.finish_legacy_sink()?;
+    if input.show_rejected {
+        for ignored in transactor.rejected() {
+            eprintln!("{}", ignored);
+        }
+    }
+    if let Some(rejects_path) = &input.rejects {
+        let mut file = RollingFile::create(rejects_path, rolling)?;
+        for ignored in transactor.rejected() {
+            writeln!(file, "{}", ignored)?;
+        }
+        file.flush()?;
+    }
+    transactor.finish_run_log()?;
+    if let Some(archive_path) = &input.close_period {
+        transactor
+            .close_period(input.close_period_through.map(TransactionId::from))
+            .write_csv(archive_path.clone())
+            .await?;
+    }
+    if let Some(state_path) = &input.save_state {
+        transactor
+            .snapshot_to_with_format(state_path.clone(), input.snapshot_format.into())
+            .await?;
+    }
+    if let Some(stats_path) = &input.stats_per_client {
+        transactor.write_client_stats(stats_path.clone()).await?;
+    }
+    if let Some(report_path) = &input.freeze_report {
+        transactor.write_freeze_report(report_path.clone()).await?;
+    }
+    if let Some(report_path) = &input.owed_report {
+        transactor.write_owed_report(report_path.clone()).await?;
+    }
+    if let Some(ledger_path) = &input.export_ledger {
+        let mut file = tokio::fs::File::create(ledger_path).await?;
+        transactor
+            .write_ledger(&mut file, input.ledger_format.into())
+            .await?;
+    }
+    if let Some(digests_path) = &input.digests {
+        let format = match input.digest_format {
+            DigestFormatArg::Text => DigestFormat::Text(
+                input
+                    .digest_template
+                    .clone()
+                    .map_or_else(DigestTemplate::default, DigestTemplate::new),
+            ),
+            DigestFormatArg::Json => DigestFormat::Json,
+        };
+        transactor
+            .write_client_digests(digests_path.clone(), &format)
+            .await?;
+    }
+    if let Some(metrics_path) = &input.metrics {
+        let metrics = transactor.metrics().render_prometheus();
+        tokio::fs::write(metrics_path, metrics).await?;
+    }
     // NOTE: Unslash this println!() call for a peek at the `transactor`
     //       state after it's done processing all the transactions:
     // println!("transactor: {:#?}", transactor);
-    transactor.print_output().await;
+    Ok(transactor)
+}
+
+/// Validate the transactions files at `filepaths`, and write the issues
+/// found, one per line, to `output`.
+async fn validate_input(
+    filepaths: &[PathBuf],
+    type_alias: &[(String, TransactionType)],
+    output: Option<PathBuf>,
+) -> AppResult<()> {
+    let config = Config {
+        type_aliases: type_alias.iter().cloned().collect(),
+        ..Config::default()
+    };
+    let mut validator = Validator::new(&config);
+    let mut out = String::new();
+    for filepath in filepaths {
+        let before = validator.issues().len();
+        if filepath == Path::new("-") {
+            validator.validate_csv_reader(tokio::io::stdin()).await?;
+        } else {
+            validator.validate_csv_file(filepath.clone()).await?;
+        }
+        for issue in &validator.issues()[before..] {
+            out.push_str(&format!("{}: {}\n", filepath.display(), issue));
+        }
+    }
+    let mut output = open_output(output).await?;
+    output.write_all(out.as_bytes()).await?;
+    output.shutdown().await?;
+    let report = validator.finish();
+    if !report.is_valid() {
+        return Err(AppError::InvalidInput {
+            count: report.issues.len(),
+        });
+    }
     Ok(())
 }
 
-fn get_filepath_from_cli_arg() -> AppResult<PathBuf> {
-    match std::env::args_os().nth(1) {
-        None => Err(AppError::NoFileNameCliArgFound),
-        Some(path) => Ok(PathBuf::from(path)),
+/// Ensure that none of the `filepaths` is a URL, as the inputs of `flag`
+/// are read as files.
+#[cfg(any(feature = "http", feature = "object-store"))]
+fn ensure_no_urls(filepaths: &[PathBuf], flag: &'static str) -> AppResult<()> {
+    for input in filepaths.iter().filter_map(|filepath| filepath.to_str()) {
+        #[cfg(feature = "http")]
+        let is_remote = is_url(input);
+        #[cfg(not(feature = "http"))]
+        let is_remote = false;
+        #[cfg(feature = "object-store")]
+        let is_remote = is_remote || is_object_url(input);
+        if is_remote {
+            let input = input.to_string();
+            return Err(AppError::UnsupportedForUrls { input, flag });
+        }
     }
+    Ok(())
+}
+
+/// Without the `http` and `object-store` features, inputs are never URLs.
+#[cfg(not(any(feature = "http", feature = "object-store")))]
+fn ensure_no_urls(_filepaths: &[PathBuf], _flag: &'static str) -> AppResult<()> {
+    Ok(())
+}
+
+/// The file at `path` if given, for writing the main output of a
+/// subcommand to, or `stdout` otherwise. The output is complete once it's
+/// shut down.
+async fn open_output(path: Option<PathBuf>) -> AppResult<Box<dyn AsyncWrite + Send + Unpin>> {
+    #[cfg(feature = "object-store")]
+    if let Some(url) = path
+        .as_ref()
+        .and_then(|p| p.to_str())
+        .filter(|p| is_object_url(p))
+    {
+        return Ok(Box::new(ObjectLocation::parse(url)?.writer()));
+    }
+    Ok(match path {
+        Some(path) => Box::new(tokio::fs::File::create(path).await?),
+        None => Box::new(tokio::io::stdout()),
+    })
+}
+
+/// Restore the state in the bundle at `bundle`, and write it as a snapshot
+/// in the given `format` to `output`.
+async fn import_bundle(
+    bundle: PathBuf,
+    format: SnapshotFormatArg,
+    output: PathBuf,
+) -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    transactor.restore_from_bundle(bundle).await?;
+    transactor
+        .snapshot_to_with_format(output, format.into())
+        .await
+}
+
+/// Redistribute the accounts in the shard snapshots at `inputs` over as
+/// many new shards as there are `outputs`, as the table at `shard_table`
+/// says if given, and write the verified totals as `JSON` to `report`.
+async fn rebalance(
+    inputs: &[PathBuf],
+    outputs: &[PathBuf],
+    shard_table: Option<PathBuf>,
+    format: SnapshotFormatArg,
+    report: Option<PathBuf>,
+) -> AppResult<()> {
+    let totals = match shard_table {
+        Some(table_path) => {
+            let table = TableAssignment::read_from(table_path).await?;
+            rebalance_snapshots(inputs, table, outputs, format.into()).await?
+        }
+        None => rebalance_snapshots(inputs, HashAssignment, outputs, format.into()).await?,
+    };
+    let mut totals = serde_json::to_vec(&totals)?;
+    totals.push(b'\n');
+    let mut output = open_output(report).await?;
+    output.write_all(&totals).await?;
+    output.shutdown().await?;
+    Ok(())
 }
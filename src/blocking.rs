@@ -0,0 +1,123 @@
+//! This module defines a blocking API for embedding the engine in programs
+//! that don't run a tokio runtime, enabled with the `sync` feature.
+//!
+//! The engine itself never waits on anything while it processes
+//! transactions; only its inputs and outputs do. So each blocking method
+//! reads and writes with `std::io` instead, and drives the same code as
+//! its async counterpart to completion on the calling thread.
+
+#[cfg(test)]
+mod tests;
+
+use crate::compression::decompress;
+use crate::core::{Transaction, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+impl<S: AccountStore> Transactor<S> {
+    /// Process the `transactions` in order, in the same way as the rows of
+    /// a `CSV` input. Rejected transactions are kept, as with
+    /// `process_row()`. Only fatal errors are returned.
+    pub fn process_iter<I>(&mut self, transactions: I) -> AppResult<()>
+    where
+        I: IntoIterator<Item = Transaction>,
+    {
+        let rows = transactions.into_iter().map(Ok::<_, AppError>);
+        block_on(self.process_stream(tokio_stream::iter(rows)))
+    }
+
+    /// Read, deserialize and process the transactions in the `CSV` file at
+    /// `filepath`, decompressing it if needed, blocking the calling thread
+    /// until it's done.
+    pub fn process_csv_file_sync(&mut self, filepath: PathBuf) -> AppResult<()> {
+        let file = std::fs::File::open(&filepath)?;
+        let reader = decompress(SyncReader(file), self.config.compression_of(&filepath));
+        let source = filepath.display().to_string();
+        block_on(self.process_named_csv_reader(reader, source))
+    }
+
+    /// Read, deserialize and process the transactions in the `CSV` data
+    /// produced by `reader`, blocking the calling thread until it's done.
+    pub fn process_csv_reader_sync<R>(&mut self, reader: R) -> AppResult<()>
+    where
+        R: Read + Unpin + Send + Sync,
+    {
+        block_on(self.process_csv_reader(SyncReader(reader)))
+    }
+
+    /// Write the account summary as `CSV` to `writer`, blocking the calling
+    /// thread until it's done.
+    pub fn write_output_sync<W>(&self, writer: W) -> AppResult<()>
+    where
+        W: Write + Unpin,
+    {
+        block_on(self.write_output(SyncWriter(writer)))
+    }
+}
+
+/// Wakes a thread that is blocked on a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll `future` to completion on the calling thread, parking the thread
+/// whenever the future isn't ready.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// An `AsyncRead` that reads from a blocking `Read`, so it's always ready.
+struct SyncReader<R>(R);
+
+impl<R: Read + Unpin> AsyncRead for SyncReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = self.0.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// An `AsyncWrite` that writes to a blocking `Write`, so it's always ready.
+struct SyncWriter<W>(W);
+
+impl<W: Write + Unpin> AsyncWrite for SyncWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.0.write(buf))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.0.flush())
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.0.flush())
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+use crate::core::{ClientId, Currency, TransactionId};
+use crate::test_util::temp::TempPath;
+use async_compression::tokio::bufread::GzipEncoder;
+use tokio::io::AsyncReadExt;
+
+// NOTE: These are plain tests rather than `#[tokio::test]`s, to show that
+//       the blocking API doesn't need a runtime.
+
+#[test]
+fn process_an_iterator() -> AppResult<()> {
+    let (cid, tid) = (ClientId::from, TransactionId::from);
+    let amount = Currency::from_str;
+    let transactions = vec![
+        Transaction::deposit(cid(1), tid(1), amount("10.0")?),
+        Transaction::withdrawal(cid(1), tid(2), amount("4.0")?),
+        Transaction::withdrawal(cid(1), tid(3), amount("7.0")?),
+        Transaction::dispute(cid(1), tid(1)),
+    ];
+    let mut transactor = Transactor::new();
+    transactor.process_iter(transactions)?;
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), amount("-4.0")?);
+    assert_eq!(account.held(), amount("10.0")?);
+    let rejected: Vec<_> = transactor
+        .rejected()
+        .map(|r| r.transaction().tid())
+        .collect();
+    assert_eq!(rejected, [TransactionId(3)]);
+    Ok(())
+}
+
+#[test]
+fn process_a_csv_file_and_write_the_output() -> AppResult<()> {
+    let path = TempPath::new("blocking.csv");
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.5\n\
+               deposit,2,2,2.0\n\
+               withdrawal,2,3,0.5\n";
+    std::fs::write(&path, csv)?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_file_sync(path.to_path_buf())?;
+    let mut output = vec![];
+    transactor.write_output_sync(&mut output)?;
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "client,available,held,total,locked\n\
+         1,1.5000,0.0000,1.5000,false\n\
+         2,1.5000,0.0000,1.5000,false\n"
+    );
+    let mut from_reader = Transactor::new();
+    from_reader.process_csv_reader_sync(csv.as_bytes())?;
+    assert!(from_reader
+        .account_summaries()
+        .eq(transactor.account_summaries()));
+    Ok(())
+}
+
+#[test]
+fn process_a_compressed_csv_file() -> AppResult<()> {
+    let path = TempPath::new("blocking.csv.gz");
+    let csv = "type,client,tx,amount\ndeposit,1,1,1.5\n";
+    let mut compressed = vec![];
+    let mut encoder = GzipEncoder::new(csv.as_bytes());
+    block_on(encoder.read_to_end(&mut compressed))?;
+    std::fs::write(&path, compressed)?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_file_sync(path.to_path_buf())?;
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), Currency::from_str("1.5")?);
+    Ok(())
+}
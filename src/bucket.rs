@@ -0,0 +1,125 @@
+//! This module defines objects in object stores, e.g. S3, GCS or Azure Blob
+//! Storage buckets, as a source of `CSV` input and a destination of reports.
+//!
+//! Objects are addressed by URLs such as `s3://payments/2024-06.csv`,
+//! `gs://payments/2024-06.csv` or `az://payments/2024-06.csv`. The
+//! credentials and region of each store are read from the environment, in
+//! the same way as its own command line tools do, e.g. `AWS_ACCESS_KEY_ID`
+//! and `AWS_REGION` for S3.
+//!
+//! Objects are streamed into the engine while they download, and
+//! decompressed going by the extension of their path, unless
+//! `Config::compression` says otherwise. Reports are uploaded in parts as
+//! they're written, and the object only appears in the store once its
+//! writer is shut down.
+
+#[cfg(test)]
+mod tests;
+
+use crate::compression::Compression;
+use crate::core::Transactor;
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use object_store::aws::AmazonS3Builder;
+use object_store::azure::MicrosoftAzureBuilder;
+use object_store::buffered::BufWriter;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, ObjectStoreScheme};
+use std::path::Path;
+use std::sync::Arc;
+use url::Url;
+
+/// Whether `input` is the URL of an object in a supported object store,
+/// rather than a path.
+pub fn is_object_url(input: &str) -> bool {
+    Url::parse(input).is_ok_and(|url| object_scheme(&url).is_some())
+}
+
+/// The scheme of the object store that `url` addresses, if it's supported.
+fn object_scheme(url: &Url) -> Option<ObjectStoreScheme> {
+    if url.scheme() == "http" || url.scheme() == "https" {
+        // NOTE: `HTTP(S)` URLs are downloaded as plain files, even those
+        //       of the `HTTP` endpoints of object stores.
+        return None;
+    }
+    match ObjectStoreScheme::parse(url) {
+        Ok((
+            scheme @ (ObjectStoreScheme::AmazonS3
+            | ObjectStoreScheme::GoogleCloudStorage
+            | ObjectStoreScheme::MicrosoftAzure),
+            _,
+        )) => Some(scheme),
+        _ => None,
+    }
+}
+
+/// An object in an object store.
+#[derive(Clone, Debug)]
+pub struct ObjectLocation {
+    store: Arc<dyn ObjectStore>,
+    path: ObjectPath,
+    /// The name of the object in logs and errors, e.g. its URL.
+    name: String,
+}
+
+impl ObjectLocation {
+    /// The object at `url`, in a store that is configured from the
+    /// environment.
+    pub fn parse(url: &str) -> AppResult<Self> {
+        let invalid = || AppError::InvalidObjectUrl {
+            url: url.to_string(),
+        };
+        let parsed = Url::parse(url).map_err(|_| invalid())?;
+        let store: Arc<dyn ObjectStore> = match object_scheme(&parsed).ok_or_else(invalid)? {
+            ObjectStoreScheme::AmazonS3 => {
+                Arc::new(AmazonS3Builder::from_env().with_url(url).build()?)
+            }
+            ObjectStoreScheme::GoogleCloudStorage => Arc::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_url(url)
+                    .build()?,
+            ),
+            ObjectStoreScheme::MicrosoftAzure => {
+                Arc::new(MicrosoftAzureBuilder::from_env().with_url(url).build()?)
+            }
+            _ => return Err(invalid()),
+        };
+        let (_, path) = ObjectStoreScheme::parse(&parsed).map_err(|_| invalid())?;
+        Ok(Self {
+            store,
+            path,
+            name: url.to_string(),
+        })
+    }
+
+    /// The object at `path` in `store`, e.g. a store that the embedding
+    /// application configured itself.
+    pub fn new(store: Arc<dyn ObjectStore>, path: &str) -> AppResult<Self> {
+        Ok(Self {
+            store,
+            path: ObjectPath::parse(path).map_err(object_store::Error::from)?,
+            name: path.to_string(),
+        })
+    }
+
+    /// A writer that replaces the object with the data written to it, once
+    /// it's shut down.
+    pub fn writer(&self) -> BufWriter {
+        BufWriter::new(self.store.clone(), self.path.clone())
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Stream the `CSV` object at `object` from its store, and process the
+    /// transactions in it in the same way as those in a local file.
+    pub async fn process_csv_object(&mut self, object: &ObjectLocation) -> AppResult<()> {
+        let body = object.store.get(&object.path).await?.into_stream();
+        let compression = self
+            .config
+            .compression
+            .unwrap_or_else(|| Compression::detect(Path::new(object.path.as_ref())));
+        self.process_byte_stream(body, compression, object.name.clone())
+            .await
+    }
+}
@@ -0,0 +1,57 @@
+use super::*;
+use object_store::memory::InMemory;
+use object_store::PutPayload;
+use tokio::io::AsyncWriteExt;
+
+const CSV: &str = "type,client,tx,amount
+deposit,1,1,1.0
+deposit,2,2,2.0
+withdrawal,1,3,0.5
+";
+
+#[test]
+fn tell_object_urls_from_paths() {
+    assert!(is_object_url("s3://payments/2024-06.csv"));
+    assert!(is_object_url("gs://payments/2024-06.csv"));
+    assert!(is_object_url("az://payments/2024-06.csv"));
+    assert!(!is_object_url("https://payments.example.com/2024-06.csv"));
+    assert!(!is_object_url("2024-06.csv"));
+    assert!(!is_object_url("-"));
+}
+
+#[tokio::test]
+async fn process_an_object_and_write_the_summary_back() -> AppResult<()> {
+    let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+    let input = ObjectLocation::new(store.clone(), "2024-06.csv")?;
+    store
+        .put(&input.path, PutPayload::from_static(CSV.as_bytes()))
+        .await?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_object(&input).await?;
+    let output = ObjectLocation::new(store.clone(), "reports/accounts.csv")?;
+    let mut writer = output.writer();
+    transactor.write_output(&mut writer).await?;
+    writer.shutdown().await?;
+    let written = store.get(&output.path).await?.bytes().await?;
+    let mut expected = Transactor::new();
+    expected.process_csv_reader(CSV.as_bytes()).await?;
+    let mut summary = vec![];
+    expected.write_output(&mut summary).await?;
+    assert_eq!(written.as_ref(), summary.as_slice());
+    assert_eq!(transactor.data_quality()[0].source, "2024-06.csv");
+    Ok(())
+}
+
+#[tokio::test]
+async fn fail_on_a_missing_object() -> AppResult<()> {
+    let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+    let input = ObjectLocation::new(store, "missing.csv")?;
+    let result = Transactor::new().process_csv_object(&input).await;
+    assert!(matches!(
+        result,
+        Err(AppError::ObjectStoreError(
+            object_store::Error::NotFound { .. }
+        ))
+    ));
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! This module defines bundles: single `tar` archives that hold everything
+//! needed to reproduce the state of a `Transactor` elsewhere, e.g. to hand
+//! a run off to another machine, or to attach it to a bug report.
+//!
+//! A bundle holds these entries:
+//! * `manifest.json`: a `BundleManifest` that describes the run, including
+//!   the command line it was started with, which configured it.
+//! * `snapshot.json`: a snapshot of the state, as written by `snapshot_to()`.
+//! * `config.json`: the `Config` of the `Transactor`.
+//! * `stats.json`: the `EngineStats` at the time of the export.
+//!
+//! Restoring a bundle restores its snapshot, migrating it if an older
+//! version of the crate wrote it, along with its `Config`, so that the
+//! restored `Transactor` goes on processing rows in the same way. Bundles
+//! without a `Config`, i.e. those exported by older versions of the crate,
+//! leave the `Config` of the restoring `Transactor` as it is.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::Transactor;
+use crate::error::{AppError, AppResult};
+use crate::migrations::SNAPSHOT_VERSION;
+use crate::snapshot::SnapshotFormat;
+use crate::store::AccountStore;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::PathBuf;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SNAPSHOT_ENTRY: &str = "snapshot.json";
+const CONFIG_ENTRY: &str = "config.json";
+const STATS_ENTRY: &str = "stats.json";
+
+/// Describes the run that a bundle was exported from.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BundleManifest {
+    /// The version of the crate that exported the bundle.
+    pub crate_version: String,
+    /// The layout version of the snapshot in the bundle.
+    pub snapshot_version: u64,
+    pub rows_processed: u64,
+    pub accounts: usize,
+    /// The command line of the run, if it was started from one.
+    pub command_line: Vec<String>,
+}
+
+impl<S> Transactor<S>
+where
+    S: AccountStore + serde::Serialize + DeserializeOwned,
+{
+    /// Write a bundle of the state of the `Transactor` to the file at
+    /// `filepath`, replacing the file if it exists. The `command_line` that
+    /// started the run, if any, is recorded in its manifest.
+    pub async fn export_bundle(
+        &self,
+        filepath: PathBuf,
+        command_line: Vec<String>,
+    ) -> AppResult<BundleManifest> {
+        let manifest = BundleManifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            snapshot_version: SNAPSHOT_VERSION,
+            rows_processed: self.rows_processed,
+            accounts: self.accounts.len(),
+            command_line,
+        };
+        let mut builder = tar::Builder::new(vec![]);
+        for (entry, contents) in [
+            (MANIFEST_ENTRY, serde_json::to_vec_pretty(&manifest)?),
+            (
+                SNAPSHOT_ENTRY,
+                self.serialize_snapshot(SnapshotFormat::Json)?,
+            ),
+            (CONFIG_ENTRY, serde_json::to_vec_pretty(&self.config)?),
+            (STATS_ENTRY, serde_json::to_vec_pretty(&self.stats()?)?),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, entry, contents.as_slice())?;
+        }
+        let bundle = builder.into_inner()?;
+        tokio::fs::write(filepath, bundle).await?;
+        Ok(manifest)
+    }
+
+    /// Replace the state and `Config` of the `Transactor` by those in the
+    /// bundle in the file at `filepath`, and return the manifest of the
+    /// bundle. The sinks of the `Transactor` are kept.
+    pub async fn restore_from_bundle(&mut self, filepath: PathBuf) -> AppResult<BundleManifest> {
+        let bundle = tokio::fs::read(filepath).await?;
+        let (mut manifest, mut snapshot, mut config) = (None, None, None);
+        let mut archive = tar::Archive::new(bundle.as_slice());
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let slot = match entry.path()?.to_str() {
+                Some(MANIFEST_ENTRY) => &mut manifest,
+                Some(SNAPSHOT_ENTRY) => &mut snapshot,
+                Some(CONFIG_ENTRY) => &mut config,
+                // NOTE: The stats are for people, and aren't restored.
+                _ => continue,
+            };
+            let mut contents = vec![];
+            entry.read_to_end(&mut contents)?;
+            *slot = Some(contents);
+        }
+        let missing = |entry| AppError::IncompleteBundle { entry };
+        let manifest = manifest.ok_or_else(|| missing(MANIFEST_ENTRY))?;
+        let snapshot = snapshot.ok_or_else(|| missing(SNAPSHOT_ENTRY))?;
+        let manifest = serde_json::from_slice(&manifest)?;
+        if let Some(config) = config {
+            // NOTE: Restoring the snapshot depends on the `Config`, e.g. to
+            //       rebuild the secondary indexes, so it goes first.
+            self.config = serde_json::from_slice(&config)?;
+        }
+        self.restore_from_slice(&snapshot)?;
+        Ok(manifest)
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+use crate::compression::Compression;
+use crate::config::{ClientIdMap, Config, DisputeSemantics};
+use crate::core::{ClientId, Currency, Transaction, TransactionId, TransactionType};
+use crate::test_util::temp::TempPath;
+use std::time::Duration;
+
+#[tokio::test]
+async fn export_and_restore_a_bundle() -> AppResult<()> {
+    let path = TempPath::new("handoff.bundle");
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let mut transactor = Transactor::new();
+    for t in [
+        Transaction::deposit(cid(1), tid(1), amount("2.5")?),
+        Transaction::deposit(cid(2), tid(2), amount("1.0")?),
+        Transaction::dispute(cid(2), tid(2)),
+    ] {
+        transactor.process_row(t).await?;
+    }
+    let command_line = vec!["giant-squid".to_string(), "in.csv".to_string()];
+    transactor
+        .export_bundle(path.to_path_buf(), command_line)
+        .await?;
+    let mut restored = Transactor::new();
+    let manifest = restored.restore_from_bundle(path.to_path_buf()).await?;
+    assert_eq!(manifest.snapshot_version, SNAPSHOT_VERSION);
+    assert_eq!(manifest.rows_processed, 3);
+    assert_eq!(manifest.accounts, 2);
+    assert_eq!(manifest.command_line, ["giant-squid", "in.csv"]);
+    assert_eq!(
+        restored.account_summaries().collect::<Vec<_>>(),
+        transactor.account_summaries().collect::<Vec<_>>()
+    );
+    // NOTE: The open dispute survives the hand-off, so it can be resolved.
+    restored
+        .process_row(Transaction::resolve(cid(2), tid(2)))
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_the_config_of_a_bundle() -> AppResult<()> {
+    let path = TempPath::new("config.bundle");
+    let mut client_ids = ClientIdMap::new();
+    client_ids.insert(ClientId(1), ClientId(7))?;
+    let config = Config {
+        latency_budget: Some(Duration::from_millis(5)),
+        dispute_grace_rows: 10,
+        dispute_semantics: DisputeSemantics::DirectionAware,
+        client_ids,
+        type_aliases: [("credit".to_string(), TransactionType::Deposit)].into(),
+        keep_statements: true,
+        compression: Some(Compression::Gzip),
+        ..Config::default()
+    };
+    let transactor = Transactor::with_config(config.clone());
+    transactor.export_bundle(path.to_path_buf(), vec![]).await?;
+    let mut restored = Transactor::new();
+    restored.restore_from_bundle(path.to_path_buf()).await?;
+    assert_eq!(restored.config, config);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_a_bundle_without_a_snapshot() -> AppResult<()> {
+    let path = TempPath::new("incomplete.bundle");
+    let mut builder = tar::Builder::new(vec![]);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(2);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_ENTRY, &b"{}"[..])?;
+    std::fs::write(&path, builder.into_inner()?)?;
+    let result = Transactor::new()
+        .restore_from_bundle(path.to_path_buf())
+        .await;
+    assert!(matches!(
+        result,
+        Err(AppError::IncompleteBundle {
+            entry: SNAPSHOT_ENTRY
+        })
+    ));
+    Ok(())
+}
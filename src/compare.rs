@@ -0,0 +1,118 @@
+//! This module compares the account summaries produced by 2 versions of the
+//! engine for the same input, e.g. to validate an upgrade.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Transactor};
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use crate::trailer::verify_trailer;
+use csv_async::AsyncReaderBuilder;
+use rust_decimal::prelude::Decimal;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_stream::StreamExt;
+
+/// A single row of an account summary. The amounts are parsed as decimals,
+/// so that formatting differences (e.g. `1.5` vs `1.5000`) don't count as
+/// semantic differences.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct AccountRow {
+    pub client: ClientId,
+    pub available: Decimal,
+    pub held: Decimal,
+    pub total: Decimal,
+    pub locked: bool,
+}
+
+/// A semantic difference between 2 account summaries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference {
+    /// The client only has an account in the new summary.
+    MissingInOld(AccountRow),
+    /// The client only has an account in the old summary.
+    MissingInNew(AccountRow),
+    /// The client's account differs between the summaries.
+    Mismatch { old: AccountRow, new: AccountRow },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingInOld(new) => write!(f, "client {}: only in new: {:?}", new.client, new),
+            Self::MissingInNew(old) => write!(f, "client {}: only in old: {:?}", old.client, old),
+            Self::Mismatch { old, new } => {
+                write!(f, "client {}: old {:?} != new {:?}", old.client, old, new)
+            }
+        }
+    }
+}
+
+/// Read an account summary in the engine's CSV output format.
+/// If the summary ends in a trailer, the rows are verified against it.
+pub async fn read_account_rows<R>(mut reader: R) -> AppResult<BTreeMap<ClientId, AccountRow>>
+where
+    R: AsyncRead + Unpin + Send + Sync,
+{
+    let mut summary = String::new();
+    reader.read_to_string(&mut summary).await?;
+    let _ = verify_trailer(&summary)?;
+    let mut rows = AsyncReaderBuilder::new()
+        .trim(csv_async::Trim::All)
+        .comment(Some(b'#')) // Skip the trailer
+        .create_deserializer(summary.as_bytes())
+        .into_deserialize::<AccountRow>();
+    let mut accounts = BTreeMap::new();
+    while let Some(row) = rows.next().await {
+        let row: AccountRow = row?;
+        accounts.insert(row.client, row);
+    }
+    Ok(accounts)
+}
+
+/// Compare an `old` and a `new` account summary, and return all semantic
+/// differences between them, ordered by `ClientId`.
+pub fn compare(
+    old: &BTreeMap<ClientId, AccountRow>,
+    new: &BTreeMap<ClientId, AccountRow>,
+) -> Vec<Difference> {
+    let mut cids: Vec<&ClientId> = old.keys().chain(new.keys()).collect();
+    cids.sort();
+    cids.dedup();
+    cids.into_iter()
+        .filter_map(|cid| match (old.get(cid), new.get(cid)) {
+            (Some(o), Some(n)) if o == n => None,
+            (Some(o), Some(n)) => Some(Difference::Mismatch {
+                old: o.clone(),
+                new: n.clone(),
+            }),
+            (Some(o), None) => Some(Difference::MissingInNew(o.clone())),
+            (None, Some(n)) => Some(Difference::MissingInOld(n.clone())),
+            (None, None) => None,
+        })
+        .collect()
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// The account summary of this `Transactor`, in the same form as an
+    /// account summary read by `read_account_rows()`.
+    pub fn account_rows(&self) -> BTreeMap<ClientId, AccountRow> {
+        self.accounts
+            .values()
+            .map(|account| {
+                let cid = account.id;
+                let row = AccountRow {
+                    client: cid,
+                    available: account.available.0,
+                    held: account.held.0,
+                    total: account.total.0,
+                    locked: account.is_locked,
+                };
+                (cid, row)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,52 @@
+use super::*;
+use crate::core::{Currency, Transaction, TransactionId, TransactionType};
+
+#[tokio::test]
+async fn compare_summaries_semantically() -> AppResult<()> {
+    let old = "client,available,held,total,locked\n\
+               1,1.5,0,1.5,false\n\
+               2,2.0000,0.0000,2.0000,false\n\
+               3,1.0000,0.0000,1.0000,false\n";
+    let new = "client, available, held, total, locked\n\
+               1, 1.5000, 0.0000, 1.5000, false\n\
+               2, 2.0000, 0.0000, 2.0000, true\n\
+               4, 0.0000, 0.0000, 0.0000, false\n";
+    let old = read_account_rows(old.as_bytes()).await?;
+    let new = read_account_rows(new.as_bytes()).await?;
+    let row = |cid: u16, amount: &str, locked: bool| AccountRow {
+        client: ClientId(cid),
+        available: amount.parse().unwrap(),
+        held: Decimal::ZERO,
+        total: amount.parse().unwrap(),
+        locked,
+    };
+    assert_eq!(
+        compare(&old, &new),
+        vec![
+            Difference::Mismatch {
+                old: row(2, "2", false),
+                new: row(2, "2", true),
+            },
+            Difference::MissingInNew(row(3, "1", false)),
+            Difference::MissingInOld(row(4, "0", false)),
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn compare_transactor_with_its_own_output() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(1),
+        tid: TransactionId(1),
+        amount: Some(Currency::from_str("1.2345")?),
+    };
+    transactor.process_transaction(deposit).await?;
+    let mut output = vec![];
+    transactor.write_output(&mut output).await?;
+    let old = read_account_rows(&output[..]).await?;
+    assert_eq!(compare(&old, &transactor.account_rows()), vec![]);
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! This module defines the decompression of `CSV` inputs that are shipped
+//! compressed, e.g. the exports of upstream systems.
+//!
+//! Inputs are decompressed while they're read, so a compressed input never
+//! has to fit in memory or on disk uncompressed. The compression of a file
+//! is detected from its extension, `.gz` for gzip and `.zst` for Zstandard,
+//! unless `Config::compression` says otherwise.
+
+#[cfg(test)]
+mod tests;
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+#[cfg(feature = "async_file_reads")]
+use async_compression::tokio::write;
+use serde_derive::{Deserialize, Serialize};
+#[cfg(feature = "async_file_reads")]
+use std::io;
+use std::path::Path;
+#[cfg(feature = "async_file_reads")]
+use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, BufReader};
+
+/// The compression of a `CSV` input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Compression {
+    Uncompressed,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of the file at `filepath`, going by its extension.
+    pub fn detect(filepath: &Path) -> Self {
+        match filepath
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("gz") => Self::Gzip,
+            Some("zst") => Self::Zstd,
+            _ => Self::Uncompressed,
+        }
+    }
+}
+
+/// A reader of decompressed data.
+pub(crate) type DecompressingReader = Box<dyn AsyncRead + Unpin + Send + Sync>;
+
+/// Wrap `reader` in a reader that decompresses its data.
+pub(crate) fn decompress<R>(reader: R, compression: Compression) -> DecompressingReader
+where
+    R: AsyncRead + Unpin + Send + Sync + 'static,
+{
+    match compression {
+        Compression::Uncompressed => Box::new(reader),
+        Compression::Gzip => Box::new(GzipDecoder::new(BufReader::new(reader))),
+        Compression::Zstd => Box::new(ZstdDecoder::new(BufReader::new(reader))),
+    }
+}
+
+#[cfg(feature = "async_file_reads")]
+/// Decompresses data that is handed to it in chunks, for readers that
+/// don't implement `AsyncRead`, such as those of `tokio-uring`.
+pub(crate) enum ChunkDecoder {
+    Gzip(write::GzipDecoder<Vec<u8>>),
+    Zstd(write::ZstdDecoder<Vec<u8>>),
+}
+
+#[cfg(feature = "async_file_reads")]
+impl ChunkDecoder {
+    /// A decoder for `compression`, if it needs one.
+    pub(crate) fn new(compression: Compression) -> Option<Self> {
+        match compression {
+            Compression::Uncompressed => None,
+            Compression::Gzip => Some(Self::Gzip(write::GzipDecoder::new(vec![]))),
+            Compression::Zstd => Some(Self::Zstd(write::ZstdDecoder::new(vec![]))),
+        }
+    }
+
+    /// Decompress the next `chunk` of compressed data, and append the data
+    /// decompressed so far to `out`.
+    pub(crate) async fn decode(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(chunk).await?;
+                out.append(decoder.get_mut());
+            }
+            Self::Zstd(decoder) => {
+                decoder.write_all(chunk).await?;
+                out.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the rest of the decompressed data to `out`, once all of
+    /// the compressed data was decoded.
+    pub(crate) async fn finish(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Gzip(decoder) => {
+                decoder.shutdown().await?;
+                out.append(decoder.get_mut());
+            }
+            Self::Zstd(decoder) => {
+                decoder.shutdown().await?;
+                out.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use super::*;
+use crate::core::Transactor;
+use crate::error::AppResult;
+#[cfg(not(feature = "async_file_reads"))]
+use crate::test_util::temp::TempPath;
+use async_compression::tokio::bufread::{GzipEncoder, ZstdEncoder};
+use std::path::PathBuf;
+use tokio::io::AsyncReadExt;
+
+const CSV: &str = "type,client,tx,amount
+deposit,1,1,1.0
+deposit,2,2,2.0
+withdrawal,1,3,0.5
+dispute,2,2,
+";
+
+/// Compress `CSV` with `compression`.
+async fn compress(compression: Compression) -> AppResult<Vec<u8>> {
+    let mut compressed = vec![];
+    match compression {
+        Compression::Uncompressed => compressed.extend(CSV.as_bytes()),
+        Compression::Gzip => {
+            let _ = GzipEncoder::new(CSV.as_bytes())
+                .read_to_end(&mut compressed)
+                .await?;
+        }
+        Compression::Zstd => {
+            let _ = ZstdEncoder::new(CSV.as_bytes())
+                .read_to_end(&mut compressed)
+                .await?;
+        }
+    }
+    Ok(compressed)
+}
+
+#[test]
+fn detect_the_compression_from_the_extension() {
+    let detect = |path: &str| Compression::detect(&PathBuf::from(path));
+    assert_eq!(detect("transactions.csv.gz"), Compression::Gzip);
+    assert_eq!(detect("transactions.csv.zst"), Compression::Zstd);
+    assert_eq!(detect("transactions.csv"), Compression::Uncompressed);
+    assert_eq!(detect("-"), Compression::Uncompressed);
+}
+
+#[tokio::test]
+async fn decompress_a_reader() -> AppResult<()> {
+    let mut summaries = vec![];
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let compressed = compress(compression).await?;
+        let mut transactor = Transactor::new();
+        let reader = decompress(std::io::Cursor::new(compressed), compression);
+        transactor.process_csv_reader(reader).await?;
+        summaries.push(transactor.account_summaries().collect::<Vec<_>>());
+    }
+    let mut transactor = Transactor::new();
+    transactor.process_csv_reader(CSV.as_bytes()).await?;
+    let expected = transactor.account_summaries().collect::<Vec<_>>();
+    assert_eq!(summaries, [expected.clone(), expected]);
+    Ok(())
+}
+
+// NOTE: Reading files with `tokio-uring` requires its own runtime.
+#[cfg(not(feature = "async_file_reads"))]
+#[tokio::test]
+async fn read_a_compressed_file_by_its_extension() -> AppResult<()> {
+    let path = TempPath::new("transactions.csv.gz");
+    std::fs::write(&path, compress(Compression::Gzip).await?)?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_file(path.to_path_buf()).await?;
+    assert_eq!(transactor.account_summaries().count(), 2);
+    assert_eq!(transactor.rows_processed, 4);
+    Ok(())
+}
@@ -0,0 +1,446 @@
+//! This module defines the configuration of a `Transactor`.
+
+use crate::compression::Compression;
+use crate::core::{ClientId, Currency, Transaction, TransactionType};
+use crate::error::{AppError, AppResult, TransactionError, TransactionResult};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The knobs that control how a `Transactor` processes transactions.
+/// The `Default` impl yields the behavior of `Transactor::new()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Transactions that take longer than this to process are recorded in
+    /// the slow operations log of the `Transactor`.
+    /// When set to `None`, no transactions are recorded.
+    pub latency_budget: Option<Duration>,
+    /// The order in which transactions are expected to arrive.
+    pub ordering: OrderingPolicy,
+    /// For how many subsequent rows a dispute, resolve or chargeback that
+    /// refers to a transaction that hasn't been seen yet is kept around,
+    /// waiting for that transaction to arrive. `0` rejects them right away.
+    pub dispute_grace_rows: usize,
+    /// What to do with transactions other than deposits for clients that
+    /// don't have an account yet.
+    pub unknown_clients: UnknownClientPolicy,
+    /// Caps on the resources that processing may use.
+    pub limits: Limits,
+    /// Signals other than chargebacks that freeze an account.
+    pub freeze: FreezePolicy,
+    /// When an account frozen by a chargeback is unfrozen again.
+    pub unfreeze: UnfreezePolicy,
+    /// How many decimal places amounts may have.
+    pub amount_scale: AmountScalePolicy,
+    /// How much of the transaction history of each account is kept.
+    pub retention: RetentionPolicy,
+    /// How many rows of a `CSV` input to read ahead, so that the accounts
+    /// they need can be loaded in one batch with `AccountStore::prefetch()`
+    /// before they are processed. `0` disables reading ahead.
+    pub prefetch_rows: usize,
+    /// How many rows of a `CSV` file or `stdin` to parse ahead of
+    /// processing, on a task of its own, so that reading and parsing
+    /// overlap with processing. `0` parses each row on the processing task,
+    /// right before it's processed. With the `async_file_reads` feature,
+    /// files are read by `tokio-uring` instead, and only `stdin` is
+    /// pipelined.
+    pub pipeline_depth: usize,
+    /// How disputes, resolves and chargebacks of withdrawals affect the
+    /// funds of an account.
+    pub dispute_semantics: DisputeSemantics,
+    /// How accounts left with negative funds by a chargeback are repaid.
+    pub repayment: RepaymentPolicy,
+    /// The client ids to replace in the input before processing it.
+    pub client_ids: ClientIdMap,
+    /// Alternative names for the transaction types in the `type` column
+    /// of `CSV` input, e.g. `credit` for deposits, so that the vocabulary
+    /// of a partner can be read without preprocessing it.
+    pub type_aliases: BTreeMap<String, TransactionType>,
+    /// What to do with disputes, resolves and chargebacks of transactions
+    /// that belong to a closed accounting period.
+    pub sealed_transactions: SealedTransactionPolicy,
+    /// Whether to record a statement of every client, for
+    /// `Transactor::statement()`. Off by default, as it keeps a line for
+    /// every transaction that changes an account.
+    pub keep_statements: bool,
+    /// Rules that raise an alert in the metrics when the total funds of an
+    /// account leave a range, e.g. for paging through Alertmanager.
+    pub balance_alerts: Vec<BalanceAlert>,
+    /// Whether to skip the rows of an input that can't be read as a
+    /// transaction, e.g. because of an unknown type, rather than end the
+    /// input with an error. Skipped rows count against the data quality of
+    /// the input.
+    pub skip_malformed_rows: bool,
+    /// The compression of the `CSV` inputs. `None` detects it from the
+    /// extension of each file, and reads `stdin` uncompressed.
+    pub compression: Option<Compression>,
+}
+
+impl Config {
+    /// The compression of the `CSV` file at `filepath`.
+    #[inline]
+    pub fn compression_of(&self, filepath: &Path) -> Compression {
+        self.compression
+            .unwrap_or_else(|| Compression::detect(filepath))
+    }
+}
+
+/// Determines what happens to a dispute, resolve or chargeback that refers
+/// to a transaction sealed by `Transactor::close_period()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SealedTransactionPolicy {
+    /// Reject it with a `TransactionSealed` error.
+    Reject,
+    /// Apply it in the open period, and record it as an `Adjustment` that
+    /// is reported when the open period is closed. The archive of the
+    /// closed period stays as it was.
+    Adjust,
+}
+
+impl Default for SealedTransactionPolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Reject
+    }
+}
+
+/// Maps client ids of the input to the client ids that their accounts are
+/// kept under, e.g. because upstream renumbered its clients between two
+/// extracts. Client ids that aren't mapped are kept as they are.
+///
+/// A client id that isn't mapped, but that another client id is mapped to,
+/// would merge 2 clients into 1 account. Its transactions are rejected with
+/// a `ClientIdCollision` error instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ClientIdMap {
+    mapping: BTreeMap<ClientId, ClientId>,
+    /// The client ids that are mapped to
+    targets: BTreeSet<ClientId>,
+}
+
+impl ClientIdMap {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map the client id `old` to `new`. Fails if `old` is mapped already,
+    /// or if another client id is mapped to `new`.
+    pub fn insert(&mut self, old: ClientId, new: ClientId) -> AppResult<()> {
+        if self.mapping.contains_key(&old) {
+            return Err(AppError::DuplicateClientIdMapping { old });
+        }
+        if !self.targets.insert(new) {
+            return Err(AppError::ClientIdMappingCollision { new });
+        }
+        self.mapping.insert(old, new);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.mapping.is_empty()
+    }
+
+    /// The client id that `cid` maps to.
+    pub fn get(&self, cid: ClientId) -> TransactionResult<ClientId> {
+        match self.mapping.get(&cid) {
+            Some(&new) => Ok(new),
+            None if self.targets.contains(&cid) => Err(TransactionError::ClientIdCollision { cid }),
+            None => Ok(cid),
+        }
+    }
+
+    /// Replace the client id of `t` as this map says.
+    #[inline]
+    pub(crate) fn remap(&self, mut t: Transaction) -> TransactionResult<Transaction> {
+        if !self.is_empty() {
+            t.cid = self.get(t.cid)?;
+        }
+        Ok(t)
+    }
+}
+
+/// Read a client id map file for `Config::client_ids`. The file contains
+/// an `old,new` pair of client ids per line, optionally preceded by an
+/// `old,new` header. Empty lines and `#`-prefixed comments are skipped.
+pub async fn read_client_id_map(filepath: PathBuf) -> AppResult<ClientIdMap> {
+    let contents = tokio::fs::read_to_string(filepath).await?;
+    let mut map = ClientIdMap::new();
+    for (idx, line) in contents.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') || (idx == 0 && line == "old,new") {
+            continue;
+        }
+        let (old, new) =
+            line.split_once(',')
+                .ok_or_else(|| AppError::MalformedClientIdMapping {
+                    line: line.to_string(),
+                })?;
+        let old = ClientId(old.trim().parse()?);
+        let new = ClientId(new.trim().parse()?);
+        map.insert(old, new)?;
+    }
+    Ok(map)
+}
+
+/// Determines how accounts that a chargeback left with negative held funds
+/// are repaid. The amounts that clients still owe are reported by
+/// `Transactor::amounts_owed()` either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RepaymentPolicy {
+    /// Sweep deposits into an account with negative held funds against
+    /// them, so that only what's left of a deposit after repaying the
+    /// shortfall becomes available.
+    pub sweep_deposits: bool,
+}
+
+/// Determines how disputes, resolves and chargebacks affect the funds of
+/// an account, depending on the type of the disputed transaction.
+/// Disputes of deposits are handled the same way either way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DisputeSemantics {
+    /// Handle a disputed withdrawal like a disputed deposit, i.e. a dispute
+    /// moves the withdrawn amount from the available to the held funds.
+    /// This penalizes the client twice, as the amount was already taken
+    /// from the available funds by the withdrawal itself.
+    Legacy,
+    /// A dispute of a withdrawal holds the withdrawn amount, without taking
+    /// it from the available funds once more. A resolve releases the hold,
+    /// after which the withdrawal stands as before the dispute, and
+    /// a chargeback returns the withdrawn amount to the available funds.
+    DirectionAware,
+}
+
+impl Default for DisputeSemantics {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Legacy
+    }
+}
+
+/// Determines how many decimal places the amounts of deposits and
+/// withdrawals may have, as written in the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AmountScalePolicy {
+    /// The maximum number of decimal places. `None` disables the check.
+    pub max_scale: Option<u32>,
+    /// What to do with an amount that has more decimal places.
+    pub on_violation: ScaleViolation,
+}
+
+impl AmountScalePolicy {
+    /// Allow as many decimal places as the account summary has, i.e. 4.
+    #[inline(always)]
+    pub const fn four_decimal_places(on_violation: ScaleViolation) -> Self {
+        Self {
+            max_scale: Some(4),
+            on_violation,
+        }
+    }
+}
+
+impl Default for AmountScalePolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            max_scale: None,
+            on_violation: ScaleViolation::Reject,
+        }
+    }
+}
+
+/// What to do with an amount that has more decimal places than the
+/// `AmountScalePolicy` allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScaleViolation {
+    /// Reject the transaction with an `AmountScaleExceeded` error.
+    Reject,
+    /// Truncate the amount to the allowed number of decimal places, and
+    /// record a `Warning` on the `Transactor`.
+    Truncate,
+    /// Round the amount to the nearest value with the allowed number of
+    /// decimal places, rounding ties to even (i.e. banker's rounding), and
+    /// record a `Warning` on the `Transactor`. The account summary is
+    /// rounded in the same way, rather than truncated.
+    RoundHalfEven,
+}
+
+/// Determines when an account that was frozen by a chargeback is unfrozen
+/// automatically, and which frozen accounts operators may unlock manually.
+/// Accounts frozen by other triggers are only unfrozen manually.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UnfreezePolicy {
+    /// Unfreeze the account once this many rows in a row were processed
+    /// without a transaction for the account being rejected because it is
+    /// frozen. `None` disables this path.
+    pub cooling_off_rows: Option<u64>,
+    /// Unfreeze the account when a resolve arrives for the transaction that
+    /// was charged back. The resolve has no other effect.
+    pub on_matching_resolve: bool,
+    /// Which frozen accounts can be unlocked with
+    /// `Transactor::unlock_account()`.
+    pub manual: ManualUnlockPolicy,
+}
+
+/// Determines which frozen accounts operators may unlock, e.g. after
+/// a manual review.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ManualUnlockPolicy {
+    /// No account can be unlocked manually.
+    Disabled,
+    /// Any frozen account can be unlocked.
+    Allowed,
+    /// Only frozen accounts whose total funds aren't negative can be
+    /// unlocked, i.e. a chargeback must have been made good first.
+    RequireNonNegativeTotal,
+}
+
+impl Default for ManualUnlockPolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Allowed
+    }
+}
+
+/// Signals that freeze an account in addition to a chargeback, which always
+/// does. The first signal that freezes an account is recorded on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FreezePolicy {
+    /// Freeze an account after this many withdrawals in a row were rejected
+    /// for insufficient funds. `None` disables this trigger.
+    pub max_rejected_withdrawals: Option<u32>,
+    /// Freeze an account when a withdrawal brings its total funds below this
+    /// amount. `None` disables this trigger.
+    pub balance_floor: Option<Currency>,
+    /// Clients whose accounts are frozen as soon as they are opened.
+    pub watchlist: BTreeSet<ClientId>,
+}
+
+/// A rule that raises an alert when the total funds of an account drop
+/// below `floor` or rise above `ceiling`. Unlike `FreezePolicy::balance_floor`
+/// it only shows up in the metrics, and leaves the account as it is.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BalanceAlert {
+    /// The name of the rule, used as the `rule` label of its metrics.
+    pub name: String,
+    pub floor: Option<Currency>,
+    pub ceiling: Option<Currency>,
+}
+
+impl BalanceAlert {
+    /// The bound of the rule that `total` is beyond, if any.
+    pub fn breached_bound(&self, total: Currency) -> Option<AlertBound> {
+        if self.floor.is_some_and(|floor| total < floor) {
+            Some(AlertBound::Floor)
+        } else if self.ceiling.is_some_and(|ceiling| total > ceiling) {
+            Some(AlertBound::Ceiling)
+        } else {
+            None
+        }
+    }
+}
+
+/// The bound of a `BalanceAlert` that an account breached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AlertBound {
+    Floor,
+    Ceiling,
+}
+
+impl AlertBound {
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Floor => "floor",
+            Self::Ceiling => "ceiling",
+        }
+    }
+}
+
+/// Read a watchlist file for `FreezePolicy::watchlist`. The file contains one
+/// client id per line. Empty lines and `#`-prefixed comments are skipped.
+pub async fn read_watchlist(filepath: PathBuf) -> AppResult<BTreeSet<ClientId>> {
+    let contents = tokio::fs::read_to_string(filepath).await?;
+    let mut watchlist = BTreeSet::new();
+    for line in contents.lines().map(str::trim) {
+        if !line.is_empty() && !line.starts_with('#') {
+            watchlist.insert(ClientId(line.parse()?));
+        }
+    }
+    Ok(watchlist)
+}
+
+/// Caps on the resources used by a `Transactor`, so that a malformed feed
+/// fails loudly rather than exhausting the memory of the host.
+/// Exceeding any of them aborts processing. `None` means no cap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Limits {
+    /// The maximum number of distinct client accounts.
+    pub max_accounts: Option<usize>,
+    /// The maximum number of open disputes per client account.
+    pub max_open_disputes: Option<usize>,
+    /// The maximum number of transactions stored across all accounts.
+    pub max_resident_transactions: Option<usize>,
+}
+
+/// Determines how much of the transaction history of an account is kept
+/// in memory. Unlike `Limits`, exceeding it isn't an error: the oldest
+/// settled transactions are forgotten instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RetentionPolicy {
+    /// The maximum number of processed, undisputed transactions kept per
+    /// account. Once an account has more, those with the lowest ids are
+    /// evicted, and can no longer be disputed. Disputed and resolved
+    /// transactions are always kept. `None` keeps them all.
+    pub max_settled_per_account: Option<usize>,
+    /// Keep no history at all, and reject every dispute, resolve and
+    /// chargeback with a `DisputesNotSupported` error. For feeds of only
+    /// deposits and withdrawals, this saves the memory of the histories.
+    pub history_free: bool,
+}
+
+/// Determines which orderings of the input transactions are acceptable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OrderingPolicy {
+    /// Transactions may arrive in any order.
+    Any,
+    /// Deposits and withdrawals must arrive in order of increasing
+    /// `TransactionId`. This catches producers that concatenate files
+    /// out of order.
+    MonotonicTid(OrderingViolation),
+}
+
+impl Default for OrderingPolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Any
+    }
+}
+
+/// What to do with a transaction that violates the `OrderingPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OrderingViolation {
+    /// Reject the transaction with a `TransactionIdOutOfOrder` error.
+    Reject,
+    /// Process the transaction, but record a `Warning` on the `Transactor`.
+    Warn,
+}
+
+/// Determines how transactions for clients without an account are handled.
+/// Deposits always open an account for the client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum UnknownClientPolicy {
+    /// Open an empty account for the client, then process the transaction.
+    OpenAccount,
+    /// Reject the transaction with an `UnknownClient` error.
+    Reject,
+}
+
+impl Default for UnknownClientPolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::OpenAccount
+    }
+}
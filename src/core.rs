@@ -3,34 +3,164 @@
 #[cfg(test)]
 mod tests;
 
+use crate::compression::{decompress, Compression};
+use crate::config::{
+    Config, DisputeSemantics, ManualUnlockPolicy, OrderingPolicy, OrderingViolation,
+    ScaleViolation, UnknownClientPolicy,
+};
 use crate::error::{AppError, AppResult, TransactionError, TransactionResult};
-use rust_decimal::prelude::Decimal;
-use serde_derive::Deserialize;
-use std::collections::BTreeMap;
+use crate::index::TransactionIndex;
+use crate::ledger::StatementLine;
+use crate::legacy::LegacySink;
+use crate::metrics::Metrics;
+use crate::partition::ShardAssignment;
+use crate::period::Adjustment;
+use crate::quality::{DataQuality, QualityTracker};
+use crate::run_log::RunLog;
+use crate::store::AccountStore;
+use rust_decimal::prelude::{Decimal, RoundingStrategy};
+use serde::de::{self, Deserializer, Visitor};
+use serde::Serializer;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::ops::Bound;
 use std::path::PathBuf;
-use tokio_stream::StreamExt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
 
-#[cfg(not(feature = "async_file_reads"))]
-use csv_async::AsyncReaderBuilder;
+use csv_async::{AsyncReaderBuilder, AsyncWriterBuilder, StringRecord};
 #[cfg(feature = "async_file_reads")]
 use {
+    crate::compression::ChunkDecoder,
     async_stream::{stream, AsyncStream},
     std::future::Future,
 };
 
+/// The header row of the account summary.
+pub(crate) const OUTPUT_HEADER: [&str; 5] = ["client", "available", "held", "total", "locked"];
+
+/// The columns of a `CSV` input of transactions.
+#[cfg(feature = "async_file_reads")]
+const CSV_HEADERS: [&str; 4] = ["type", "client", "tx", "amount"];
+
 /// An instance of this type acts as a transaction engine.
 /// It is fed CSV files, which are read and processed asynchronously.
-#[derive(Debug, Deserialize)]
-pub struct Transactor {
-    pub(crate) accounts: BTreeMap<ClientId, Account>,
+/// The client accounts are kept in an `AccountStore` of type `S`.
+///
+/// Administrative operations such as `unlock_account()` and
+/// `void_transaction()` borrow the `Transactor` mutably, as does processing
+/// a row, so they are fenced off from each other by the borrow checker:
+/// a row observes an account either fully before or fully after an
+/// administrative operation. A `Transactor` shared between a stream and an
+/// admin task is best wrapped in a `tokio::sync::Mutex` that is locked once
+/// per row, to keep that guarantee.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Transactor<S = BTreeMap<ClientId, Account>> {
+    pub(crate) accounts: S,
+    #[serde(skip)]
+    pub(crate) config: Config,
+    /// Transactions whose processing exceeded `config.latency_budget`
+    #[serde(skip)]
+    pub(crate) slow_operations: Vec<SlowOperation>,
+    #[serde(skip)]
+    pub(crate) warnings: Vec<Warning>,
+    /// The number of rows handed to `process_row()` so far
+    #[serde(default)]
+    pub(crate) rows_processed: u64,
+    /// Transactions waiting for the transaction they refer to to arrive
+    #[serde(default)]
+    pub(crate) parked_transactions: Vec<ParkedTransaction>,
+    /// The highest `TransactionId` of any deposit or withdrawal seen so far
+    #[serde(default)]
+    pub(crate) max_tid: Option<TransactionId>,
+    /// The number of transactions stored in the histories of all accounts
+    #[serde(default)]
+    pub(crate) resident_transactions: usize,
+    /// Per-client counters of how the client's transactions were handled
+    #[serde(skip)]
+    pub(crate) client_stats: BTreeMap<ClientId, ClientStats>,
+    /// Where accepted transactions are mirrored to during a migration
+    #[serde(skip)]
+    pub(crate) legacy_sink: Option<LegacySink>,
+    /// Freezes and unfreezes of accounts, in the order in which they happened
+    #[serde(skip)]
+    pub(crate) account_events: Vec<AccountEvent>,
+    /// The row number at which each account frozen by a chargeback is
+    /// unfrozen, if the `UnfreezePolicy` has a cooling-off period
+    #[serde(default)]
+    pub(crate) cooling_off: BTreeMap<ClientId, u64>,
+    /// Per-transaction-type counters and latencies, and dispute durations
+    #[serde(skip)]
+    pub(crate) metrics: Metrics,
+    /// Where every processed row is logged to, if anywhere
+    #[serde(skip)]
+    pub(crate) run_log: Option<RunLog>,
+    /// The rows that were rejected, along with the reason why
+    #[serde(skip)]
+    pub(crate) ignored_transactions: Vec<IgnoredTransaction>,
+    /// The number of accounting periods closed so far
+    #[serde(default)]
+    pub(crate) periods_closed: u32,
+    /// Changes to transactions of closed periods since the last close
+    #[serde(default)]
+    pub(crate) adjustments: Vec<Adjustment>,
+    /// The statement of each client, if statements are kept
+    #[serde(default)]
+    pub(crate) statements: BTreeMap<ClientId, Vec<StatementLine>>,
+    /// The data quality of each input processed so far
+    #[serde(skip)]
+    pub(crate) data_quality: Vec<DataQuality>,
+    /// Which shard owns each client in sharded processing, if not the
+    /// `HashAssignment`
+    #[serde(skip)]
+    pub(crate) shard_assignment: Option<Box<dyn ShardAssignment>>,
+    /// The secondary indexes over the histories, if they are kept
+    #[serde(skip)]
+    pub(crate) index: Option<TransactionIndex>,
 }
 
 impl Transactor {
     #[inline(always)]
     pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    #[inline(always)]
+    pub fn with_config(config: Config) -> Self {
+        Self::with_store(config, BTreeMap::new())
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Create a `Transactor` that keeps the client accounts in `accounts`.
+    #[inline(always)]
+    pub fn with_store(config: Config, accounts: S) -> Self {
         Self {
-            accounts: BTreeMap::new(),
+            accounts,
+            config,
+            slow_operations: vec![],
+            warnings: vec![],
+            rows_processed: 0,
+            parked_transactions: vec![],
+            max_tid: None,
+            resident_transactions: 0,
+            client_stats: BTreeMap::new(),
+            legacy_sink: None,
+            account_events: vec![],
+            cooling_off: BTreeMap::new(),
+            metrics: Metrics::default(),
+            run_log: None,
+            ignored_transactions: vec![],
+            periods_closed: 0,
+            adjustments: vec![],
+            statements: BTreeMap::new(),
+            data_quality: vec![],
+            shard_assignment: None,
+            index: None,
         }
     }
 
@@ -41,30 +171,88 @@ impl Transactor {
     /// It is assumed that the last transaction in one `CSV` file is ordered
     /// in time strictly before the first item of the next CSV file.
     pub async fn process_csv_file(&mut self, filepath: PathBuf) -> AppResult<()> {
-        let file = tokio::fs::File::open(filepath).await?;
-        let reader = AsyncReaderBuilder::new()
-            .trim(csv_async::Trim::All) // Allow nicely aligned columns
-            .flexible(true) // Allow rows of type dispute, resolve & chargeback
-            .comment(Some(b'#')) // Allow #-prefixed line comments
-            .create_deserializer(file);
-        let mut transactions_stream: csv_async::DeserializeRecordsIntoStream<_, _> =
-            reader.into_deserialize::<Transaction>();
-        while let Some(csv_async_result) = transactions_stream.next().await {
-            let transaction: Transaction = csv_async_result?;
-            let result = self.process_transaction(transaction).await;
-            if let Err(_transaction_error) = result {
-                // NOTE: The transaction failed. To prevent producing
-                //       undesirable output, for now both the error
-                //       and the transaction itself are ignored.
-                //       This would be inadvisable in a real-world system,
-                //       of course, and this note would be replaced by
-                //       error handling code and logging.
-                // return Err(_transaction_error);
-            }
+        let file = tokio::fs::File::open(&filepath).await?;
+        let reader = decompress(file, self.config.compression_of(&filepath));
+        let source = filepath.display().to_string();
+        self.process_pipelined_csv_reader(reader, source).await
+    }
+
+    /// Process the transactions in the `CSV` files at `filepaths` one file
+    /// after the other, accumulating the account state across all of them.
+    pub async fn process_csv_files(&mut self, filepaths: &[PathBuf]) -> AppResult<()> {
+        for filepath in filepaths {
+            self.process_csv_file(filepath.clone()).await?;
         }
         Ok(())
     }
 
+    /// Read `CSV` data from `stdin`, and process the transactions in it
+    /// in the same way as those in a `CSV` file.
+    pub async fn process_csv_stdin(&mut self) -> AppResult<()> {
+        let compression = self.config.compression.unwrap_or(Compression::Uncompressed);
+        let reader = decompress(tokio::io::stdin(), compression);
+        self.process_pipelined_csv_reader(reader, "-".to_string())
+            .await
+    }
+
+    /// Asynchronously read, deserialize and process the transactions in
+    /// the `CSV` data produced by `reader`.
+    #[inline(always)]
+    pub async fn process_csv_reader<R>(&mut self, reader: R) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync,
+    {
+        self.process_named_csv_reader(reader, "reader".to_string())
+            .await
+    }
+
+    /// Process the `CSV` data produced by `reader`, whose data quality is
+    /// reported under the name `source`.
+    pub(crate) async fn process_named_csv_reader<R>(
+        &mut self,
+        reader: R,
+        source: String,
+    ) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync,
+    {
+        let aliases = self.config.type_aliases.clone();
+        let rows = read_csv_rows(reader, aliases).await?;
+        self.process_numbered_stream(rows, source).await
+    }
+
+    /// Process the `CSV` data produced by `reader` like
+    /// `process_named_csv_reader()`. If `config.pipeline_depth` isn't `0`,
+    /// the rows are parsed by a task of their own, up to that many rows
+    /// ahead of processing.
+    async fn process_pipelined_csv_reader<R>(&mut self, reader: R, source: String) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync + 'static,
+    {
+        let depth = self.config.pipeline_depth;
+        if depth == 0 {
+            return self.process_named_csv_reader(reader, source).await;
+        }
+        let aliases = self.config.type_aliases.clone();
+        let rows = read_csv_rows(reader, aliases).await?;
+        let (sender, receiver) = mpsc::channel(depth);
+        let parser = tokio::spawn(async move {
+            tokio::pin!(rows);
+            while let Some(row) = rows.next().await {
+                if sender.send(row).await.is_err() {
+                    // NOTE: Processing ended early because of a fatal error.
+                    break;
+                }
+            }
+        });
+        let rows = ReceiverStream::new(receiver);
+        let result = self.process_numbered_stream(rows, source).await;
+        // NOTE: If the parser panicked, the rows it didn't parse would be
+        //       missing without a trace.
+        parser.await?;
+        result
+    }
+
     #[cfg(feature = "async_file_reads")]
     /// Asynchronously read, deserialize and process the transactions
     /// in a `CSV` file to an async Stream using `tokio-uring` (which in turn
@@ -76,81 +264,698 @@ impl Transactor {
     /// It is assumed that the last transaction in one `CSV` file is ordered
     /// in time strictly before the first item of the next CSV file.
     pub async fn process_csv_file(&mut self, filepath: PathBuf) -> AppResult<()> {
-        let transaction_results: AsyncStream<AppResult<Transaction>, _> =
-            Transaction::stream_from_csv_file(filepath).await?;
-        tokio::pin!(transaction_results);
-        while let Some(transaction_result) = transaction_results.next().await {
-            let transaction: Transaction = transaction_result?;
-            let result = self.process_transaction(transaction).await;
-            if let Err(_transaction_error) = result {
-                // NOTE: The transaction failed. To prevent producing
-                //       undesirable output, for now both the error
-                //       and the transaction itself are ignored.
-                //       This would be inadvisable in a real-world system,
-                //       of course, and this note would be replaced by
-                //       error handling code and logging.
-                // return Err(_transaction_error);
+        let source = filepath.display().to_string();
+        let compression = self.config.compression_of(&filepath);
+        let aliases = self.config.type_aliases.clone();
+        let transaction_results: AsyncStream<AppResult<(u64, Transaction)>, _> =
+            Transaction::stream_from_csv_file(filepath, compression, aliases).await?;
+        let rows = transaction_results.map(|row| row.map(|(line, t)| (Some(line), t)));
+        self.process_numbered_stream(rows, source).await
+    }
+
+    /// Process the transactions produced by `stream`, e.g. from a network
+    /// connection, a message queue or a generator, in the same way as the
+    /// rows of a `CSV` input. Rejected transactions are kept, as with
+    /// `process_row()`. The first `Err` in the stream ends processing and
+    /// is returned, after the transactions before it have been processed.
+    pub async fn process_stream<St, E>(&mut self, stream: St) -> AppResult<()>
+    where
+        St: Stream<Item = Result<Transaction, E>>,
+        E: Into<AppError>,
+    {
+        let rows = stream.map(|row| row.map(|t| (None, t)).map_err(Into::into));
+        self.process_numbered_stream(rows, "stream".to_string())
+            .await
+    }
+
+    /// Process a stream of transactions, along with the line numbers of the
+    /// input they were read from, if known. This is what all inputs other
+    /// than single rows are processed with. The data quality of the input
+    /// is recorded under the name `source`, even if processing fails.
+    async fn process_numbered_stream<St>(&mut self, rows: St, source: String) -> AppResult<()>
+    where
+        St: Stream<Item = AppResult<(Option<u64>, Transaction)>>,
+    {
+        let mut quality = QualityTracker::new(source);
+        let result = self.process_tracked_stream(rows, &mut quality).await;
+        self.record_data_quality(quality.finish());
+        result
+    }
+
+    async fn process_tracked_stream<St>(
+        &mut self,
+        rows: St,
+        quality: &mut QualityTracker,
+    ) -> AppResult<()>
+    where
+        St: Stream<Item = AppResult<(Option<u64>, Transaction)>>,
+    {
+        tokio::pin!(rows);
+        let mut batch = Vec::with_capacity(self.config.prefetch_rows.max(1));
+        while let Some(row) = rows.next().await {
+            quality.observe(&row);
+            match row {
+                Ok(row) => batch.push(row),
+                Err(e) if e.is_row_error() && self.config.skip_malformed_rows => continue,
+                Err(e) => {
+                    // NOTE: Process the rows before the bad one first,
+                    //       as if there were no batch at all.
+                    self.process_csv_batch(&mut batch).await?;
+                    return Err(e);
+                }
+            }
+            if batch.len() >= self.config.prefetch_rows {
+                self.process_csv_batch(&mut batch).await?;
             }
         }
+        self.process_csv_batch(&mut batch).await?;
+        self.expire_parked_transactions(None);
         Ok(())
     }
 
-    #[rustfmt::skip]
-    /// Process a single transaction.
-    pub(crate) async fn process_transaction(
+    /// Process and clear a `batch` of transactions, along with their line
+    /// numbers. If reading ahead is enabled, the accounts of the batch are
+    /// prefetched first.
+    async fn process_csv_batch(
+        &mut self,
+        batch: &mut Vec<(Option<u64>, Transaction)>,
+    ) -> AppResult<()> {
+        if self.config.prefetch_rows > 0 && !batch.is_empty() {
+            let client_ids = &self.config.client_ids;
+            let cids: BTreeSet<ClientId> = batch
+                .iter()
+                .filter_map(|(_, t)| client_ids.get(t.cid).ok())
+                .collect();
+            self.accounts.prefetch(&cids);
+        }
+        for (line, transaction) in batch.drain(..) {
+            self.process_csv_row(transaction, line).await?;
+        }
+        Ok(())
+    }
+
+    /// Process the transaction `t` read from line number `line` of an
+    /// input, if known. Only fatal errors are returned, along with the line
+    /// number.
+    pub(crate) async fn process_csv_row(
         &mut self,
-        t: Transaction
+        t: Transaction,
+        line: Option<u64>,
+    ) -> AppResult<()> {
+        match self.process_row_at(t, line).await {
+            Ok(()) => Ok(()),
+            Err(error) if error.is_fatal() => Err(match line {
+                Some(line) => AppError::TransactionErrorAtLine { line, error },
+                None => AppError::TransactionError(error),
+            }),
+            Err(_transaction_error) => {
+                // NOTE: The transaction failed. To prevent producing
+                //       undesirable output, processing continues with the
+                //       next row. The transaction and the error, including
+                //       the line number, are kept as an `IgnoredTransaction`.
+                Ok(())
+            }
+        }
+    }
+
+    /// Process a single transaction read from an input stream.
+    ///
+    /// Disputes, resolves and chargebacks that refer to a transaction that
+    /// hasn't been seen yet are parked rather than rejected, for up to
+    /// `config.dispute_grace_rows` subsequent rows. Each time a transaction
+    /// is successfully processed for a client, that client's parked
+    /// transactions are retried.
+    #[inline(always)]
+    pub async fn process_row(&mut self, t: Transaction) -> TransactionResult<()> {
+        self.process_row_at(t, None).await
+    }
+
+    /// Process a single transaction like `process_row()`, which was read
+    /// from line number `line` of the input, if known.
+    pub(crate) async fn process_row_at(
+        &mut self,
+        t: Transaction,
+        line: Option<u64>,
     ) -> TransactionResult<()> {
-        match t.ttype {
-            TransactionType::Deposit    => self.deposit(&t).await,
-            TransactionType::Withdrawal => self.withdraw(&t).await,
-            TransactionType::Dispute    => self.dispute(&t).await,
-            TransactionType::Resolve    => self.resolve(&t).await,
-            TransactionType::Chargeback => self.chargeback(&t).await,
+        self.rows_processed += 1;
+        let grace_rows = self.config.dispute_grace_rows;
+        let mut parked = false;
+        // NOTE: Client ids are remapped exactly once, before anything else
+        //       sees the transaction, so parked ones aren't remapped again.
+        let (t, result) = match self.config.client_ids.remap(t) {
+            Ok(remapped) => (remapped, self.process_transaction(remapped).await),
+            Err(e) => (t, Err(e)),
+        };
+        let result = match result {
+            Err(e) if grace_rows > 0 && e.refers_to_unknown_transaction() => {
+                self.parked_transactions.push(ParkedTransaction {
+                    transaction: t,
+                    line,
+                    deadline: self.rows_processed + grace_rows as u64,
+                    error: e,
+                });
+                parked = true;
+                Ok(())
+            }
+            Ok(()) => {
+                self.record_outcome(&t, line, &Ok(()));
+                self.retry_parked_transactions(t.cid).await;
+                Ok(())
+            }
+            Err(e) => {
+                self.record_outcome(&t, line, &Err(e.clone()));
+                Err(e)
+            }
+        };
+        if let Some(run_log) = self.run_log.as_mut() {
+            let outcome = match &result {
+                Ok(()) if parked => "parked",
+                Ok(()) => "accepted",
+                Err(e) => e.reason(),
+            };
+            let rounding = self.config.amount_scale.on_violation;
+            let account = self.accounts.get(&t.cid);
+            run_log.emit(self.rows_processed, &t, outcome, account, rounding);
         }
+        self.expire_parked_transactions(Some(self.rows_processed));
+        self.end_cooling_off_periods();
+        result
+    }
+
+    /// Retry the parked transactions of the client with id `cid`.
+    async fn retry_parked_transactions(&mut self, cid: ClientId) {
+        let mut idx = 0;
+        while idx < self.parked_transactions.len() {
+            let ParkedTransaction {
+                transaction: parked,
+                line,
+                ..
+            } = self.parked_transactions[idx];
+            if parked.cid != cid {
+                idx += 1;
+                continue;
+            }
+            match self.process_transaction(parked).await {
+                Err(e) if e.refers_to_unknown_transaction() => idx += 1,
+                result => {
+                    self.record_outcome(&parked, line, &result);
+                    // NOTE: The parked transaction is done with, one way
+                    //       or another. If it was processed successfully,
+                    //       it may have unblocked one of the transactions
+                    //       before it, so start over.
+                    let _ = self.parked_transactions.remove(idx);
+                    idx = 0;
+                }
+            }
+        }
+    }
+
+    /// Drop the parked transactions whose deadline is at or before row
+    /// number `row`, or all of them if `row` is `None`.
+    pub(crate) fn expire_parked_transactions(&mut self, row: Option<u64>) {
+        let (expired, parked): (Vec<_>, Vec<_>) = self
+            .parked_transactions
+            .drain(..)
+            .partition(|parked| row.is_none_or(|row| parked.deadline <= row));
+        self.parked_transactions = parked;
+        for ParkedTransaction {
+            transaction,
+            line,
+            error,
+            ..
+        } in expired
+        {
+            self.record_outcome(&transaction, line, &Err(error));
+        }
+    }
+
+    /// Process a single transaction immediately. Unlike `process_row()`,
+    /// this neither parks the transaction nor updates the client statistics.
+    pub async fn process_transaction(&mut self, t: Transaction) -> TransactionResult<()> {
+        let start = Instant::now();
+        let was_locked = self.accounts.get(&t.cid).is_some_and(|a| a.is_locked);
+        let result = self.apply_transaction(&t).await;
+        self.track_freezes(t.cid, was_locked, &result);
+        let elapsed = start.elapsed();
+        self.metrics.record(&t, elapsed, &result);
+        if result.is_ok() && !self.config.balance_alerts.is_empty() {
+            if let Some(account) = self.accounts.get(&t.cid) {
+                let rules = &self.config.balance_alerts;
+                self.metrics
+                    .check_balance_alerts(rules, t.cid, account.total);
+            }
+        }
+        if let Some(budget) = self.config.latency_budget {
+            if elapsed > budget {
+                self.record_slow_operation(t, elapsed, &result);
+            }
+        }
+        result
+    }
+
+    #[rustfmt::skip]
+    /// Apply a single transaction to the account it belongs to.
+    async fn apply_transaction(&mut self, t: &Transaction) -> TransactionResult<()> {
+        let t = &self.ensure_amount_scale(*t)?;
+        self.ensure_disputes_supported(t)?;
+        self.ensure_transaction_order(t).await?;
+        if t.ttype == TransactionType::Resolve && self.unfreeze_on_matching_resolve(t) {
+            return Ok(());
+        }
+        let is_adjustment = self.ensure_not_sealed(t)?;
+        let result = match t.ttype {
+            TransactionType::Deposit    => self.deposit(t).await,
+            TransactionType::Withdrawal => self.withdraw(t).await,
+            TransactionType::Dispute    => self.dispute(t).await,
+            TransactionType::Resolve    => self.resolve(t).await,
+            TransactionType::Chargeback => self.chargeback(t).await,
+        };
+        if is_adjustment && result.is_ok() {
+            let row = self.rows_processed;
+            self.adjustments.push(Adjustment { row, transaction: *t });
+        }
+        if result.is_ok() {
+            self.record_statement_line(t, false);
+        }
+        result
+    }
+
+    /// Log the freeze of the account of client `cid` if processing one of
+    /// its transactions froze it, and keep track of its cooling-off period.
+    fn track_freezes(&mut self, cid: ClientId, was_locked: bool, result: &TransactionResult<()>) {
+        let account = match self.accounts.get(&cid) {
+            Some(account) => account,
+            None => return,
+        };
+        if !was_locked && account.is_locked {
+            if let Some(trigger) = account.freeze_trigger.clone() {
+                let is_chargeback = matches!(trigger, FreezeTrigger::Chargeback { .. });
+                self.account_events.push(AccountEvent {
+                    row: self.rows_processed,
+                    cid,
+                    change: AccountChange::Frozen(trigger),
+                });
+                if let (true, Some(rows)) = (is_chargeback, self.config.unfreeze.cooling_off_rows) {
+                    self.cooling_off.insert(cid, self.rows_processed + rows);
+                }
+            }
+        } else if let Err(TransactionError::AccountIsLocked { .. }) = result {
+            if let Some(deadline) = self.cooling_off.get_mut(&cid) {
+                // NOTE: The account is still in use, so the period restarts
+                *deadline =
+                    self.rows_processed + self.config.unfreeze.cooling_off_rows.unwrap_or(0);
+            }
+        }
+    }
+
+    /// Unfreeze the accounts whose cooling-off period has ended.
+    pub(crate) fn end_cooling_off_periods(&mut self) {
+        let row = self.rows_processed;
+        let ended: Vec<ClientId> = self
+            .cooling_off
+            .iter()
+            .filter(|(_, &deadline)| deadline <= row)
+            .map(|(&cid, _)| cid)
+            .collect();
+        for cid in ended {
+            self.unfreeze(cid, UnfreezeReason::CoolingOff);
+        }
+    }
+
+    /// If the `UnfreezePolicy` allows it and the resolve `t` refers to the
+    /// chargeback that froze its account, unfreeze the account. Returns
+    /// `true` if the account was unfrozen.
+    fn unfreeze_on_matching_resolve(&mut self, t: &Transaction) -> bool {
+        if !self.config.unfreeze.on_matching_resolve {
+            return false;
+        }
+        let trigger = self
+            .accounts
+            .get(&t.cid)
+            .and_then(|a| a.freeze_trigger.as_ref());
+        if trigger != Some(&FreezeTrigger::Chargeback { tid: t.tid }) {
+            return false;
+        }
+        self.unfreeze(t.cid, UnfreezeReason::MatchingResolve { tid: t.tid });
+        true
+    }
+
+    /// Unfreeze the account of client `cid` and log it, if it is frozen.
+    fn unfreeze(&mut self, cid: ClientId, reason: UnfreezeReason) {
+        let _ = self.cooling_off.remove(&cid);
+        if let Some(account) = self.accounts.get_mut(&cid) {
+            if account.is_locked {
+                account.unfreeze();
+                self.account_events.push(AccountEvent {
+                    row: self.rows_processed,
+                    cid,
+                    change: AccountChange::Unfrozen(reason),
+                });
+            }
+        }
+    }
+
+    /// Unlock the frozen account of the client with id `cid`, e.g. after
+    /// an operator reviewed it, as far as the `ManualUnlockPolicy` permits.
+    /// The unlock is logged like any other unfreeze.
+    pub fn unlock_account(&mut self, cid: ClientId) -> TransactionResult<()> {
+        let account = self
+            .accounts
+            .get(&cid)
+            .ok_or(TransactionError::UnknownClient { cid })?;
+        if !account.is_locked {
+            return Err(TransactionError::AccountIsNotLocked { cid });
+        }
+        let permitted = match self.config.unfreeze.manual {
+            ManualUnlockPolicy::Disabled => false,
+            ManualUnlockPolicy::Allowed => true,
+            ManualUnlockPolicy::RequireNonNegativeTotal => account.total >= Currency::ZERO,
+        };
+        if !permitted {
+            return Err(TransactionError::UnlockNotPermitted { cid });
+        }
+        self.unfreeze(cid, UnfreezeReason::Manual);
+        Ok(())
+    }
+
+    /// The freezes and unfreezes of accounts, in the order in which they
+    /// happened.
+    pub fn account_events(&self) -> &[AccountEvent] {
+        &self.account_events
+    }
+
+    /// Add a transaction that took `elapsed` time to process to the
+    /// slow operations log, along with the state of its client account.
+    fn record_slow_operation(
+        &mut self,
+        transaction: Transaction,
+        elapsed: Duration,
+        result: &TransactionResult<()>,
+    ) {
+        let account = self.accounts.get(&transaction.cid);
+        let history_len = account.map_or(0, |account| {
+            account.histories().iter().map(|(_, h)| h.len()).sum()
+        });
+        let open_disputes = account.map_or(0, |account| account.disputed_transactions.len());
+        self.slow_operations.push(SlowOperation {
+            transaction,
+            elapsed,
+            error: result.clone().err(),
+            history_len,
+            open_disputes,
+        });
+    }
+
+    /// Update the statistics of the client that transaction `t` belongs to
+    /// with the final `result` of processing it. Mirror the transaction to
+    /// the legacy sink if it was accepted, or keep it if it was rejected.
+    fn record_outcome(
+        &mut self,
+        t: &Transaction,
+        line: Option<u64>,
+        result: &TransactionResult<()>,
+    ) {
+        let stats = self.client_stats.entry(t.cid).or_default();
+        *stats.by_type.entry(t.ttype.name()).or_default() += 1;
+        match result {
+            Ok(()) => {
+                if let Some(sink) = self.legacy_sink.as_mut() {
+                    sink.emit(t);
+                }
+                stats.accepted += 1;
+                match t.ttype {
+                    TransactionType::Dispute => stats.disputes_opened += 1,
+                    TransactionType::Resolve => stats.disputes_resolved += 1,
+                    TransactionType::Chargeback => stats.disputes_charged_back += 1,
+                    _ => {}
+                }
+            }
+            Err(e) => {
+                *stats.rejected.entry(e.reason()).or_default() += 1;
+                self.ignored_transactions.push(IgnoredTransaction {
+                    transaction: *t,
+                    line,
+                    reason: e.clone(),
+                });
+            }
+        }
+    }
+
+    /// The rows that were rejected so far, in the order in which they
+    /// were rejected, so that callers can audit what was skipped.
+    pub fn rejected(&self) -> impl Iterator<Item = &IgnoredTransaction> {
+        self.ignored_transactions.iter()
+    }
+
+    /// The processing statistics of the client with id `cid`, or `None` if
+    /// no transactions were processed for that client.
+    pub fn client_stats(&self, cid: ClientId) -> Option<&ClientStats> {
+        self.client_stats.get(&cid)
+    }
+
+    /// The clients whose accounts are frozen, along with what froze them.
+    pub fn frozen_accounts(&self) -> Vec<(ClientId, Option<&FreezeTrigger>)> {
+        self.accounts
+            .values()
+            .filter(|account| account.is_locked)
+            .map(|account| (account.id, account.freeze_trigger.as_ref()))
+            .collect()
+    }
+
+    /// The accounts that were left with negative held or total funds, e.g.
+    /// by a chargeback, along with how much their clients owe.
+    pub fn amounts_owed(&self) -> Vec<AmountOwed> {
+        self.accounts
+            .values()
+            .filter_map(|account| {
+                let owed = -account.held.min(account.total);
+                if owed <= Currency::ZERO {
+                    return None;
+                }
+                Some(AmountOwed {
+                    client: account.id,
+                    held: account.held,
+                    total: account.total,
+                    owed,
+                    repaid: account.repaid,
+                })
+            })
+            .collect()
+    }
+
+    /// Write the amounts owed to the file at `filepath` as `CSV`, in
+    /// `client,held,total,owed,repaid` form.
+    pub async fn write_owed_report(&self, filepath: PathBuf) -> AppResult<()> {
+        let mut csv = String::from("client,held,total,owed,repaid\n");
+        for owed in self.amounts_owed() {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                owed.client,
+                owed.held.to_output_string(),
+                owed.total.to_output_string(),
+                owed.owed.to_output_string(),
+                owed.repaid.to_output_string(),
+            ));
+        }
+        tokio::fs::write(filepath, csv).await?;
+        Ok(())
+    }
+
+    /// Write the frozen accounts to the file at `filepath` as `CSV`, in
+    /// `client,trigger,detail` form.
+    pub async fn write_freeze_report(&self, filepath: PathBuf) -> AppResult<()> {
+        let mut csv = String::from("client,trigger,detail\n");
+        for (cid, trigger) in self.frozen_accounts() {
+            let (trigger, detail) = match trigger {
+                Some(FreezeTrigger::Chargeback { tid }) => ("chargeback", tid.to_string()),
+                Some(FreezeTrigger::RejectedWithdrawals { count }) => {
+                    ("rejected_withdrawals", count.to_string())
+                }
+                Some(FreezeTrigger::BalanceBelowFloor { floor }) => {
+                    ("balance_below_floor", floor.to_output_string())
+                }
+                Some(FreezeTrigger::Watchlist) => ("watchlist", String::new()),
+                None => ("unknown", String::new()),
+            };
+            csv.push_str(&format!("{},{},{}\n", cid, trigger, detail));
+        }
+        tokio::fs::write(filepath, csv).await?;
+        Ok(())
+    }
+
+    /// Write the processing statistics of all clients to the file at
+    /// `filepath` as `CSV`, in `client,statistic,count` form.
+    /// Each rejection reason gets its own `rejected:<reason>` statistic.
+    pub async fn write_client_stats(&self, filepath: PathBuf) -> AppResult<()> {
+        let mut csv = String::from("client,statistic,count\n");
+        for (cid, stats) in self.client_stats.iter() {
+            let counters = [
+                ("accepted", stats.accepted),
+                ("disputes_opened", stats.disputes_opened),
+                ("disputes_resolved", stats.disputes_resolved),
+                ("disputes_charged_back", stats.disputes_charged_back),
+            ];
+            for (statistic, count) in counters.iter() {
+                csv.push_str(&format!("{},{},{}\n", cid, statistic, count));
+            }
+            for (reason, count) in stats.rejected.iter() {
+                csv.push_str(&format!("{},rejected:{},{}\n", cid, reason, count));
+            }
+        }
+        tokio::fs::write(filepath, csv).await?;
+        Ok(())
+    }
+
+    /// The warnings raised while processing transactions, in the order in
+    /// which they were raised.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The transactions whose processing exceeded the configured latency
+    /// budget, in the order in which they were processed.
+    pub fn slow_operations(&self) -> &[SlowOperation] {
+        &self.slow_operations
     }
 
     /// Handle a deposit transaction.
     async fn deposit(&mut self, t: &Transaction) -> TransactionResult<()> {
+        self.ensure_resident_transactions_limit().await?;
+        // NOTE: Deposits open accounts regardless of the `UnknownClientPolicy`
+        self.ensure_client_account_exists(t.cid).await?;
+        let sweep_deposits = self.config.repayment.sweep_deposits;
+        let history_free = self.config.retention.history_free;
         let account = self.account_mut(t.cid).await?;
+        Self::ensure_transaction_id_not_disputed(account, t.tid).await?;
         let amount = t.amount.ok_or(TransactionError::MalformedInputData)?;
-        account.available = account.available + amount;
-        account.total = account.total + amount;
-        Self::ensure_account_balance_invariant(&account).await?;
-        account.processed_transactions.insert(t.tid, *t);
+        let shortfall = -account.held;
+        let swept = if sweep_deposits && shortfall > Currency::ZERO && amount > Currency::ZERO {
+            amount.min(shortfall)
+        } else {
+            Currency::ZERO
+        };
+        let repaid = account
+            .repaid
+            .checked_add(swept)
+            .ok_or(TransactionError::AmountOverflow { cid: t.cid })?;
+        account.adjust_balances(amount - swept, swept)?;
+        account.repaid = repaid;
+        account.has_applied_transactions = true;
+        Self::ensure_account_balance_invariant(account).await?;
+        if !history_free {
+            let replaced = account.processed_transactions.insert(t.tid, *t);
+            self.track_processed_transaction(t, replaced);
+        }
         Ok(())
     }
 
+    /// Account for the processed transaction `t` that was just stored in the
+    /// history of its account, in place of the `replaced` one if any.
+    /// NOTE: Only a transaction with a new id adds to the resident ones.
+    fn track_processed_transaction(&mut self, t: &Transaction, replaced: Option<Transaction>) {
+        match replaced {
+            Some(replaced) => {
+                if let Some(index) = &mut self.index {
+                    index.remove(&replaced);
+                }
+            }
+            None => self.resident_transactions += 1,
+        }
+        self.index_transaction(t, TransactionState::Processed);
+        self.apply_retention_policy(t.cid);
+    }
+
+    /// Evict the oldest settled transactions of the account of the client
+    /// with id `cid`, as the `RetentionPolicy` says.
+    fn apply_retention_policy(&mut self, cid: ClientId) {
+        let keep = match self.config.retention.max_settled_per_account {
+            Some(keep) => keep,
+            None => return,
+        };
+        if let Some(account) = self.accounts.get_mut(&cid) {
+            let evicted = account.evict_settled_transactions(keep);
+            debug_assert!(
+                evicted.len() <= self.resident_transactions,
+                "evicted more transactions than are resident"
+            );
+            self.resident_transactions = self.resident_transactions.saturating_sub(evicted.len());
+            if let Some(index) = &mut self.index {
+                evicted.iter().for_each(|t| index.remove(t));
+            }
+        }
+    }
+
     /// Handle a withdrawal transaction.
     async fn withdraw(&mut self, t: &Transaction) -> TransactionResult<()> {
+        self.ensure_resident_transactions_limit().await?;
+        let max_rejected = self.config.freeze.max_rejected_withdrawals;
+        let balance_floor = self.config.freeze.balance_floor;
+        let history_free = self.config.retention.history_free;
         let account = self.account_mut(t.cid).await?;
+        Self::ensure_transaction_id_not_disputed(account, t.tid).await?;
         let amount = t.amount.ok_or(TransactionError::MalformedInputData)?;
-        Self::ensure_account_has_sufficient_funds_available(&account, amount).await?;
-        account.available = account.available - amount;
-        account.total = account.total - amount;
-        Self::ensure_account_balance_invariant(&account).await?;
-        account.processed_transactions.insert(t.tid, *t);
+        let funds_check =
+            Self::ensure_account_has_sufficient_funds_available(account, amount).await;
+        if let Err(e) = funds_check {
+            account.rejected_withdrawals_in_a_row += 1;
+            let count = account.rejected_withdrawals_in_a_row;
+            if max_rejected.is_some_and(|max| count >= max) {
+                account.freeze(FreezeTrigger::RejectedWithdrawals { count });
+            }
+            return Err(e);
+        }
+        account.adjust_balances(-amount, Currency::ZERO)?;
+        account.rejected_withdrawals_in_a_row = 0;
+        account.has_applied_transactions = true;
+        Self::ensure_account_balance_invariant(account).await?;
+        if let Some(floor) = balance_floor {
+            if account.total < floor {
+                account.freeze(FreezeTrigger::BalanceBelowFloor { floor });
+            }
+        }
+        if !history_free {
+            let replaced = account.processed_transactions.insert(t.tid, *t);
+            self.track_processed_transaction(t, replaced);
+        }
         Ok(())
     }
 
     /// Handle a dispute transaction.
     async fn dispute(&mut self, dispute: &Transaction) -> TransactionResult<()> {
+        let max_open_disputes = self.config.limits.max_open_disputes;
+        let semantics = self.config.dispute_semantics;
+        let row = self.rows_processed;
         let account = self.account_mut(dispute.cid).await?;
-        if let Some(disputed) = account.processed_transactions.get(&dispute.tid) {
+        if let Some(&disputed) = account.processed_transactions.get(&dispute.tid) {
+            if let Some(max) = max_open_disputes {
+                if account.disputed_transactions.len() >= max {
+                    let cid = account.id;
+                    return Err(TransactionError::TooManyOpenDisputes { cid, max });
+                }
+            }
             // NOTE: Found the `disputed` transaction that the `dispute` refers to
-            let disputed_amount = disputed.amount.unwrap(
-                // This should be safe as long as `disputed.ttype` is either
-                // TransactionType::Deposit or TransactionType::Withdrawal.
-                // The data is malformed if the field equals neither value.
+            let disputed_amount = Self::disputable_amount(&disputed)?;
+            Self::ensure_account_balance_invariant(account).await?;
+            let (available, held) = dispute_adjustment(
+                semantics,
+                &disputed,
+                disputed_amount,
+                TransactionState::Disputed,
             );
-            Self::ensure_account_balance_invariant(&account).await?;
-            account.available = account.available - disputed_amount;
-            account.held = account.held + disputed_amount;
-            Self::ensure_account_balance_invariant(&account).await?;
+            account.adjust_balances(available, held)?;
+            Self::ensure_account_balance_invariant(account).await?;
             // NOTE: mark the `dispute` transaction as disputed:
-            account.disputed_transactions.insert(dispute.tid, *disputed);
+            account.disputed_transactions.insert(dispute.tid, disputed);
             let _ = account.processed_transactions.remove(&dispute.tid);
+            let (from, to) = (TransactionState::Processed, TransactionState::Disputed);
+            account.link_dispute(dispute, row, from, to);
+            self.index_transaction(&disputed, to);
             Ok(())
+        } else if account.may_have_evicted(dispute.tid) {
+            Err(TransactionError::TransactionEvicted {
+                tid: dispute.tid,
+                cid: account.id,
+            })
         } else {
             // NOTE: The account mentioned in the dispute doesn't exist.
             Err(TransactionError::NoSuchProcessedTransactionForClient {
@@ -162,21 +967,27 @@ impl Transactor {
 
     /// Handle a dispute resolution transaction.
     async fn resolve(&mut self, dispute: &Transaction) -> TransactionResult<()> {
+        let semantics = self.config.dispute_semantics;
+        let row = self.rows_processed;
         let account = self.account_mut(dispute.cid).await?;
-        if let Some(disputed) = account.disputed_transactions.get(&dispute.tid) {
+        if let Some(&disputed) = account.disputed_transactions.get(&dispute.tid) {
             // NOTE: Found the `disputed` transaction that the `dispute` refers to
-            let disputed_amount = disputed.amount.unwrap(
-                // This should be safe as long as `disputed.ttype` is either
-                // TransactionType::Deposit or TransactionType::Withdrawal.
-                // The data is malformed if the field equals neither value.
+            let disputed_amount = Self::disputable_amount(&disputed)?;
+            Self::ensure_account_balance_invariant(account).await?;
+            let (available, held) = dispute_adjustment(
+                semantics,
+                &disputed,
+                disputed_amount,
+                TransactionState::Resolved,
             );
-            Self::ensure_account_balance_invariant(&account).await?;
-            account.available = account.available + disputed_amount;
-            account.held = account.held - disputed_amount;
-            Self::ensure_account_balance_invariant(&account).await?;
+            account.adjust_balances(available, held)?;
+            Self::ensure_account_balance_invariant(account).await?;
             // NOTE: mark the `dispute` transaction as resolved:
-            account.resolved_transactions.insert(dispute.tid, *disputed);
+            account.resolved_transactions.insert(dispute.tid, disputed);
             let _ = account.disputed_transactions.remove(&dispute.tid);
+            let (from, to) = (TransactionState::Disputed, TransactionState::Resolved);
+            account.link_dispute(dispute, row, from, to);
+            self.index_transaction(&disputed, to);
             Ok(())
         } else {
             // NOTE: The account mentioned in the dispute doesn't exist.
@@ -189,24 +1000,30 @@ impl Transactor {
 
     /// Handle a chargeback transaction.
     async fn chargeback(&mut self, dispute: &Transaction) -> TransactionResult<()> {
+        let semantics = self.config.dispute_semantics;
+        let row = self.rows_processed;
         let account = self.account_mut(dispute.cid).await?;
-        if let Some(disputed) = account.resolved_transactions.get(&dispute.tid) {
+        if let Some(&disputed) = account.resolved_transactions.get(&dispute.tid) {
             // NOTE: Found the `disputed` transaction that the `dispute` refers to
-            let disputed_amount = disputed.amount.unwrap(
-                // This should be safe as long as `disputed.ttype` is either
-                // TransactionType::Deposit or TransactionType::Withdrawal.
-                // The data is malformed if the field equals neither value.
+            let disputed_amount = Self::disputable_amount(&disputed)?;
+            Self::ensure_account_balance_invariant(account).await?;
+            let (available, held) = dispute_adjustment(
+                semantics,
+                &disputed,
+                disputed_amount,
+                TransactionState::ChargedBack,
             );
-            Self::ensure_account_balance_invariant(&account).await?;
-            account.total = account.total - disputed_amount;
-            account.held = account.held - disputed_amount;
-            Self::ensure_account_balance_invariant(&account).await?;
+            account.adjust_balances(available, held)?;
+            Self::ensure_account_balance_invariant(account).await?;
             // NOTE: mark the `dispute` transaction as charged back:
             account
                 .charged_back_transactions
-                .insert(dispute.tid, *disputed);
+                .insert(dispute.tid, disputed);
             let _ = account.resolved_transactions.remove(&dispute.tid);
-            account.freeze();
+            let (from, to) = (TransactionState::Resolved, TransactionState::ChargedBack);
+            account.link_dispute(dispute, row, from, to);
+            account.freeze(FreezeTrigger::Chargeback { tid: dispute.tid });
+            self.index_transaction(&disputed, to);
             Ok(())
         } else {
             // NOTE: The account mentioned in the dispute doesn't exist.
@@ -217,34 +1034,252 @@ impl Transactor {
         }
     }
 
-    pub async fn print_output(&self) {
-        println!("client,available,held,total,locked");
-        for (ClientId(cid), account) in self.accounts.iter() {
-            let Account {
-                available,
-                held,
-                total,
-                is_locked,
-                ..
-            } = &account;
-            println!(
-                "{},{:?},{:?},{:?},{}",
-                cid, available, held, total, is_locked
+    /// Void the processed transaction with id `tid` of the client with id
+    /// `cid`, e.g. to correct an operator error. The balance effect of the
+    /// transaction is reversed, but the transaction itself is kept in the
+    /// history of the account, marked as voided.
+    ///
+    /// Only transactions that aren't currently disputed can be voided.
+    /// As this is an administrative operation, locked accounts are not
+    /// exempt from it.
+    pub async fn void_transaction(
+        &mut self,
+        cid: ClientId,
+        tid: TransactionId,
+    ) -> TransactionResult<()> {
+        let account = self
+            .accounts
+            .get_mut(&cid)
+            .ok_or(TransactionError::UnknownClient { cid })?;
+        let voided = *account
+            .processed_transactions
+            .get(&tid)
+            .ok_or(TransactionError::NoSuchProcessedTransactionForClient { tid, cid })?;
+        if account.sealed_transactions.contains(&tid) {
+            return Err(TransactionError::TransactionSealed { tid, cid });
+        }
+        let amount = voided.amount.ok_or(TransactionError::MalformedInputData)?;
+        Self::ensure_account_balance_invariant(account).await?;
+        match voided.ttype {
+            TransactionType::Deposit => {
+                Self::ensure_account_has_sufficient_funds_available(account, amount).await?;
+                account.adjust_balances(-amount, Currency::ZERO)?;
+            }
+            TransactionType::Withdrawal => {
+                account.adjust_balances(amount, Currency::ZERO)?;
+            }
+            _ => return Err(TransactionError::MalformedInputData),
+        }
+        Self::ensure_account_balance_invariant(account).await?;
+        account.voided_transactions.insert(tid, voided);
+        let _ = account.processed_transactions.remove(&tid);
+        self.index_transaction(&voided, TransactionState::Voided);
+        self.record_statement_line(&voided, true);
+        Ok(())
+    }
+
+    /// Return a page of the transaction history of the client with id `cid`,
+    /// ordered by `TransactionId`. Only transactions with an id strictly
+    /// greater than `after` are included, and at most `limit` of them.
+    /// Returns `None` if there is no account for the client.
+    ///
+    /// The cursor is a `TransactionId` rather than an offset, so it remains
+    /// stable while new transactions are being processed for the client.
+    pub fn history_page(
+        &self,
+        cid: ClientId,
+        after: Option<TransactionId>,
+        limit: usize,
+    ) -> Option<HistoryPage> {
+        let account = self.accounts.get(&cid)?;
+        let lower = match after {
+            Some(tid) => Bound::Excluded(tid),
+            None => Bound::Unbounded,
+        };
+        // NOTE: A transaction lives in exactly 1 of the histories, so taking
+        //       `limit + 1` entries from each of them and merging the results
+        //       is enough to both fill the page and find out if there's more.
+        let mut entries: Vec<HistoryEntry> = vec![];
+        for (state, transactions) in account.histories().iter() {
+            let range = transactions.range((lower, Bound::Unbounded));
+            entries.extend(
+                range
+                    .take(limit + 1)
+                    .map(|(_, t)| account.history_entry(t, *state)),
             );
         }
+        entries.sort_by_key(|entry| entry.transaction.tid);
+        let has_more = entries.len() > limit;
+        entries.truncate(limit);
+        let next = match entries.last() {
+            Some(entry) if has_more => Some(entry.transaction.tid),
+            _ => None,
+        };
+        Some(HistoryPage { entries, next })
+    }
+
+    /// The dispute stages that the transaction with id `tid` of the client
+    /// with id `cid` went through, in the order in which they happened.
+    pub fn dispute_timeline(&self, cid: ClientId, tid: TransactionId) -> &[DisputeLink] {
+        self.accounts
+            .get(&cid)
+            .and_then(|account| account.dispute_timelines.get(&tid))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Search the transaction histories of all accounts for transactions
+    /// matching the `query`. The results are ordered by `ClientId` first,
+    /// and by `TransactionId` second.
+    pub fn search(&self, query: &TransactionQuery) -> Vec<HistoryEntry> {
+        if let Some(index) = &self.index {
+            return self.search_index(index, query);
+        }
+        let lower = query.min_tid.map_or(Bound::Unbounded, Bound::Included);
+        let upper = query.max_tid.map_or(Bound::Unbounded, Bound::Included);
+        let mut results = vec![];
+        for account in self.accounts.values() {
+            let mut entries: Vec<HistoryEntry> = vec![];
+            for (state, transactions) in account.histories().iter() {
+                let range = transactions.range((lower, upper));
+                entries.extend(
+                    range
+                        .map(|(_, t)| account.history_entry(t, *state))
+                        .filter(|entry| query.matches(entry)),
+                );
+            }
+            entries.sort_by_key(|entry| entry.transaction.tid);
+            results.extend(entries);
+        }
+        results
+    }
+
+    /// Answer the `query` from the secondary indexes.
+    fn search_index(
+        &self,
+        index: &TransactionIndex,
+        query: &TransactionQuery,
+    ) -> Vec<HistoryEntry> {
+        let mut results = vec![];
+        for ((cid, tid), state) in index.candidates(query) {
+            let account = match self.accounts.get(&cid) {
+                Some(account) => account,
+                None => continue,
+            };
+            if let Some(t) = account.history(state).get(&tid) {
+                let entry = account.history_entry(t, state);
+                if query.matches(&entry) {
+                    results.push(entry);
+                }
+            }
+        }
+        results
+    }
+
+    /// Keep secondary indexes over the transaction histories of all
+    /// accounts from now on, so that `search()` doesn't have to scan them.
+    /// The indexes start out with the transactions processed so far.
+    /// They take several entries per transaction, so they aren't kept by
+    /// default.
+    pub fn index_histories(&mut self) {
+        self.index = Some(TransactionIndex::build(self.accounts.values()));
+    }
+
+    /// Add the transaction `t` in state `state` to the secondary indexes,
+    /// or move it to that state, if they are kept.
+    #[inline]
+    fn index_transaction(&mut self, t: &Transaction, state: TransactionState) {
+        if let Some(index) = &mut self.index {
+            index.insert(t, state);
+        }
+    }
+
+    /// Drop the accounts that never had a transaction applied to them, e.g.
+    /// accounts that were opened implicitly for a withdrawal that was then
+    /// rejected because of insufficient funds. Returns the number of
+    /// accounts that were dropped.
+    pub fn drop_empty_accounts(&mut self) -> usize {
+        let num_accounts = self.accounts.len();
+        self.accounts.retain(&mut |account| !account.is_empty());
+        num_accounts - self.accounts.len()
+    }
+
+    /// Print the account summary to `stdout` as `CSV`.
+    pub async fn print_output(&self) -> AppResult<()> {
+        self.write_output(tokio::io::stdout()).await
+    }
+
+    /// Write the account summary as `CSV` to `writer`.
+    pub async fn write_output<W>(&self, writer: W) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut writer = self.serialize_output(writer).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Serialize the account summary as `CSV` to `writer`, and hand back
+    /// the `writer` so that more can be written to it.
+    pub(crate) async fn serialize_output<W>(&self, writer: W) -> AppResult<W>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut serializer = AsyncWriterBuilder::new()
+            .has_headers(false) // The header is written even if there are no rows
+            .create_serializer(writer);
+        serializer.serialize(OUTPUT_HEADER).await?;
+        for summary in self.account_summaries() {
+            serializer.serialize(summary).await?;
+        }
+        let writer = serializer.into_inner().await.map_err(|e| e.into_error())?;
+        Ok(writer)
+    }
+
+    /// Write the account summary to `writer` in the JSON Lines format,
+    /// i.e. one `JSON` object per account, with the same fields as the
+    /// `CSV` summary. Each line is flushed as soon as it's written, so that
+    /// consumers can process it right away.
+    pub async fn write_output_jsonl<W>(&self, mut writer: W) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        for summary in self.account_summaries() {
+            let mut line = serde_json::to_vec(&summary)?;
+            line.push(b'\n');
+            writer.write_all(&line).await?;
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// The rows of the account summary, ordered by `ClientId`.
+    pub fn account_summaries(&self) -> impl Iterator<Item = AccountSummary> + '_ {
+        let policy = self.config.amount_scale.on_violation;
+        self.accounts.values().map(move |account| AccountSummary {
+            client: account.id,
+            available: account.available.rounded_for_output(policy),
+            held: account.held.rounded_for_output(policy),
+            total: account.total.rounded_for_output(policy),
+            locked: account.is_locked,
+        })
     }
 
     #[inline]
     /// Access an account based on `ClientId`. If successful, several checks
     /// are performed to ensure that the account is in the correct state.
+    ///
+    /// Depending on the `UnknownClientPolicy`, an account is opened for a
+    /// client that doesn't have one yet, or an error is returned instead.
     async fn account_mut(&mut self, cid: ClientId) -> TransactionResult<&mut Account> {
-        self.ensure_client_account_exists(cid).await?;
-        let account = self.accounts.get_mut(&cid).unwrap(
-            // NOTE: Should be safe b/c of the `ensure_client_account_exists()`
-            //       call above. If this panicks, then that's definitely a bug.
-        );
-        Self::ensure_account_is_not_locked(&account).await?;
-        Self::ensure_account_balance_invariant(&account).await?;
+        if self.config.unknown_clients == UnknownClientPolicy::OpenAccount {
+            self.ensure_client_account_exists(cid).await?;
+        }
+        let account = self
+            .accounts
+            .get_mut(&cid)
+            .ok_or(TransactionError::UnknownClient { cid })?;
+        Self::ensure_account_is_not_locked(account).await?;
+        Self::ensure_account_balance_invariant(account).await?;
         Ok(account)
     }
 
@@ -253,11 +1288,32 @@ impl Transactor {
     /// an account for the client `id` if no such account exists yet.
     async fn ensure_client_account_exists(&mut self, cid: ClientId) -> TransactionResult<()> {
         if !self.accounts.contains_key(&cid) {
-            self.accounts.insert(cid, Account::new(cid));
+            if let Some(max) = self.config.limits.max_accounts {
+                if self.accounts.len() >= max {
+                    return Err(TransactionError::TooManyAccounts { max });
+                }
+            }
+            let mut account = Account::new(cid);
+            if self.config.freeze.watchlist.contains(&cid) {
+                account.freeze(FreezeTrigger::Watchlist);
+            }
+            self.accounts.insert(account);
         }
         Ok(())
     }
 
+    #[inline]
+    /// Ensure that there is room for 1 more transaction in the histories
+    /// of the accounts, as limited by `config.limits`.
+    async fn ensure_resident_transactions_limit(&self) -> TransactionResult<()> {
+        match self.config.limits.max_resident_transactions {
+            Some(max) if self.resident_transactions >= max => {
+                Err(TransactionError::TooManyResidentTransactions { max })
+            }
+            _ => Ok(()),
+        }
+    }
+
     #[inline]
     /// Ensure a client account exists. This is accomplished by opening
     /// an account for the client `id` if no such account exists yet.
@@ -269,18 +1325,135 @@ impl Transactor {
         }
     }
 
+    #[inline]
+    /// Ensure that `t` isn't a dispute, resolve or chargeback, if the
+    /// `RetentionPolicy` keeps no history for them to refer to.
+    fn ensure_disputes_supported(&self, t: &Transaction) -> TransactionResult<()> {
+        let is_dispute = !matches!(
+            t.ttype,
+            TransactionType::Deposit | TransactionType::Withdrawal
+        );
+        if is_dispute && self.config.retention.history_free {
+            let (tid, cid) = (t.tid, t.cid);
+            return Err(TransactionError::DisputesNotSupported { tid, cid });
+        }
+        Ok(())
+    }
+
+    #[inline]
+    /// Ensure that deposits and withdrawals arrive in order of increasing
+    /// `TransactionId`, if the configured `OrderingPolicy` demands it.
+    /// Other transaction types refer to earlier transactions, and so
+    /// are exempt from this check.
+    async fn ensure_transaction_order(&mut self, t: &Transaction) -> TransactionResult<()> {
+        if !matches!(
+            t.ttype,
+            TransactionType::Deposit | TransactionType::Withdrawal
+        ) {
+            return Ok(());
+        }
+        match (self.config.ordering, self.max_tid) {
+            (OrderingPolicy::MonotonicTid(action), Some(max_tid)) if t.tid < max_tid => {
+                let tid = t.tid;
+                match action {
+                    OrderingViolation::Reject => {
+                        return Err(TransactionError::TransactionIdOutOfOrder { tid, max_tid });
+                    }
+                    OrderingViolation::Warn => {
+                        let warning = Warning::TransactionIdOutOfOrder { tid, max_tid };
+                        self.warnings.push(warning);
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.max_tid = self.max_tid.max(Some(t.tid));
+        Ok(())
+    }
+
+    /// Ensure that the amount of `t` has no more decimal places than the
+    /// `AmountScalePolicy` allows, and return `t` with its amount
+    /// normalized if the policy says so.
+    fn ensure_amount_scale(&mut self, mut t: Transaction) -> TransactionResult<Transaction> {
+        let policy = self.config.amount_scale;
+        let (max_scale, amount) = match (policy.max_scale, t.amount) {
+            (Some(max_scale), Some(amount)) => (max_scale, amount),
+            _ => return Ok(t),
+        };
+        let scale = amount.0.scale();
+        let tid = t.tid;
+        match amount.with_scale(max_scale, policy.on_violation) {
+            None => Err(TransactionError::AmountScaleExceeded {
+                tid,
+                scale,
+                max_scale,
+            }),
+            Some(normalized) => {
+                if scale > max_scale {
+                    t.amount = Some(normalized);
+                    let warning = Warning::AmountScaleNormalized {
+                        tid,
+                        scale,
+                        max_scale,
+                    };
+                    self.warnings.push(warning);
+                }
+                Ok(t)
+            }
+        }
+    }
+
+    #[inline]
+    /// The amount of the transaction `disputed` that a dispute, resolve or
+    /// chargeback refers to. Only deposits and withdrawals can be disputed,
+    /// so any other transaction, e.g. from a hand-edited snapshot, is
+    /// rejected rather than trusted to have an amount.
+    fn disputable_amount(disputed: &Transaction) -> TransactionResult<Currency> {
+        match disputed.ttype {
+            TransactionType::Deposit | TransactionType::Withdrawal => {
+                disputed.amount.ok_or(TransactionError::MalformedInputData)
+            }
+            ttype => Err(TransactionError::TransactionNotDisputable {
+                tid: disputed.tid,
+                ttype,
+            }),
+        }
+    }
+
     #[inline]
     /// Ensure that the addition of available funds + held funds
     /// for a given `account` equals its total funds.
     /// This should hold before and after any transaction.
     async fn ensure_account_balance_invariant(account: &Account) -> TransactionResult<()> {
-        if account.available + account.held == account.total {
+        if account.available.checked_add(account.held) == Some(account.total) {
             Ok(())
         } else {
             Err(TransactionError::AccountBalanceInvariantViolated { cid: account.id })
         }
     }
 
+    /// Ensure that the id `tid` of a deposit or withdrawal isn't that of a
+    /// transaction in the dispute histories of an `account`: storing it
+    /// would file the same id under 2 `TransactionState`s.
+    async fn ensure_transaction_id_not_disputed(
+        account: &Account,
+        tid: TransactionId,
+    ) -> TransactionResult<()> {
+        let disputed = account
+            .histories()
+            .iter()
+            .filter(|(state, _)| *state != TransactionState::Processed)
+            .any(|(_, history)| history.contains_key(&tid));
+        if disputed {
+            Err(TransactionError::TransactionIdReused {
+                tid,
+                cid: account.id,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline]
     /// Ensure that an `account` has >= `amount` of funds available.
     async fn ensure_account_has_sufficient_funds_available(
@@ -295,59 +1468,409 @@ impl Transactor {
     }
 }
 
+impl Default for Transactor {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // NOTE: The `*_transactions` fields are of type `BTreeMap<_, _>`
 //       to preserve ordering (which is temporal) while also allowing
 //       non-sequential storage of transactions.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct Account {
     pub(crate) id: ClientId,
     pub(crate) available: Currency,
     pub(crate) held: Currency,
     pub(crate) total: Currency,
+    #[serde(default)]
     pub(crate) is_locked: bool,
+    /// What caused the account to be frozen, if it is
+    #[serde(default)]
+    pub(crate) freeze_trigger: Option<FreezeTrigger>,
+    /// The number of withdrawals rejected for insufficient funds since
+    /// the last successful one
+    #[serde(default)]
+    pub(crate) rejected_withdrawals_in_a_row: u32,
     /// Transactions that have been processed, and are not disputed
+    #[serde(default)]
     pub(crate) processed_transactions: BTreeMap<TransactionId, Transaction>,
     /// Transactions that have been disputed
+    #[serde(default)]
     pub(crate) disputed_transactions: BTreeMap<TransactionId, Transaction>,
     /// Transactions that have been disputed, and the dispute has been resolved
+    #[serde(default)]
     pub(crate) resolved_transactions: BTreeMap<TransactionId, Transaction>,
     /// Transactions that have been charged back
+    #[serde(default)]
     pub(crate) charged_back_transactions: BTreeMap<TransactionId, Transaction>,
+    /// Transactions that have been voided by an operator
+    #[serde(default)]
+    pub(crate) voided_transactions: BTreeMap<TransactionId, Transaction>,
+    /// The dispute stages that each disputed transaction went through
+    #[serde(default)]
+    pub(crate) dispute_timelines: BTreeMap<TransactionId, Vec<DisputeLink>>,
+    /// The part of the deposits that was swept against negative held funds
+    #[serde(default)]
+    pub(crate) repaid: Currency,
+    /// The transactions that belong to a closed accounting period
+    #[serde(default)]
+    pub(crate) sealed_transactions: BTreeSet<TransactionId>,
+    /// The highest id of the transactions evicted by the `RetentionPolicy`
+    #[serde(default)]
+    pub(crate) evicted_through: Option<TransactionId>,
+    /// Whether a deposit or withdrawal was ever applied to the account,
+    /// which its history doesn't show when it's evicted or not kept
+    #[serde(default)]
+    pub(crate) has_applied_transactions: bool,
 }
 
 impl Account {
     #[inline(always)]
-    fn new(id: ClientId) -> Self {
+    pub(crate) fn new(id: ClientId) -> Self {
         Self {
             id,
             available: Currency::ZERO,
             held: Currency::ZERO,
             total: Currency::ZERO,
             is_locked: false,
+            freeze_trigger: None,
+            rejected_withdrawals_in_a_row: 0,
             processed_transactions: BTreeMap::new(),
             disputed_transactions: BTreeMap::new(),
             resolved_transactions: BTreeMap::new(),
             charged_back_transactions: BTreeMap::new(),
+            voided_transactions: BTreeMap::new(),
+            dispute_timelines: BTreeMap::new(),
+            repaid: Currency::ZERO,
+            sealed_transactions: BTreeSet::new(),
+            evicted_through: None,
+            has_applied_transactions: false,
+        }
+    }
+
+    /// The number of transactions in the history of the account.
+    #[inline]
+    pub(crate) fn history_len(&self) -> usize {
+        self.processed_transactions.len()
+            + self.disputed_transactions.len()
+            + self.resolved_transactions.len()
+            + self.charged_back_transactions.len()
+            + self.voided_transactions.len()
+    }
+
+    /// Evict the processed transactions with the lowest ids until at most
+    /// `keep` remain. Returns the transactions evicted.
+    fn evict_settled_transactions(&mut self, keep: usize) -> Vec<Transaction> {
+        let mut evicted = vec![];
+        while self.processed_transactions.len() > keep {
+            if let Some((tid, t)) = self.processed_transactions.pop_first() {
+                let _ = self.sealed_transactions.remove(&tid);
+                self.evicted_through = self.evicted_through.max(Some(tid));
+                evicted.push(t);
+            }
+        }
+        evicted
+    }
+
+    /// Returns `true` if the transaction with id `tid` isn't in the history
+    /// of the account, possibly because it was evicted from it.
+    fn may_have_evicted(&self, tid: TransactionId) -> bool {
+        self.evicted_through.is_some_and(|max| tid <= max)
+            && !self.disputed_transactions.contains_key(&tid)
+            && !self.resolved_transactions.contains_key(&tid)
+            && !self.charged_back_transactions.contains_key(&tid)
+            && !self.voided_transactions.contains_key(&tid)
+    }
+
+    /// Record that the dispute stage of the transaction `t` refers to
+    /// changed from `from` to `to` at row `row`.
+    fn link_dispute(
+        &mut self,
+        t: &Transaction,
+        row: u64,
+        from: TransactionState,
+        to: TransactionState,
+    ) {
+        let link = DisputeLink {
+            tid: t.tid,
+            ttype: t.ttype,
+            row,
+            from,
+            to,
+        };
+        self.dispute_timelines.entry(t.tid).or_default().push(link);
+    }
+
+    /// The entry for the transaction `t` in state `state` in the history
+    /// of this account.
+    pub(crate) fn history_entry(&self, t: &Transaction, state: TransactionState) -> HistoryEntry {
+        HistoryEntry {
+            transaction: *t,
+            state,
+            timeline: self
+                .dispute_timelines
+                .get(&t.tid)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The transaction histories of this account, paired with the
+    /// `TransactionState` of the transactions that each of them contains.
+    pub(crate) fn histories(
+        &self,
+    ) -> [(TransactionState, &BTreeMap<TransactionId, Transaction>); 5] {
+        [
+            (TransactionState::Processed, &self.processed_transactions),
+            (TransactionState::Disputed, &self.disputed_transactions),
+            (TransactionState::Resolved, &self.resolved_transactions),
+            (
+                TransactionState::ChargedBack,
+                &self.charged_back_transactions,
+            ),
+            (TransactionState::Voided, &self.voided_transactions),
+        ]
+    }
+
+    /// The transaction history of this account with the transactions in
+    /// state `state`.
+    #[rustfmt::skip]
+    pub(crate) fn history(&self, state: TransactionState) -> &BTreeMap<TransactionId, Transaction> {
+        match state {
+            TransactionState::Processed   => &self.processed_transactions,
+            TransactionState::Disputed    => &self.disputed_transactions,
+            TransactionState::Resolved    => &self.resolved_transactions,
+            TransactionState::ChargedBack => &self.charged_back_transactions,
+            TransactionState::Voided      => &self.voided_transactions,
+        }
+    }
+
+    /// Returns `true` if the account has no funds, no transaction history
+    /// and isn't locked, i.e. if it is indistinguishable from a new account.
+    fn is_empty(&self) -> bool {
+        self.available == Currency::ZERO
+            && self.held == Currency::ZERO
+            && self.total == Currency::ZERO
+            && !self.is_locked
+            && !self.has_applied_transactions
+            && self.evicted_through.is_none()
+            && self
+                .histories()
+                .iter()
+                .all(|(_, history)| history.is_empty())
+    }
+
+    /// Add `available` and `held` to the respective funds of the account,
+    /// and both to its total funds. If any of the funds would overflow,
+    /// the account is left as it was.
+    fn adjust_balances(&mut self, available: Currency, held: Currency) -> TransactionResult<()> {
+        let overflow = TransactionError::AmountOverflow { cid: self.id };
+        let new_available = self.available.checked_add(available);
+        let new_held = self.held.checked_add(held);
+        let new_total = available
+            .checked_add(held)
+            .and_then(|delta| self.total.checked_add(delta));
+        match (new_available, new_held, new_total) {
+            (Some(available), Some(held), Some(total)) => {
+                self.available = available;
+                self.held = held;
+                self.total = total;
+                Ok(())
+            }
+            _ => Err(overflow),
         }
     }
 
     #[inline(always)]
-    fn freeze(&mut self) {
-        self.is_locked = true;
+    /// Freeze the account because of `trigger`. Only the first trigger that
+    /// froze the account is recorded.
+    fn freeze(&mut self, trigger: FreezeTrigger) {
+        if !self.is_locked {
+            self.is_locked = true;
+            self.freeze_trigger = Some(trigger);
+        }
+    }
+
+    #[inline(always)]
+    fn unfreeze(&mut self) {
+        self.is_locked = false;
+        self.freeze_trigger = None;
+        self.rejected_withdrawals_in_a_row = 0;
+    }
+}
+
+#[rustfmt::skip]
+/// The amounts to add to the available and held funds of an account when
+/// the transaction `disputed` of `amount` moves to the dispute stage `to`.
+/// The total funds change by the sum of both.
+fn dispute_adjustment(
+    semantics: DisputeSemantics,
+    disputed: &Transaction,
+    amount: Currency,
+    to: TransactionState,
+) -> (Currency, Currency) {
+    let zero = Currency::ZERO;
+    let direction_aware = semantics == DisputeSemantics::DirectionAware
+        && disputed.ttype == TransactionType::Withdrawal;
+    match (direction_aware, to) {
+        (false, TransactionState::Disputed)    => (-amount, amount),
+        (false, TransactionState::Resolved)    => (amount, -amount),
+        (false, TransactionState::ChargedBack) => (zero, -amount),
+        (true, TransactionState::Disputed)     => (zero, amount),
+        (true, TransactionState::Resolved)     => (zero, -amount),
+        (true, TransactionState::ChargedBack)  => (amount, zero),
+        (_, TransactionState::Processed) | (_, TransactionState::Voided) => (zero, zero),
     }
 }
 
 // NOTE: I purposely left out the actual currency designation, since the
 // assignment has done so as well. It's a unicurrency, unibank world.
-#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
-pub(crate) struct Currency(Decimal);
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Currency(pub(crate) Decimal);
+
+impl<'de> de::Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // NOTE: Amounts are parsed from their text. Letting the format pick
+        //       a type for them doesn't work for `CSV`, whose fields that
+        //       look like numbers are read as `f64` or `u128` first, which
+        //       loses precision beyond ~17 digits, or fails outright.
+        struct CurrencyVisitor;
+
+        impl<'de> Visitor<'de> for CurrencyVisitor {
+            type Value = Currency;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a decimal amount")
+            }
+
+            fn visit_str<E: de::Error>(self, amount: &str) -> Result<Currency, E> {
+                use std::str::FromStr;
+                Decimal::from_str(amount.trim())
+                    .map(Currency)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(amount), &self))
+            }
+        }
+
+        deserializer.deserialize_str(CurrencyVisitor)
+    }
+}
+
+/// A single row of the account summary that a `Transactor` outputs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AccountSummary {
+    pub client: ClientId,
+    #[serde(serialize_with = "serialize_amount")]
+    pub available: Currency,
+    #[serde(serialize_with = "serialize_amount")]
+    pub held: Currency,
+    #[serde(serialize_with = "serialize_amount")]
+    pub total: Currency,
+    pub locked: bool,
+}
+
+impl AccountSummary {
+    /// The fields of this row, exactly as they are serialized.
+    pub(crate) fn fields(&self) -> [String; 5] {
+        [
+            self.client.to_string(),
+            self.available.to_output_string(),
+            self.held.to_output_string(),
+            self.total.to_output_string(),
+            self.locked.to_string(),
+        ]
+    }
+}
+
+fn serialize_amount<S: Serializer>(amount: &Currency, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&amount.to_output_string())
+}
 
 impl Currency {
-    const ZERO: Self = Self(Decimal::ZERO);
+    pub(crate) const ZERO: Self = Self(Decimal::ZERO);
+
+    #[inline(always)]
+    pub const fn new(amount: Decimal) -> Self {
+        Self(amount)
+    }
+
+    /// The amount in minor units, i.e. the smallest amount shown in the
+    /// account summary, which has `OUTPUT_SCALE` decimal places. E.g.
+    /// `Currency::from_minor_units(15_000)` is `1.5`.
+    #[inline(always)]
+    pub fn from_minor_units(units: i64) -> Self {
+        Self(Decimal::new(units, Self::OUTPUT_SCALE))
+    }
+
+    #[inline(always)]
+    pub fn as_decimal(self) -> Decimal {
+        self.0
+    }
+
+    /// Add `rhs`, or return `None` if the sum can't be represented.
+    #[inline(always)]
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    /// Subtract `rhs`, or return `None` if the difference can't be
+    /// represented.
+    #[inline(always)]
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    /// Multiply by `factor`, or return `None` if the product can't be
+    /// represented.
+    #[inline(always)]
+    pub fn checked_mul(self, factor: Decimal) -> Option<Self> {
+        self.0.checked_mul(factor).map(Self)
+    }
+
+    /// The number of decimal places in the account summary.
+    pub const OUTPUT_SCALE: u32 = 4;
+
+    /// Limit the amount to at most `max_scale` decimal places, in the way
+    /// that `policy` says, or return `None` if the amount has more places
+    /// and `policy` rejects it.
+    pub fn with_scale(self, max_scale: u32, policy: ScaleViolation) -> Option<Self> {
+        if self.0.scale() <= max_scale {
+            return Some(self);
+        }
+        let strategy = match policy {
+            ScaleViolation::Reject => return None,
+            ScaleViolation::Truncate => RoundingStrategy::ToZero,
+            ScaleViolation::RoundHalfEven => RoundingStrategy::MidpointNearestEven,
+        };
+        Some(Self(self.0.round_dp_with_strategy(max_scale, strategy)))
+    }
+
+    /// Limit the amount to the `OUTPUT_SCALE`, rounding ties to even if
+    /// `policy` rounds amounts on ingest, and truncating it otherwise.
+    pub(crate) fn rounded_for_output(self, policy: ScaleViolation) -> Self {
+        let policy = match policy {
+            ScaleViolation::RoundHalfEven => ScaleViolation::RoundHalfEven,
+            ScaleViolation::Reject | ScaleViolation::Truncate => ScaleViolation::Truncate,
+        };
+        self.with_scale(Self::OUTPUT_SCALE, policy).unwrap_or(self)
+    }
 
-    #[allow(unused)]
+    /// Format the amount as it appears in the account summary, i.e. with
+    /// exactly 4 decimal places. Any further places are truncated, as they
+    /// have always been in the output.
+    pub(crate) fn to_output_string(self) -> String {
+        let mut rounded = self
+            .0
+            .round_dp_with_strategy(Self::OUTPUT_SCALE, RoundingStrategy::ToZero);
+        rounded.rescale(Self::OUTPUT_SCALE);
+        rounded.to_string()
+    }
+
+    /// Parse an amount such as `1.5`. This is the `FromStr` impl, callable
+    /// without importing the trait.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(amount: &str) -> AppResult<Self> {
-        // NOTE: used for testing purposes
         use std::str::FromStr;
         match Decimal::from_str(amount) {
             Ok(decimal) => Ok(Self(decimal)),
@@ -358,6 +1881,22 @@ impl Currency {
     }
 }
 
+impl std::str::FromStr for Currency {
+    type Err = AppError;
+
+    #[inline(always)]
+    fn from_str(amount: &str) -> AppResult<Self> {
+        Self::from_str(amount)
+    }
+}
+
+impl From<Decimal> for Currency {
+    #[inline(always)]
+    fn from(amount: Decimal) -> Self {
+        Self(amount)
+    }
+}
+
 impl fmt::Debug for Currency {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // NOTE: These Debug printouts are so short that it's more useful and
@@ -377,6 +1916,9 @@ impl fmt::Display for Currency {
     }
 }
 
+// NOTE: Like those of `Decimal`, the arithmetic operators panic when the
+//       result can't be represented. The engine itself only uses the
+//       checked methods, and rejects a transaction that would overflow.
 impl std::ops::Add<Self> for Currency {
     type Output = Self;
 
@@ -386,6 +1928,13 @@ impl std::ops::Add<Self> for Currency {
     }
 }
 
+impl std::ops::AddAssign<Self> for Currency {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
 impl std::ops::Sub<Self> for Currency {
     type Output = Self;
 
@@ -395,31 +1944,371 @@ impl std::ops::Sub<Self> for Currency {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+impl std::ops::Mul<Decimal> for Currency {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, factor: Decimal) -> Self::Output {
+        Self(self.0 * factor)
+    }
+}
+
+impl std::ops::Neg for Currency {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl std::iter::Sum for Currency {
+    fn sum<I: Iterator<Item = Self>>(amounts: I) -> Self {
+        amounts.fold(Self::ZERO, |sum, amount| sum + amount)
+    }
+}
+
+impl<'a> std::iter::Sum<&'a Currency> for Currency {
+    fn sum<I: Iterator<Item = &'a Self>>(amounts: I) -> Self {
+        amounts.copied().sum()
+    }
+}
+
+/// A problem with the input that, unlike a `TransactionError`,
+/// does not cause the transaction to be rejected.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Warning {
+    /// A deposit or withdrawal arrived after one with a higher id `max_tid`.
+    TransactionIdOutOfOrder {
+        tid: TransactionId,
+        max_tid: TransactionId,
+    },
+    /// The amount of the transaction with id `tid` had `scale` decimal
+    /// places, and was truncated or rounded to the `max_scale` that is
+    /// allowed.
+    AmountScaleNormalized {
+        tid: TransactionId,
+        scale: u32,
+        max_scale: u32,
+    },
+}
+
+/// The reason an account was frozen.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum FreezeTrigger {
+    /// The transaction with id `tid` was charged back.
+    Chargeback { tid: TransactionId },
+    /// `count` withdrawals in a row were rejected for insufficient funds.
+    RejectedWithdrawals { count: u32 },
+    /// A withdrawal brought the total funds below `floor`.
+    BalanceBelowFloor { floor: Currency },
+    /// The client is on the watchlist.
+    Watchlist,
+}
+
+/// An account with negative funds, as reported by
+/// `Transactor::amounts_owed()`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AmountOwed {
+    pub client: ClientId,
+    pub held: Currency,
+    pub total: Currency,
+    /// The larger of the shortfalls of the held and the total funds.
+    pub owed: Currency,
+    /// How much of the deposits was swept against negative held funds so
+    /// far, if the `RepaymentPolicy` sweeps deposits.
+    pub repaid: Currency,
+}
+
+/// A change in the state of an account that isn't the direct result of
+/// applying a transaction to it.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct AccountEvent {
+    /// The number of rows processed when the change happened.
+    pub row: u64,
+    pub cid: ClientId,
+    pub change: AccountChange,
+}
+
+/// The kind of change in an `AccountEvent`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum AccountChange {
+    Frozen(FreezeTrigger),
+    Unfrozen(UnfreezeReason),
+}
+
+/// The reason an account was unfrozen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum UnfreezeReason {
+    /// The cooling-off period of the `UnfreezePolicy` ended.
+    CoolingOff,
+    /// A resolve arrived for the charged back transaction with id `tid`.
+    MatchingResolve { tid: TransactionId },
+    /// An operator unlocked the account with `Transactor::unlock_account()`.
+    Manual,
+}
+
+/// A dispute, resolve or chargeback that refers to a transaction that
+/// hasn't been seen yet. It is retried until row number `deadline`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct ParkedTransaction {
+    transaction: Transaction,
+    /// The line of the input that the `transaction` was read from, if known
+    line: Option<u64>,
+    deadline: u64,
+    /// The reason the `transaction` was parked
+    error: TransactionError,
+}
+
+impl ParkedTransaction {
+    /// The client of the parked transaction.
+    #[inline(always)]
+    pub(crate) fn cid(&self) -> ClientId {
+        self.transaction.cid
+    }
+}
+
+/// Counters of how the transactions of a single client were handled.
+/// Parked transactions are only counted once their fate is decided.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ClientStats {
+    /// The number of transactions that were applied successfully.
+    pub accepted: u64,
+    /// The number of rejected transactions, per `TransactionError::reason()`.
+    pub rejected: BTreeMap<&'static str, u64>,
+    /// The number of disputes that were applied successfully.
+    pub disputes_opened: u64,
+    /// The number of resolves that were applied successfully.
+    pub disputes_resolved: u64,
+    /// The number of chargebacks that were applied successfully.
+    pub disputes_charged_back: u64,
+    /// The number of transactions, accepted or rejected, per
+    /// `TransactionType::name()`.
+    pub by_type: BTreeMap<&'static str, u64>,
+}
+
+/// A transaction whose processing exceeded the latency budget,
+/// along with the context needed to figure out why.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SlowOperation {
+    pub transaction: Transaction,
+    /// How long it took to process the `transaction`.
+    pub elapsed: Duration,
+    /// The reason the `transaction` was rejected, if it was.
+    pub error: Option<TransactionError>,
+    /// The number of transactions in the client's history afterwards.
+    pub history_len: usize,
+    /// The number of open disputes of the client afterwards.
+    pub open_disputes: usize,
+}
+
+/// The lifecycle stage that a transaction in an account's history is in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum TransactionState {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+    Voided,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub transaction: Transaction,
+    pub state: TransactionState,
+    /// The stages that the transaction went through before reaching
+    /// `state`, if it was ever disputed.
+    pub timeline: Vec<DisputeLink>,
+}
+
+/// A change in the dispute stage of a transaction, caused by a dispute,
+/// resolve or chargeback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct DisputeLink {
+    /// The id of the dispute, resolve or chargeback that caused the change.
+    /// Since these share the id of the transaction they refer to, this is
+    /// the id of the disputed transaction itself.
+    pub tid: TransactionId,
+    pub ttype: TransactionType,
+    /// The number of rows processed when the change happened.
+    pub row: u64,
+    pub from: TransactionState,
+    pub to: TransactionState,
+}
+
+/// A page of a client's transaction history, as returned by
+/// `Transactor::history_page()`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HistoryPage {
+    /// The entries on this page, ordered by `TransactionId`.
+    pub entries: Vec<HistoryEntry>,
+    /// The cursor to pass as `after` to fetch the next page,
+    /// or `None` if this is the last page.
+    pub next: Option<TransactionId>,
+}
+
+/// A set of criteria for `Transactor::search()`. A transaction matches the
+/// query if it satisfies every criterion that is set; `None` means that any
+/// value is accepted. All ranges are inclusive.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TransactionQuery {
+    pub ttype: Option<TransactionType>,
+    pub state: Option<TransactionState>,
+    pub min_amount: Option<Currency>,
+    pub max_amount: Option<Currency>,
+    pub min_tid: Option<TransactionId>,
+    pub max_tid: Option<TransactionId>,
+}
+
+impl TransactionQuery {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        let t = &entry.transaction;
+        let amount_in_range = match t.amount {
+            Some(amount) => {
+                self.min_amount.is_none_or(|min| min <= amount)
+                    && self.max_amount.is_none_or(|max| amount <= max)
+            }
+            None => self.min_amount.is_none() && self.max_amount.is_none(),
+        };
+        self.ttype.is_none_or(|ttype| ttype == t.ttype)
+            && self.state.is_none_or(|state| state == entry.state)
+            && self.min_tid.is_none_or(|min| min <= t.tid)
+            && self.max_tid.is_none_or(|max| t.tid <= max)
+            && amount_in_range
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct IgnoredTransaction {
     /// The actual transaction being ignored.
     transaction: Transaction,
+    /// The line of the input that `transaction` was read from, if known.
+    line: Option<u64>,
     /// The reason that `transaction` was ignored.
     reason: TransactionError,
 }
 
-#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+impl IgnoredTransaction {
+    #[inline(always)]
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    #[inline(always)]
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+
+    #[inline(always)]
+    pub fn reason(&self) -> &TransactionError {
+        &self.reason
+    }
+}
+
+impl fmt::Display for IgnoredTransaction {
+    /// E.g. `line 4812: withdrawal for client 7 rejected: insufficient funds`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(
+            f,
+            "{} for client {} rejected: {}",
+            self.transaction.ttype.name(),
+            self.transaction.cid,
+            self.reason.reason().replace('_', " ")
+        )
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct Transaction {
     #[serde(rename = "type")]
-    ttype: TransactionType,
+    pub(crate) ttype: TransactionType,
     #[serde(rename = "client")]
-    cid: ClientId,
+    pub(crate) cid: ClientId,
     #[serde(rename = "tx")]
-    tid: TransactionId,
-    amount: Option<Currency>,
+    pub(crate) tid: TransactionId,
+    pub(crate) amount: Option<Currency>,
 }
 
 impl Transaction {
+    /// Create a transaction of any type. Prefer the constructors for
+    /// specific types, e.g. `Transaction::deposit()`, which can't be given
+    /// an amount where none belongs, or be left without one where it does.
+    #[inline(always)]
+    pub fn new(
+        ttype: TransactionType,
+        cid: ClientId,
+        tid: TransactionId,
+        amount: Option<Currency>,
+    ) -> Self {
+        Self {
+            ttype,
+            cid,
+            tid,
+            amount,
+        }
+    }
+
+    /// A deposit of `amount` into the account of client `cid`.
+    #[inline(always)]
+    pub fn deposit(cid: ClientId, tid: TransactionId, amount: Currency) -> Self {
+        Self::new(TransactionType::Deposit, cid, tid, Some(amount))
+    }
+
+    /// A withdrawal of `amount` from the account of client `cid`.
+    #[inline(always)]
+    pub fn withdrawal(cid: ClientId, tid: TransactionId, amount: Currency) -> Self {
+        Self::new(TransactionType::Withdrawal, cid, tid, Some(amount))
+    }
+
+    /// A dispute of the transaction `tid` of client `cid`.
+    #[inline(always)]
+    pub fn dispute(cid: ClientId, tid: TransactionId) -> Self {
+        Self::new(TransactionType::Dispute, cid, tid, None)
+    }
+
+    /// A resolve of the dispute of the transaction `tid` of client `cid`.
+    #[inline(always)]
+    pub fn resolve(cid: ClientId, tid: TransactionId) -> Self {
+        Self::new(TransactionType::Resolve, cid, tid, None)
+    }
+
+    /// A chargeback of the transaction `tid` of client `cid`.
+    #[inline(always)]
+    pub fn chargeback(cid: ClientId, tid: TransactionId) -> Self {
+        Self::new(TransactionType::Chargeback, cid, tid, None)
+    }
+
+    #[inline(always)]
+    pub fn ttype(&self) -> TransactionType {
+        self.ttype
+    }
+
+    #[inline(always)]
+    pub fn cid(&self) -> ClientId {
+        self.cid
+    }
+
+    #[inline(always)]
+    pub fn tid(&self) -> TransactionId {
+        self.tid
+    }
+
+    #[inline(always)]
+    pub fn amount(&self) -> Option<Currency> {
+        self.amount
+    }
+
     #[cfg(feature = "async_file_reads")]
-    /// Stream transactions from a CSV file located @ `filepath`.
+    /// Stream transactions from a CSV file located @ `filepath`, which is
+    /// decompressed as it's read if `compression` says so.
     async fn stream_from_csv_file(
         filepath: PathBuf,
-    ) -> AppResult<AsyncStream<AppResult<Self>, impl Future<Output = ()>>> {
+        compression: Compression,
+        aliases: BTreeMap<String, TransactionType>,
+    ) -> AppResult<AsyncStream<AppResult<(u64, Self)>, impl Future<Output = ()>>> {
         Ok(stream! {
             const CAPACITY: usize = 8192;
             let file = tokio_uring::fs::File::open(filepath).await?;
@@ -431,46 +2320,57 @@ impl Transaction {
             let mut accumulator: Vec<u8> = Vec::with_capacity(CAPACITY);
             let mut headers: Vec<String> = vec![];
             let mut byte_offset: u64 = 0;
-            let mut lineno: usize = 0;
-            loop {
+            let mut lineno: u64 = 0;
+            let mut decoder = ChunkDecoder::new(compression);
+            let mut at_eof = false;
+            while !at_eof {
                 // NOTE: Read some data, the `buffer` is passed by ownership
                 // and submitted to the kernel. When the operation completes,
                 // the kernel gives ownership of the buffer back.
                 let (result, buf) = file.read_at(buffer, byte_offset).await;
                 buffer = buf;
                 let num_bytes_read = result?;
-                if num_bytes_read == 0 {
-                    break; // NOTE: Reached EOF
-                }
                 byte_offset += num_bytes_read as u64;
-                accumulator.extend(&buffer[.. num_bytes_read]);
+                let chunk = &buffer[.. num_bytes_read];
+                at_eof = num_bytes_read == 0;
+                match (&mut decoder, at_eof) {
+                    (None, _) => accumulator.extend(chunk),
+                    (Some(decoder), false) => decoder.decode(chunk, &mut accumulator).await?,
+                    // NOTE: The decoder may still hold the last lines.
+                    (Some(decoder), true) => decoder.finish(&mut accumulator).await?,
+                }
                 const NEWLINE: &[u8] = "\n".as_bytes();
+                if at_eof && !accumulator.is_empty() && !accumulator.ends_with(NEWLINE) {
+                    // NOTE: The last line has no newline, so terminate it.
+                    accumulator.extend(NEWLINE);
+                }
                 while let Some(newline_idx) = find(NEWLINE, &accumulator) {
                     let line: &[u8] = &accumulator[.. newline_idx];
-                    let line: &str = std::str::from_utf8(line)?;
-                    if lineno == 0 { // NOTE: parse the headers
-                        lineno += 1;
+                    let line: &str = std::str::from_utf8(line)?.trim();
+                    lineno += 1;
+                    if lineno == 1 { // NOTE: parse the headers
                         let columns = line.split(',');
                         headers = columns
                             .map(str::trim)
                             .map(String::from)
                             .collect();
-                        let _ = accumulator
-                            .drain(.. newline_idx + NEWLINE.len()) // drain the line
-                            .collect::<Vec<_>>();
-                    } else {
-                        lineno += 1;
+                        let unknown = headers.iter().find(|h| !CSV_HEADERS.contains(&h.as_str()));
+                        if let Some(header) = unknown {
+                            yield Err(AppError::UnknownCsvHeader { header: header.clone() });
+                            return;
+                        }
+                    } else if !line.is_empty() && !line.starts_with('#') {
                         // NOTE: create a `Transaction` value and stream it:
-                        let transaction =
-                            Transaction::from_csv_line(&*headers, line).await;
-                        let _ = accumulator
-                            .drain(.. newline_idx + NEWLINE.len()) // drain the line
-                            .collect::<Vec<_>>();
+                        let transaction = Transaction::from_csv_line(&*headers, line, lineno, &aliases)
+                            .await
+                            .map(|transaction| (lineno, transaction));
                         yield transaction;
                     }
+                    let _ = accumulator
+                        .drain(.. newline_idx + NEWLINE.len()) // drain the line
+                        .collect::<Vec<_>>();
                 }
             }
-            assert!(accumulator.is_empty(), "The accumulator isn't empty");
         })
     }
 
@@ -478,40 +2378,43 @@ impl Transaction {
     #[cfg(feature = "async_file_reads")]
     async fn from_csv_line<S: AsRef<str>>(
         headers: &[S],
-        line: &str
+        line: &str,
+        lineno: u64,
+        aliases: &BTreeMap<String, TransactionType>,
     ) -> AppResult<Self> {
         let mut transaction = Self::default();
         let columns = line.split(',');
+        let malformed = |_| AppError::MalformedRow { line: lineno };
         for (value, header) in columns.map(str::trim).zip(headers.iter()) {
             match header.as_ref() {
                 "type" => {
-                    transaction.ttype = match value {
-                        "deposit" => TransactionType::Deposit,
-                        "withdrawal" => TransactionType::Withdrawal,
-                        "dispute" => TransactionType::Dispute,
-                        "resolve" => TransactionType::Resolve,
-                        "chargeback" => TransactionType::Chargeback,
-                        _ => panic!("unknown type '{}'", value),
-                    }
+                    transaction.ttype = TransactionType::from_name(value)
+                        .or_else(|| aliases.get(value).copied())
+                        .ok_or_else(|| AppError::UnknownTransactionType {
+                            line: lineno,
+                            name: value.to_string(),
+                        })?
                 }
-                "client" => transaction.cid = ClientId(value.parse()?),
-                "tx" => transaction.tid = TransactionId(value.parse()?),
+                "client" => transaction.cid = ClientId(value.parse().map_err(malformed)?),
+                "tx" => transaction.tid = TransactionId(value.parse().map_err(malformed)?),
                 "amount" => {
                     transaction.amount = match transaction.ttype {
                         TransactionType::Deposit | TransactionType::Withdrawal => {
-                            Some(Currency::from_str(&value)?)
+                            Some(Currency::from_str(value)?)
                         }
                         _ => None,
                     }
                 },
-                header => panic!("unknown header '{}'", header),
+                header => {
+                    return Err(AppError::UnknownCsvHeader { header: header.to_string() })
+                }
             }
         }
         Ok(transaction)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum TransactionType {
     #[serde(rename = "deposit")]
     Deposit,
@@ -525,6 +2428,94 @@ pub enum TransactionType {
     Chargeback,
 }
 
+impl TransactionType {
+    #[rustfmt::skip]
+    /// The name of the transaction type, as used in `CSV` input.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Deposit    => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Dispute    => "dispute",
+            Self::Resolve    => "resolve",
+            Self::Chargeback => "chargeback",
+        }
+    }
+
+    #[rustfmt::skip]
+    /// The transaction type with the given `name`, as used in `CSV` input.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "deposit"    => Some(Self::Deposit),
+            "withdrawal" => Some(Self::Withdrawal),
+            "dispute"    => Some(Self::Dispute),
+            "resolve"    => Some(Self::Resolve),
+            "chargeback" => Some(Self::Chargeback),
+            _            => None,
+        }
+    }
+}
+
+/// Read the rows of the `CSV` data produced by `reader`, along with their
+/// line numbers, resolving the type `aliases`.
+pub(crate) async fn read_csv_rows<'r, R>(
+    reader: R,
+    aliases: BTreeMap<String, TransactionType>,
+) -> AppResult<impl Stream<Item = AppResult<(Option<u64>, Transaction)>> + 'r>
+where
+    R: AsyncRead + Unpin + Send + Sync + 'r,
+{
+    let mut reader = AsyncReaderBuilder::new()
+        .trim(csv_async::Trim::All) // Allow nicely aligned columns
+        .flexible(true) // Allow rows of type dispute, resolve & chargeback
+        .comment(Some(b'#')) // Allow #-prefixed line comments
+        .create_reader(reader);
+    let headers = reader.headers().await?.clone();
+    let type_column = headers.iter().position(|header| header == "type");
+    Ok(reader.into_records().map(move |csv_async_result| {
+        let record = csv_async_result?;
+        let line = record.position().map_or(0, csv_async::Position::line);
+        let name = type_column.and_then(|idx| record.get(idx)).unwrap_or("");
+        if TransactionType::from_name(name).is_none() && !aliases.contains_key(name) {
+            let name = name.to_string();
+            return Err(AppError::UnknownTransactionType { line, name });
+        }
+        let transaction = resolve_type_alias(record, type_column, &aliases)
+            .deserialize::<Transaction>(Some(&headers))?;
+        Ok((Some(line), transaction))
+    }))
+}
+
+/// Replace the alias in the `type_column` of `record`, if any, with the name
+/// of the `TransactionType` that `aliases` maps it to. The names of the
+/// transaction types themselves can't be aliased.
+pub(crate) fn resolve_type_alias(
+    record: StringRecord,
+    type_column: Option<usize>,
+    aliases: &BTreeMap<String, TransactionType>,
+) -> StringRecord {
+    let name = match type_column.and_then(|idx| record.get(idx)) {
+        Some(name) if TransactionType::from_name(name).is_none() => name,
+        _ => return record,
+    };
+    let ttype = match aliases.get(name) {
+        Some(ttype) => *ttype,
+        None => return record,
+    };
+    let mut resolved: StringRecord = record
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            if Some(idx) == type_column {
+                ttype.name()
+            } else {
+                field
+            }
+        })
+        .collect();
+    resolved.set_position(record.position().cloned());
+    resolved
+}
+
 impl Default for TransactionType {
     #[inline(always)]
     fn default() -> Self {
@@ -532,7 +2523,8 @@ impl Default for TransactionType {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
 pub struct ClientId(pub(crate) u16); // Newtyped for type safety reasons
 
 impl fmt::Debug for ClientId {
@@ -546,8 +2538,22 @@ impl fmt::Debug for ClientId {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
-pub struct TransactionId(u32); // Newtyped for type safety reasons
+impl From<u16> for ClientId {
+    #[inline(always)]
+    fn from(cid: u16) -> Self {
+        Self(cid)
+    }
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct TransactionId(pub(crate) u32); // Newtyped for type safety reasons
 
 impl fmt::Debug for TransactionId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -560,6 +2566,19 @@ impl fmt::Debug for TransactionId {
     }
 }
 
+impl From<u32> for TransactionId {
+    #[inline(always)]
+    fn from(tid: u32) -> Self {
+        Self(tid)
+    }
+}
+
+impl fmt::Display for TransactionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[cfg(feature = "async_file_reads")]
 /// Find a `needle` in a `haystack`.
 fn find(needle: &[u8], haystack: &[u8]) -> Option<usize> {
@@ -1,5 +1,13 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use super::*;
+use crate::config::{
+    read_client_id_map, AmountScalePolicy, ClientIdMap, Config, DisputeSemantics, FreezePolicy,
+    Limits, ManualUnlockPolicy, OrderingPolicy, OrderingViolation, RepaymentPolicy,
+    RetentionPolicy, ScaleViolation, UnfreezePolicy, UnknownClientPolicy,
+};
 use crate::error::TransactionError;
+use crate::test_util::temp::TempPath;
 
 #[tokio::test]
 async fn deposit_to_new_account() -> AppResult<()> {
@@ -19,16 +27,26 @@ async fn deposit_to_new_account() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("1.23476")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("1.23476")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![(
@@ -44,6 +62,12 @@ async fn deposit_to_new_account() -> AppResult<()> {
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -66,16 +90,26 @@ async fn deposit_to_preexisting_account() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("1.23476")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("1.23476")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![(
@@ -91,6 +125,12 @@ async fn deposit_to_preexisting_account() -> AppResult<()> {
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -99,7 +139,7 @@ async fn deposit_to_locked_account() -> AppResult<()> {
     let mut transactor = Transactor::new();
     transactor.ensure_client_account_exists(ClientId(1)).await?;
     let account = transactor.account_mut(ClientId(1)).await?;
-    account.freeze();
+    account.freeze(FreezeTrigger::Watchlist);
     let transactions = vec![Transaction {
         ttype: TransactionType::Deposit,
         cid: ClientId(1),
@@ -142,16 +182,26 @@ async fn successive_deposits() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("50.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("50.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![
@@ -178,6 +228,12 @@ async fn successive_deposits() -> AppResult<()> {
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -205,20 +261,36 @@ async fn withdraw_from_new_account() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("0.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("0.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 1);
     assert_eq!(processed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, false);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -227,7 +299,7 @@ async fn withdraw_from_locked_account() -> AppResult<()> {
     let mut transactor = Transactor::new();
     transactor.ensure_client_account_exists(ClientId(1)).await?;
     let account = transactor.account_mut(ClientId(1)).await?;
-    account.freeze();
+    account.freeze(FreezeTrigger::Watchlist);
     let transactions = vec![Transaction {
         ttype: TransactionType::Withdrawal,
         cid: ClientId(1),
@@ -268,20 +340,36 @@ async fn withdraw_from_preexisting_account_with_insufficient_funds() -> AppResul
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("0.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("0.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 1);
     assert_eq!(processed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, false);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -313,16 +401,26 @@ async fn withdraw_from_preexisting_account_with_sufficient_funds() -> AppResult<
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("8.9975")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("8.9975")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![
@@ -349,6 +447,12 @@ async fn withdraw_from_preexisting_account_with_sufficient_funds() -> AppResult<
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -386,16 +490,26 @@ async fn successively_withdraw_from_preexisting_account() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("8.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("8.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![
@@ -431,6 +545,12 @@ async fn successively_withdraw_from_preexisting_account() -> AppResult<()> {
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -460,20 +580,36 @@ async fn dispute_nonexistent_transaction() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("0.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("0.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(processed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, false);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -504,16 +640,26 @@ async fn dispute_existent_transaction() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("0.0000")?);
     assert_eq!(*held, Currency::from_str("10.0000")?);
     assert_eq!(*total, Currency::from_str("10.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(processed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(
         disputed_transactions.iter().collect::<Vec<_>>(),
@@ -529,6 +675,18 @@ async fn dispute_existent_transaction() -> AppResult<()> {
     );
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(
+        dispute_timelines[&TransactionId(1)]
+            .iter()
+            .map(|link| link.to)
+            .collect::<Vec<_>>(),
+        vec![TransactionState::Disputed]
+    );
     Ok(())
 }
 
@@ -537,7 +695,7 @@ async fn dispute_using_locked_account() -> AppResult<()> {
     let mut transactor = Transactor::new();
     transactor.ensure_client_account_exists(ClientId(1)).await?;
     let account = transactor.account_mut(ClientId(1)).await?;
-    account.freeze();
+    account.freeze(FreezeTrigger::Watchlist);
     let transactions = vec![Transaction {
         ttype: TransactionType::Dispute,
         cid: ClientId(1),
@@ -579,20 +737,36 @@ async fn resolve_nonexistent_disputed_transaction() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("0.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("0.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(processed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, false);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -635,16 +809,26 @@ async fn resolve_existent_disputed_transaction() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("5.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("5.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![(
@@ -671,6 +855,18 @@ async fn resolve_existent_disputed_transaction() -> AppResult<()> {
         )]
     );
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(
+        dispute_timelines[&TransactionId(2)]
+            .iter()
+            .map(|link| link.to)
+            .collect::<Vec<_>>(),
+        vec![TransactionState::Disputed, TransactionState::Resolved]
+    );
     Ok(())
 }
 
@@ -679,7 +875,7 @@ async fn resolve_using_locked_account() -> AppResult<()> {
     let mut transactor = Transactor::new();
     transactor.ensure_client_account_exists(ClientId(1)).await?;
     let account = transactor.account_mut(ClientId(1)).await?;
-    account.freeze();
+    account.freeze(FreezeTrigger::Watchlist);
     let transactions = vec![Transaction {
         ttype: TransactionType::Resolve,
         cid: ClientId(1),
@@ -721,20 +917,36 @@ async fn chargeback_nonexistent_disputed_transaction() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("0.0000")?);
     assert_eq!(*held, Currency::from_str("0.0000")?);
     assert_eq!(*total, Currency::from_str("0.0000")?);
     assert_eq!(*is_locked, false);
+    assert_eq!(*freeze_trigger, None);
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(processed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(disputed_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(resolved_transactions.iter().collect::<Vec<_>>(), vec![]);
     assert_eq!(charged_back_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, false);
+    assert_eq!(dispute_timelines.len(), 0);
     Ok(())
 }
 
@@ -783,16 +995,31 @@ async fn chargeback_existent_disputed_transaction() -> AppResult<()> {
         held,
         total,
         is_locked,
+        freeze_trigger,
+        rejected_withdrawals_in_a_row,
         processed_transactions,
         disputed_transactions,
         resolved_transactions,
         charged_back_transactions,
+        voided_transactions,
+        dispute_timelines,
+        repaid,
+        sealed_transactions,
+        evicted_through,
+        has_applied_transactions,
     } = transactor.accounts.get(&ClientId(1)).unwrap();
     assert_eq!(*id, ClientId(1));
     assert_eq!(*available, Currency::from_str("5.0000")?);
     assert_eq!(*held, Currency::from_str("-5.0000")?);
     assert_eq!(*total, Currency::from_str("0.0000")?);
     assert_eq!(*is_locked, true);
+    assert_eq!(
+        *freeze_trigger,
+        Some(FreezeTrigger::Chargeback {
+            tid: TransactionId(2)
+        })
+    );
+    assert_eq!(*rejected_withdrawals_in_a_row, 0);
     assert_eq!(
         processed_transactions.iter().collect::<Vec<_>>(),
         vec![(
@@ -819,6 +1046,22 @@ async fn chargeback_existent_disputed_transaction() -> AppResult<()> {
             }
         )]
     );
+    assert_eq!(voided_transactions.iter().collect::<Vec<_>>(), vec![]);
+    assert_eq!(*repaid, Currency::ZERO);
+    assert_eq!(sealed_transactions.len(), 0);
+    assert_eq!(*evicted_through, None);
+    assert_eq!(*has_applied_transactions, true);
+    assert_eq!(
+        dispute_timelines[&TransactionId(2)]
+            .iter()
+            .map(|link| link.to)
+            .collect::<Vec<_>>(),
+        vec![
+            TransactionState::Disputed,
+            TransactionState::Resolved,
+            TransactionState::ChargedBack
+        ]
+    );
     Ok(())
 }
 
@@ -827,7 +1070,7 @@ async fn chargeback_using_locked_account() -> AppResult<()> {
     let mut transactor = Transactor::new();
     transactor.ensure_client_account_exists(ClientId(1)).await?;
     let account = transactor.account_mut(ClientId(1)).await?;
-    account.freeze();
+    account.freeze(FreezeTrigger::Watchlist);
     let transactions = vec![Transaction {
         ttype: TransactionType::Chargeback,
         cid: ClientId(1),
@@ -842,3 +1085,2038 @@ async fn chargeback_using_locked_account() -> AppResult<()> {
     }
     Ok(())
 }
+
+#[tokio::test]
+async fn paginate_history_across_states() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("5.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let first = transactor.history_page(ClientId(1), None, 2).unwrap();
+    assert_eq!(
+        first
+            .entries
+            .iter()
+            .map(|entry| (entry.transaction.tid, entry.state))
+            .collect::<Vec<_>>(),
+        vec![
+            (TransactionId(1), TransactionState::Processed),
+            (TransactionId(2), TransactionState::Disputed),
+        ]
+    );
+    assert_eq!(first.next, Some(TransactionId(2)));
+    let second = transactor.history_page(ClientId(1), first.next, 2).unwrap();
+    assert_eq!(
+        second
+            .entries
+            .iter()
+            .map(|entry| (entry.transaction.tid, entry.state))
+            .collect::<Vec<_>>(),
+        vec![(TransactionId(3), TransactionState::Processed)]
+    );
+    assert_eq!(second.next, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn paginate_history_of_unknown_client() -> AppResult<()> {
+    let transactor = Transactor::new();
+    assert_eq!(transactor.history_page(ClientId(1), None, 10), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_across_accounts() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(2),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("50.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(2),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("20.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(4),
+            amount: Some(Currency::from_str("30.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(4),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let tids = |entries: Vec<HistoryEntry>| {
+        entries
+            .iter()
+            .map(|entry| entry.transaction.tid)
+            .collect::<Vec<_>>()
+    };
+    let deposits_over_20 = TransactionQuery {
+        ttype: Some(TransactionType::Deposit),
+        min_amount: Some(Currency::from_str("20.0000")?),
+        ..TransactionQuery::default()
+    };
+    assert_eq!(
+        tids(transactor.search(&deposits_over_20)),
+        vec![TransactionId(4), TransactionId(2)]
+    );
+    let disputed = TransactionQuery {
+        state: Some(TransactionState::Disputed),
+        ..TransactionQuery::default()
+    };
+    assert_eq!(tids(transactor.search(&disputed)), vec![TransactionId(4)]);
+    let tid_range = TransactionQuery {
+        min_tid: Some(TransactionId(2)),
+        max_tid: Some(TransactionId(3)),
+        ..TransactionQuery::default()
+    };
+    assert_eq!(
+        tids(transactor.search(&tid_range)),
+        vec![TransactionId(2), TransactionId(3)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn record_transactions_exceeding_latency_budget() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        latency_budget: Some(Duration::ZERO),
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("20.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_transaction(transaction).await;
+    }
+    let slow_operations = transactor.slow_operations();
+    assert_eq!(slow_operations.len(), 2);
+    assert_eq!(slow_operations[0].transaction.tid, TransactionId(1));
+    assert_eq!(slow_operations[0].error, None);
+    assert_eq!(slow_operations[0].history_len, 1);
+    assert_eq!(slow_operations[1].transaction.tid, TransactionId(2));
+    assert_eq!(
+        slow_operations[1].error,
+        Some(TransactionError::AccountHasInsufficientFundsAvailable { cid: ClientId(1) })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_slow_operations_without_latency_budget() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transaction = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(1),
+        tid: TransactionId(1),
+        amount: Some(Currency::from_str("10.0000")?),
+    };
+    transactor.process_transaction(transaction).await?;
+    assert_eq!(transactor.slow_operations(), &[]);
+    Ok(())
+}
+
+#[test]
+fn display_ids_without_newtype_wrapper() {
+    assert_eq!(ClientId(42).to_string(), "42");
+    assert_eq!(TransactionId(4812).to_string(), "4812");
+    assert_eq!(format!("{:?}", ClientId(42)), "ClientId(42)");
+}
+
+#[tokio::test]
+async fn reject_out_of_order_transaction_ids() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        ordering: OrderingPolicy::MonotonicTid(OrderingViolation::Reject),
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(5),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(5),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let late = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(2),
+        tid: TransactionId(3),
+        amount: Some(Currency::from_str("1.0000")?),
+    };
+    assert_eq!(
+        transactor.process_transaction(late).await,
+        Err(TransactionError::TransactionIdOutOfOrder {
+            tid: TransactionId(3),
+            max_tid: TransactionId(5),
+        })
+    );
+    assert_eq!(transactor.accounts.get(&ClientId(2)), None);
+    assert_eq!(transactor.warnings(), &[]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn warn_on_out_of_order_transaction_ids() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        ordering: OrderingPolicy::MonotonicTid(OrderingViolation::Warn),
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(5),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("9.0000")?);
+    assert_eq!(
+        transactor.warnings(),
+        &[Warning::TransactionIdOutOfOrder {
+            tid: TransactionId(3),
+            max_tid: TransactionId(5),
+        }]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn park_early_dispute_until_deposit_arrives() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        dispute_grace_rows: 2,
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Resolve,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_row(transaction).await?;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("10.0000")?);
+    assert_eq!(account.held, Currency::from_str("0.0000")?);
+    assert_eq!(
+        account.resolved_transactions.keys().collect::<Vec<_>>(),
+        vec![&TransactionId(1)]
+    );
+    assert_eq!(transactor.parked_transactions, vec![]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn expire_parked_dispute_after_grace_rows() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        dispute_grace_rows: 1,
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("5.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_row(transaction).await?;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("15.0000")?);
+    assert_eq!(account.held, Currency::from_str("0.0000")?);
+    assert_eq!(account.disputed_transactions.len(), 0);
+    assert_eq!(transactor.parked_transactions, vec![]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn drop_accounts_opened_by_rejected_transactions() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(2),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(2),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_transaction(transaction).await;
+    }
+    assert_eq!(transactor.accounts.len(), 2);
+    assert_eq!(transactor.drop_empty_accounts(), 1);
+    assert_eq!(
+        transactor.accounts.keys().collect::<Vec<_>>(),
+        vec![&ClientId(2)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn keep_emptied_accounts_without_history() -> AppResult<()> {
+    let one = Currency::from_str("1.0")?;
+    for retention in [
+        RetentionPolicy {
+            history_free: true,
+            ..RetentionPolicy::default()
+        },
+        RetentionPolicy {
+            max_settled_per_account: Some(0),
+            ..RetentionPolicy::default()
+        },
+    ] {
+        let mut transactor = Transactor::with_config(Config {
+            retention,
+            ..Config::default()
+        });
+        for t in [
+            Transaction::deposit(ClientId(1), TransactionId(1), one),
+            Transaction::withdrawal(ClientId(1), TransactionId(2), one),
+        ] {
+            transactor.process_transaction(t).await?;
+        }
+        assert_eq!(transactor.drop_empty_accounts(), 0);
+        assert_eq!(transactor.accounts.len(), 1);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_transactions_for_unknown_clients() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        unknown_clients: UnknownClientPolicy::Reject,
+        ..Config::default()
+    });
+    let rejected = vec![
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+    ];
+    for transaction in rejected {
+        assert_eq!(
+            transactor.process_transaction(transaction).await,
+            Err(TransactionError::UnknownClient { cid: ClientId(1) })
+        );
+    }
+    assert_eq!(transactor.accounts.len(), 0);
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(1),
+        tid: TransactionId(2),
+        amount: Some(Currency::from_str("1.0000")?),
+    };
+    transactor.process_transaction(deposit).await?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("1.0000")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn void_processed_transactions() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("2.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("3.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    transactor
+        .void_transaction(ClientId(1), TransactionId(2))
+        .await?;
+    transactor
+        .void_transaction(ClientId(1), TransactionId(3))
+        .await?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("10.0000")?);
+    assert_eq!(account.total, Currency::from_str("10.0000")?);
+    assert_eq!(
+        account.processed_transactions.keys().collect::<Vec<_>>(),
+        vec![&TransactionId(1)]
+    );
+    assert_eq!(
+        account.voided_transactions.keys().collect::<Vec<_>>(),
+        vec![&TransactionId(2), &TransactionId(3)]
+    );
+    // NOTE: Voided transactions can't be disputed
+    let dispute = Transaction {
+        ttype: TransactionType::Dispute,
+        cid: ClientId(1),
+        tid: TransactionId(2),
+        amount: None,
+    };
+    assert_eq!(
+        transactor.process_transaction(dispute).await,
+        Err(TransactionError::NoSuchProcessedTransactionForClient {
+            tid: TransactionId(2),
+            cid: ClientId(1),
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn void_disputed_transaction() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    assert_eq!(
+        transactor
+            .void_transaction(ClientId(1), TransactionId(1))
+            .await,
+        Err(TransactionError::NoSuchProcessedTransactionForClient {
+            tid: TransactionId(1),
+            cid: ClientId(1),
+        })
+    );
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.held, Currency::from_str("10.0000")?);
+    assert_eq!(account.voided_transactions.len(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn enforce_max_accounts() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        limits: Limits {
+            max_accounts: Some(1),
+            ..Limits::default()
+        },
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let new_client = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(2),
+        tid: TransactionId(3),
+        amount: Some(Currency::from_str("1.0000")?),
+    };
+    let result = transactor.process_transaction(new_client).await;
+    assert_eq!(result, Err(TransactionError::TooManyAccounts { max: 1 }));
+    assert!(result.unwrap_err().is_fatal());
+    Ok(())
+}
+
+#[tokio::test]
+async fn enforce_max_open_disputes() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        limits: Limits {
+            max_open_disputes: Some(1),
+            ..Limits::default()
+        },
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let second_dispute = Transaction {
+        ttype: TransactionType::Dispute,
+        cid: ClientId(1),
+        tid: TransactionId(2),
+        amount: None,
+    };
+    assert_eq!(
+        transactor.process_transaction(second_dispute).await,
+        Err(TransactionError::TooManyOpenDisputes {
+            cid: ClientId(1),
+            max: 1
+        })
+    );
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.held, Currency::from_str("1.0000")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn enforce_max_resident_transactions() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        limits: Limits {
+            max_resident_transactions: Some(2),
+            ..Limits::default()
+        },
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("2.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(2),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_transaction(transaction).await;
+    }
+    assert_eq!(transactor.resident_transactions, 2);
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(1),
+        tid: TransactionId(4),
+        amount: Some(Currency::from_str("1.0000")?),
+    };
+    assert_eq!(
+        transactor.process_transaction(deposit).await,
+        Err(TransactionError::TooManyResidentTransactions { max: 2 })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn count_replaced_transactions_as_resident_once() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        limits: Limits {
+            max_resident_transactions: Some(2),
+            ..Limits::default()
+        },
+        ..Config::default()
+    });
+    let one = Currency::from_str("1.0000")?;
+    let transactions = vec![
+        Transaction::deposit(ClientId(1), TransactionId(1), one),
+        Transaction::deposit(ClientId(1), TransactionId(1), one),
+        Transaction::deposit(ClientId(1), TransactionId(2), one),
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    assert_eq!(transactor.resident_transactions, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn track_client_stats() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        dispute_grace_rows: 1,
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("5.0000")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Resolve,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(9),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_row(transaction).await;
+    }
+    // NOTE: The dispute of transaction 9 is still parked
+    let stats = transactor.client_stats(ClientId(1)).unwrap();
+    assert_eq!(stats.accepted, 3);
+    assert_eq!(stats.disputes_opened, 1);
+    assert_eq!(stats.disputes_resolved, 1);
+    assert_eq!(stats.rejected.get("insufficient_funds"), Some(&1));
+    assert_eq!(stats.rejected.get("no_such_processed_transaction"), None);
+    transactor.expire_parked_transactions(None);
+    let stats = transactor.client_stats(ClientId(1)).unwrap();
+    assert_eq!(
+        stats.rejected.get("no_such_processed_transaction"),
+        Some(&1)
+    );
+    assert_eq!(transactor.client_stats(ClientId(2)), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn process_csv_reader() -> AppResult<()> {
+    let csv = "type, client, tx, amount
+deposit, 1, 1, 2.0
+# A comment
+withdrawal, 1, 2, 0.5
+withdrawal, 1, 3, 9.0
+";
+    let mut transactor = Transactor::new();
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("1.5")?);
+    assert_eq!(account.total, Currency::from_str("1.5")?);
+    Ok(())
+}
+
+// NOTE: Reading files with `tokio-uring` requires its own runtime.
+#[cfg(not(feature = "async_file_reads"))]
+#[tokio::test]
+async fn process_csv_files() -> AppResult<()> {
+    let dir = TempPath::dir("files")?;
+    let first = dir.join("first.csv");
+    let second = dir.join("second.csv");
+    std::fs::write(&first, "type,client,tx,amount\ndeposit,1,1,2.0\n")?;
+    std::fs::write(&second, "type,client,tx,amount\nwithdrawal,1,2,0.5\n")?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_files(&[first, second]).await?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("1.5")?);
+    Ok(())
+}
+
+// NOTE: Reading files with `tokio-uring` requires its own runtime.
+#[cfg(feature = "async_file_reads")]
+#[test]
+fn process_csv_file_without_trailing_newline() -> AppResult<()> {
+    let path = TempPath::new("unterminated.csv");
+    std::fs::write(
+        &path,
+        "type,client,tx,amount\ndeposit,1,1,2.0\n# A comment\n\nwithdrawal,1,2,0.5",
+    )?;
+    let mut transactor = Transactor::new();
+    tokio_uring::start(transactor.process_csv_file(path.to_path_buf()))?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("1.5")?);
+    Ok(())
+}
+
+// NOTE: Reading files with `tokio-uring` requires its own runtime.
+#[cfg(feature = "async_file_reads")]
+#[test]
+fn skip_malformed_rows_in_csv_files() -> AppResult<()> {
+    let path = TempPath::new("malformed.csv");
+    let csv = "type,client,tx,amount
+deposit,1,1,2.0
+refund,1,2,1.0
+deposit,one,3,1.0
+# A comment
+
+withdrawal,1,4,0.5";
+    std::fs::write(&path, csv)?;
+    let mut transactor = Transactor::with_config(Config {
+        skip_malformed_rows: true,
+        ..Config::default()
+    });
+    tokio_uring::start(transactor.process_csv_file(path.to_path_buf()))?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("1.5")?);
+    let quality = &transactor.data_quality[0];
+    assert_eq!(quality.unknown_types, 1);
+    assert_eq!(quality.malformed_rows, 1);
+    Ok(())
+}
+
+#[cfg(feature = "async_file_reads")]
+#[tokio::test]
+async fn csv_line_errors() -> AppResult<()> {
+    let headers = ["type", "client", "tx", "amount"];
+    let aliases = BTreeMap::new();
+    let unknown_type = Transaction::from_csv_line(&headers, "refund,1,2,1.0", 3, &aliases).await;
+    assert!(matches!(
+        unknown_type,
+        Err(AppError::UnknownTransactionType { line: 3, ref name }) if name == "refund"
+    ));
+    let malformed = Transaction::from_csv_line(&headers, "deposit,one,2,1.0", 4, &aliases).await;
+    assert!(matches!(malformed, Err(AppError::MalformedRow { line: 4 })));
+    let headers = ["type", "client", "tx", "fee"];
+    let unknown_header = Transaction::from_csv_line(&headers, "deposit,1,2,1.0", 2, &aliases).await;
+    assert!(matches!(
+        unknown_header,
+        Err(AppError::UnknownCsvHeader { ref header }) if header == "fee"
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn freeze_after_rejected_withdrawals() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        freeze: FreezePolicy {
+            max_rejected_withdrawals: Some(2),
+            ..FreezePolicy::default()
+        },
+        ..Config::default()
+    });
+    let withdrawal = |tid, amount| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(tid),
+            amount: Some(Currency::from_str(amount)?),
+        })
+    };
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0")?),
+        },
+        withdrawal(2, "5.0")?,
+        withdrawal(3, "0.5")?, // Resets the count
+        withdrawal(4, "5.0")?,
+        withdrawal(5, "5.0")?,
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_transaction(transaction).await;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.is_locked, true);
+    assert_eq!(
+        account.freeze_trigger,
+        Some(FreezeTrigger::RejectedWithdrawals { count: 2 })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn freeze_below_balance_floor() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        freeze: FreezePolicy {
+            balance_floor: Some(Currency::from_str("1.0")?),
+            ..FreezePolicy::default()
+        },
+        ..Config::default()
+    });
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("2.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("0.5")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.total, Currency::from_str("0.5")?);
+    assert_eq!(
+        account.freeze_trigger,
+        Some(FreezeTrigger::BalanceBelowFloor {
+            floor: Currency::from_str("1.0")?
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn freeze_watchlisted_clients() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        freeze: FreezePolicy {
+            watchlist: vec![ClientId(2)].into_iter().collect(),
+            ..FreezePolicy::default()
+        },
+        ..Config::default()
+    });
+    for cid in [ClientId(1), ClientId(2)].iter() {
+        let deposit = Transaction {
+            ttype: TransactionType::Deposit,
+            cid: *cid,
+            tid: TransactionId(cid.0.into()),
+            amount: Some(Currency::from_str("1.0")?),
+        };
+        let _ = transactor.process_transaction(deposit).await;
+    }
+    assert_eq!(
+        transactor.frozen_accounts(),
+        vec![(ClientId(2), Some(&FreezeTrigger::Watchlist))]
+    );
+    let account = transactor.accounts.get(&ClientId(2)).unwrap();
+    assert_eq!(account.total, Currency::ZERO);
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_output_with_four_decimal_places() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let mut output = vec![];
+    transactor.write_output(&mut output).await?;
+    assert_eq!(output, b"client,available,held,total,locked\n");
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.5")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(2),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.23479")?),
+        },
+    ];
+    for transaction in transactions {
+        transactor.process_transaction(transaction).await?;
+    }
+    let mut output = vec![];
+    transactor.write_output(&mut output).await?;
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "client,available,held,total,locked\n\
+         1,1.5000,0.0000,1.5000,false\n\
+         2,1.2347,0.0000,1.2347,false\n"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_output_jsonl() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let mut output = vec![];
+    transactor.write_output_jsonl(&mut output).await?;
+    assert!(output.is_empty());
+    for (cid, tid, amount) in [(1, 1, "1.5"), (2, 2, "1.23479")] {
+        transactor
+            .process_transaction(Transaction {
+                ttype: TransactionType::Deposit,
+                cid: ClientId(cid),
+                tid: TransactionId(tid),
+                amount: Some(Currency::from_str(amount)?),
+            })
+            .await?;
+    }
+    transactor.write_output_jsonl(&mut output).await?;
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\"client\":1,\"available\":\"1.5000\",\"held\":\"0.0000\",\"total\":\"1.5000\",\"locked\":false}\n\
+         {\"client\":2,\"available\":\"1.2347\",\"held\":\"0.0000\",\"total\":\"1.2347\",\"locked\":false}\n"
+    );
+    Ok(())
+}
+
+/// Deposit 10, withdraw 5, then dispute, resolve and charge back the
+/// withdrawal, which freezes the account of client 1 at row 5.
+fn chargeback_transactions() -> AppResult<Vec<Transaction>> {
+    Ok(vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("10.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("5.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Resolve,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: None,
+        },
+        Transaction {
+            ttype: TransactionType::Chargeback,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: None,
+        },
+    ])
+}
+
+#[tokio::test]
+async fn unfreeze_after_cooling_off_period() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        unfreeze: UnfreezePolicy {
+            cooling_off_rows: Some(2),
+            ..UnfreezePolicy::default()
+        },
+        ..Config::default()
+    });
+    for transaction in chargeback_transactions()? {
+        transactor.process_row(transaction).await?;
+    }
+    let deposit = |cid, tid| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(cid),
+            tid: TransactionId(tid),
+            amount: Some(Currency::from_str("1.0")?),
+        })
+    };
+    // NOTE: The rejected deposit at row 6 restarts the period
+    let _ = transactor.process_row(deposit(1, 3)?).await;
+    transactor.process_row(deposit(2, 4)?).await?;
+    assert_eq!(
+        transactor.accounts.get(&ClientId(1)).unwrap().is_locked,
+        true
+    );
+    transactor.process_row(deposit(2, 5)?).await?;
+    assert_eq!(
+        transactor.accounts.get(&ClientId(1)).unwrap().is_locked,
+        false
+    );
+    transactor.process_row(deposit(1, 6)?).await?;
+    assert_eq!(
+        transactor.account_events(),
+        &[
+            AccountEvent {
+                row: 5,
+                cid: ClientId(1),
+                change: AccountChange::Frozen(FreezeTrigger::Chargeback {
+                    tid: TransactionId(2)
+                }),
+            },
+            AccountEvent {
+                row: 8,
+                cid: ClientId(1),
+                change: AccountChange::Unfrozen(UnfreezeReason::CoolingOff),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn unfreeze_on_matching_resolve() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        unfreeze: UnfreezePolicy {
+            on_matching_resolve: true,
+            ..UnfreezePolicy::default()
+        },
+        ..Config::default()
+    });
+    for transaction in chargeback_transactions()? {
+        transactor.process_row(transaction).await?;
+    }
+    let resolve = |tid| Transaction {
+        ttype: TransactionType::Resolve,
+        cid: ClientId(1),
+        tid: TransactionId(tid),
+        amount: None,
+    };
+    assert_eq!(
+        transactor.process_row(resolve(1)).await,
+        Err(TransactionError::AccountIsLocked { cid: ClientId(1) })
+    );
+    transactor.process_row(resolve(2)).await?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.is_locked, false);
+    assert_eq!(account.total, Currency::from_str("0.0")?);
+    assert_eq!(
+        transactor.account_events().last(),
+        Some(&AccountEvent {
+            row: 7,
+            cid: ClientId(1),
+            change: AccountChange::Unfrozen(UnfreezeReason::MatchingResolve {
+                tid: TransactionId(2)
+            }),
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn unlock_account_manually() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    assert_eq!(
+        transactor.unlock_account(ClientId(1)),
+        Err(TransactionError::UnknownClient { cid: ClientId(1) })
+    );
+    for transaction in chargeback_transactions()? {
+        transactor.process_row(transaction).await?;
+    }
+    transactor.unlock_account(ClientId(1))?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.is_locked, false);
+    assert_eq!(account.freeze_trigger, None);
+    assert_eq!(
+        transactor.account_events().last(),
+        Some(&AccountEvent {
+            row: 5,
+            cid: ClientId(1),
+            change: AccountChange::Unfrozen(UnfreezeReason::Manual),
+        })
+    );
+    assert_eq!(
+        transactor.unlock_account(ClientId(1)),
+        Err(TransactionError::AccountIsNotLocked { cid: ClientId(1) })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn unlock_account_as_far_as_permitted() -> AppResult<()> {
+    let transactor_with = |manual| {
+        Transactor::with_config(Config {
+            unfreeze: UnfreezePolicy {
+                manual,
+                ..UnfreezePolicy::default()
+            },
+            ..Config::default()
+        })
+    };
+    // NOTE: Charging back the deposit leaves the account with negative
+    //       total funds, since part of it was withdrawn in the meantime.
+    let transaction = |ttype, tid, amount: Option<&str>| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype,
+            cid: ClientId(1),
+            tid: TransactionId(tid),
+            amount: amount.map(Currency::from_str).transpose()?,
+        })
+    };
+    let transactions = vec![
+        transaction(TransactionType::Deposit, 1, Some("10.0"))?,
+        transaction(TransactionType::Withdrawal, 2, Some("8.0"))?,
+        transaction(TransactionType::Dispute, 1, None)?,
+        transaction(TransactionType::Resolve, 1, None)?,
+        transaction(TransactionType::Chargeback, 1, None)?,
+    ];
+    let mut disabled = transactor_with(ManualUnlockPolicy::Disabled);
+    let mut guarded = transactor_with(ManualUnlockPolicy::RequireNonNegativeTotal);
+    for transaction in transactions {
+        disabled.process_row(transaction).await?;
+        guarded.process_row(transaction).await?;
+    }
+    assert_eq!(
+        guarded.accounts.get(&ClientId(1)).unwrap().total,
+        Currency::from_str("-8.0")?
+    );
+    for transactor in [&mut disabled, &mut guarded] {
+        assert_eq!(
+            transactor.unlock_account(ClientId(1)),
+            Err(TransactionError::UnlockNotPermitted { cid: ClientId(1) })
+        );
+        assert_eq!(
+            transactor.accounts.get(&ClientId(1)).unwrap().is_locked,
+            true
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_operations_interleave_with_rows() -> AppResult<()> {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    let transactor = Arc::new(Mutex::new(Transactor::new()));
+    let stream = {
+        let transactor = transactor.clone();
+        tokio::spawn(async move {
+            for tid in 1..=100 {
+                let amount = Currency::from_str("1.0").unwrap();
+                let deposit = Transaction::deposit(ClientId(1), TransactionId(tid), amount);
+                let _ = transactor.lock().await.process_row(deposit).await;
+                let _ = tokio::task::yield_now().await;
+            }
+        })
+    };
+    let admin = {
+        let transactor = transactor.clone();
+        tokio::spawn(async move {
+            for tid in (1..=100).step_by(2) {
+                // NOTE: Retry until the stream has delivered the deposit.
+                loop {
+                    let mut transactor = transactor.lock().await;
+                    let voided = transactor
+                        .void_transaction(ClientId(1), TransactionId(tid))
+                        .await;
+                    drop(transactor);
+                    match voided {
+                        Ok(()) => break,
+                        Err(_) => tokio::task::yield_now().await,
+                    }
+                }
+            }
+        })
+    };
+    stream.await?;
+    admin.await?;
+    let transactor = transactor.lock().await;
+    assert_eq!(transactor.audit(), vec![]);
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("50.0")?);
+    assert_eq!(account.total, Currency::from_str("50.0")?);
+    assert_eq!(account.voided_transactions.len(), 50);
+    Ok(())
+}
+
+#[tokio::test]
+async fn record_rejected_rows() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               withdrawal,1,2,5.0\n\
+               dispute,2,7,\n";
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    let rejected: Vec<(Transaction, TransactionError)> = transactor
+        .rejected()
+        .map(|ignored| (*ignored.transaction(), ignored.reason().clone()))
+        .collect();
+    assert_eq!(
+        rejected,
+        vec![
+            (
+                Transaction::withdrawal(ClientId(1), TransactionId(2), Currency::from_str("5.0")?),
+                TransactionError::AccountHasInsufficientFundsAvailable { cid: ClientId(1) }
+            ),
+            (
+                Transaction::dispute(ClientId(2), TransactionId(7)),
+                TransactionError::NoSuchProcessedTransactionForClient {
+                    tid: TransactionId(7),
+                    cid: ClientId(2)
+                }
+            ),
+        ]
+    );
+    let lines: Vec<String> = transactor.rejected().map(|i| i.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "line 3: withdrawal for client 1 rejected: insufficient funds",
+            "line 4: dispute for client 2 rejected: no such processed transaction",
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn report_line_of_fatal_error() -> AppResult<()> {
+    let mut transactor = Transactor::with_config(Config {
+        limits: Limits {
+            max_accounts: Some(1),
+            ..Limits::default()
+        },
+        ..Config::default()
+    });
+    let csv = "type,client,tx,amount\n\
+               # The second client exceeds the limit\n\
+               deposit,1,1,1.0\n\
+               deposit,2,2,1.0\n";
+    assert!(matches!(
+        transactor.process_csv_reader(csv.as_bytes()).await,
+        Err(AppError::TransactionErrorAtLine {
+            line: 4,
+            error: TransactionError::TooManyAccounts { max: 1 },
+        })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn enforce_amount_scale() -> AppResult<()> {
+    let transactor_with = |on_violation| {
+        Transactor::with_config(Config {
+            amount_scale: AmountScalePolicy {
+                max_scale: Some(4),
+                on_violation,
+            },
+            ..Config::default()
+        })
+    };
+    let deposit = |tid, amount| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(tid),
+            amount: Some(Currency::from_str(amount)?),
+        })
+    };
+    let mut rejecting = transactor_with(ScaleViolation::Reject);
+    rejecting.process_row(deposit(1, "1.2345")?).await?;
+    assert_eq!(
+        rejecting.process_row(deposit(2, "1.23456")?).await,
+        Err(TransactionError::AmountScaleExceeded {
+            tid: TransactionId(2),
+            scale: 5,
+            max_scale: 4,
+        })
+    );
+    let account = rejecting.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.total, Currency::from_str("1.2345")?);
+    let mut normalizing = transactor_with(ScaleViolation::Truncate);
+    normalizing.process_row(deposit(1, "1.23456")?).await?;
+    let account = normalizing.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.total, Currency::from_str("1.2345")?);
+    assert_eq!(
+        account.processed_transactions[&TransactionId(1)].amount,
+        Some(Currency::from_str("1.2345")?)
+    );
+    assert_eq!(
+        normalizing.warnings(),
+        &[Warning::AmountScaleNormalized {
+            tid: TransactionId(1),
+            scale: 5,
+            max_scale: 4,
+        }]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn round_amounts_half_even() -> AppResult<()> {
+    let deposit = |tid, amount| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(tid as u16),
+            tid: TransactionId(tid),
+            amount: Some(Currency::from_str(amount)?),
+        })
+    };
+    let mut rounding = Transactor::with_config(Config {
+        amount_scale: AmountScalePolicy::four_decimal_places(ScaleViolation::RoundHalfEven),
+        ..Config::default()
+    });
+    rounding.process_row(deposit(1, "1.23455")?).await?;
+    rounding.process_row(deposit(2, "1.23445")?).await?;
+    rounding.process_row(deposit(3, "1.234451")?).await?;
+    let totals = |transactor: &Transactor| -> Vec<String> {
+        transactor
+            .account_summaries()
+            .map(|summary| summary.total.to_output_string())
+            .collect()
+    };
+    let totals_rounded = totals(&rounding);
+    assert_eq!(totals_rounded, vec!["1.2346", "1.2344", "1.2345"]);
+    assert_eq!(rounding.warnings().len(), 3);
+    // NOTE: Without a maximum scale, amounts are kept as they are, but the
+    //       account summary is still rounded rather than truncated.
+    let mut unlimited = Transactor::with_config(Config {
+        amount_scale: AmountScalePolicy {
+            max_scale: None,
+            on_violation: ScaleViolation::RoundHalfEven,
+        },
+        ..Config::default()
+    });
+    unlimited.process_row(deposit(1, "1.23456")?).await?;
+    let account = unlimited.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.total, Currency::from_str("1.23456")?);
+    assert_eq!(totals(&unlimited), vec!["1.2346"]);
+    assert_eq!(unlimited.warnings(), &[]);
+    let mut truncating = Transactor::new();
+    truncating.process_row(deposit(1, "1.23456")?).await?;
+    assert_eq!(totals(&truncating), vec!["1.2345"]);
+    Ok(())
+}
+
+#[test]
+fn currency_with_scale() -> AppResult<()> {
+    let amount = Currency::from_str("-2.50005")?;
+    assert_eq!(amount.with_scale(4, ScaleViolation::Reject), None);
+    assert_eq!(
+        amount.with_scale(4, ScaleViolation::Truncate),
+        Some(Currency::from_str("-2.5000")?)
+    );
+    assert_eq!(
+        amount.with_scale(4, ScaleViolation::RoundHalfEven),
+        Some(Currency::from_str("-2.5000")?)
+    );
+    assert_eq!(amount.with_scale(5, ScaleViolation::Reject), Some(amount));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_overflowing_amounts() -> AppResult<()> {
+    let max = "79228162514264337593543950335";
+    let mut harness = crate::test_util::TestHarness::new();
+    harness.deposit(1, 1, max).await.expect_accepted();
+    harness
+        .deposit(1, 2, "1")
+        .await
+        .expect_rejected(TransactionError::AmountOverflow { cid: ClientId(1) })
+        .expect_available(1, max)
+        .expect_total(1, max);
+    harness
+        .withdraw(1, 3, "1")
+        .await
+        .expect_accepted()
+        .deposit(1, 4, "1")
+        .await
+        .expect_accepted()
+        .expect_total(1, max);
+    Ok(())
+}
+
+#[test]
+fn currency_arithmetic() -> AppResult<()> {
+    let amounts = [
+        Currency::from_str("1.5")?,
+        Currency::from_str("2.25")?,
+        Currency::from_str("-0.75")?,
+    ];
+    assert_eq!(amounts.iter().sum::<Currency>(), Currency::from_str("3")?);
+    let mut sum = Currency::ZERO;
+    sum += amounts[0];
+    assert_eq!(-sum, Currency::from_str("-1.5")?);
+    assert_eq!(sum * Decimal::new(3, 1), Currency::from_str("0.45")?);
+    let max = Currency(Decimal::MAX);
+    assert_eq!(max.checked_add(amounts[0]), None);
+    assert_eq!(
+        max.checked_sub(amounts[0]),
+        Some(Currency(Decimal::MAX - amounts[0].0))
+    );
+    assert_eq!(max.checked_mul(Decimal::new(2, 0)), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn record_dispute_timeline() -> AppResult<()> {
+    let mut harness = crate::test_util::TestHarness::new();
+    harness
+        .deposit(1, 1, "3.0")
+        .await
+        .deposit(1, 2, "1.0")
+        .await
+        .dispute(1, 1)
+        .await
+        .dispute(1, 3)
+        .await
+        .resolve(1, 1)
+        .await
+        .chargeback(1, 1)
+        .await
+        .expect_accepted();
+    let transactor = harness.transactor();
+    let link = |ttype, row, from, to| DisputeLink {
+        tid: TransactionId(1),
+        ttype,
+        row,
+        from,
+        to,
+    };
+    let timeline = vec![
+        link(
+            TransactionType::Dispute,
+            3,
+            TransactionState::Processed,
+            TransactionState::Disputed,
+        ),
+        link(
+            TransactionType::Resolve,
+            5,
+            TransactionState::Disputed,
+            TransactionState::Resolved,
+        ),
+        link(
+            TransactionType::Chargeback,
+            6,
+            TransactionState::Resolved,
+            TransactionState::ChargedBack,
+        ),
+    ];
+    assert_eq!(
+        transactor.dispute_timeline(ClientId(1), TransactionId(1)),
+        &timeline[..]
+    );
+    assert_eq!(
+        transactor.dispute_timeline(ClientId(1), TransactionId(2)),
+        &[]
+    );
+    let page = transactor.history_page(ClientId(1), None, 10).unwrap();
+    assert_eq!(page.entries[0].state, TransactionState::ChargedBack);
+    assert_eq!(page.entries[0].timeline, timeline);
+    assert_eq!(page.entries[1].timeline, vec![]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn dispute_withdrawals_direction_aware() -> AppResult<()> {
+    let config = Config {
+        dispute_semantics: DisputeSemantics::DirectionAware,
+        ..Config::default()
+    };
+    let mut harness = crate::test_util::TestHarness::with_config(config);
+    harness
+        .deposit(1, 1, "10.0")
+        .await
+        .withdraw(1, 2, "4.0")
+        .await
+        .dispute(1, 2)
+        .await
+        .expect_accepted()
+        .expect_available(1, "6.0")
+        .expect_held(1, "4.0")
+        .expect_total(1, "10.0")
+        .resolve(1, 2)
+        .await
+        .expect_accepted()
+        .expect_available(1, "6.0")
+        .expect_held(1, "0.0")
+        .expect_total(1, "6.0")
+        .chargeback(1, 2)
+        .await
+        .expect_accepted()
+        .expect_available(1, "10.0")
+        .expect_held(1, "0.0")
+        .expect_total(1, "10.0")
+        .expect_locked(1, true);
+    // NOTE: Disputes of deposits are handled as they always have been.
+    harness
+        .deposit(2, 3, "5.0")
+        .await
+        .dispute(2, 3)
+        .await
+        .expect_available(2, "0.0")
+        .expect_held(2, "5.0")
+        .expect_total(2, "5.0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn dispute_withdrawals_legacy() -> AppResult<()> {
+    let mut harness = crate::test_util::TestHarness::new();
+    harness
+        .deposit(1, 1, "10.0")
+        .await
+        .withdraw(1, 2, "4.0")
+        .await
+        .dispute(1, 2)
+        .await
+        .expect_accepted()
+        .expect_available(1, "2.0")
+        .expect_held(1, "4.0")
+        .expect_total(1, "6.0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn sweep_deposits_against_negative_held_funds() -> AppResult<()> {
+    let config = Config {
+        repayment: RepaymentPolicy {
+            sweep_deposits: true,
+        },
+        ..Config::default()
+    };
+    let mut harness = crate::test_util::TestHarness::with_config(config);
+    harness
+        .deposit(1, 1, "10.0")
+        .await
+        .withdraw(1, 2, "8.0")
+        .await
+        .dispute(1, 1)
+        .await
+        .resolve(1, 1)
+        .await
+        .chargeback(1, 1)
+        .await
+        .expect_available(1, "2.0")
+        .expect_held(1, "-10.0")
+        .expect_total(1, "-8.0");
+    let owed = harness.transactor().amounts_owed();
+    assert_eq!(
+        owed,
+        vec![AmountOwed {
+            client: ClientId(1),
+            held: Currency::from_str("-10.0")?,
+            total: Currency::from_str("-8.0")?,
+            owed: Currency::from_str("10.0")?,
+            repaid: Currency::ZERO,
+        }]
+    );
+    harness
+        .transactor_mut()
+        .unlock_account(ClientId(1))
+        .expect("the account is frozen");
+    harness
+        .deposit(1, 3, "4.0")
+        .await
+        .expect_accepted()
+        .expect_available(1, "2.0")
+        .expect_held(1, "-6.0")
+        .expect_total(1, "-4.0")
+        .deposit(1, 4, "7.5")
+        .await
+        .expect_available(1, "3.5")
+        .expect_held(1, "0.0")
+        .expect_total(1, "3.5");
+    assert_eq!(harness.transactor().amounts_owed(), vec![]);
+    let account = harness.transactor().accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.repaid, Currency::from_str("10.0")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn report_amounts_owed_without_sweeping() -> AppResult<()> {
+    let mut harness = crate::test_util::TestHarness::new();
+    harness
+        .deposit(1, 1, "5.0")
+        .await
+        .dispute(1, 1)
+        .await
+        .resolve(1, 1)
+        .await
+        .chargeback(1, 1)
+        .await
+        .deposit(2, 2, "1.0")
+        .await;
+    harness
+        .transactor_mut()
+        .unlock_account(ClientId(1))
+        .expect("the account is frozen");
+    harness
+        .deposit(1, 3, "2.0")
+        .await
+        .expect_available(1, "7.0")
+        .expect_held(1, "-5.0");
+    let owed = harness.transactor().amounts_owed();
+    assert_eq!(owed.len(), 1);
+    assert_eq!(owed[0].client, ClientId(1));
+    assert_eq!(owed[0].owed, Currency::from_str("5.0")?);
+    assert_eq!(owed[0].repaid, Currency::ZERO);
+    Ok(())
+}
+
+#[tokio::test]
+async fn remap_client_ids() -> AppResult<()> {
+    let mut client_ids = ClientIdMap::new();
+    client_ids.insert(ClientId(1), ClientId(10))?;
+    client_ids.insert(ClientId(2), ClientId(1))?;
+    let mut harness = crate::test_util::TestHarness::with_config(Config {
+        client_ids,
+        ..Config::default()
+    });
+    harness
+        .deposit(1, 1, "1.0")
+        .await
+        .expect_accepted()
+        .deposit(2, 2, "2.0")
+        .await
+        .expect_accepted()
+        .deposit(3, 3, "3.0")
+        .await
+        .expect_accepted()
+        .deposit(10, 4, "4.0")
+        .await
+        .expect_rejected(TransactionError::ClientIdCollision { cid: ClientId(10) })
+        .dispute(1, 1)
+        .await
+        .expect_accepted()
+        .expect_available(10, "0.0")
+        .expect_held(10, "1.0")
+        .expect_available(1, "2.0")
+        .expect_available(3, "3.0")
+        .expect_no_account(2);
+    let rejected: Vec<_> = harness.transactor().rejected().collect();
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].transaction().cid, ClientId(10));
+    Ok(())
+}
+
+#[test]
+fn client_id_map_collisions() -> AppResult<()> {
+    let mut client_ids = ClientIdMap::new();
+    client_ids.insert(ClientId(1), ClientId(2))?;
+    assert!(matches!(
+        client_ids.insert(ClientId(1), ClientId(3)),
+        Err(AppError::DuplicateClientIdMapping { old: ClientId(1) })
+    ));
+    assert!(matches!(
+        client_ids.insert(ClientId(4), ClientId(2)),
+        Err(AppError::ClientIdMappingCollision { new: ClientId(2) })
+    ));
+    assert_eq!(client_ids.get(ClientId(3)), Ok(ClientId(3)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_client_id_map_file() -> AppResult<()> {
+    let path = TempPath::new("client-ids.csv");
+    std::fs::write(
+        &path,
+        "old,new\n# renumbered in March\n1,10\n\n 2 , 20\n1,30\n",
+    )?;
+    let result = read_client_id_map(path.to_path_buf()).await;
+    assert!(matches!(
+        result,
+        Err(AppError::DuplicateClientIdMapping { old: ClientId(1) })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn dispute_only_deposits_and_withdrawals() -> AppResult<()> {
+    let mut harness = crate::test_util::TestHarness::new();
+    harness.deposit(1, 1, "1.0").await.expect_accepted();
+    // NOTE: Only a hand-edited snapshot can get other transactions, or
+    //       deposits without an amount, into the history of an account.
+    let account = harness
+        .transactor_mut()
+        .accounts
+        .get_mut(&ClientId(1))
+        .unwrap();
+    let resolve = Transaction::new(
+        TransactionType::Resolve,
+        ClientId(1),
+        TransactionId(2),
+        None,
+    );
+    let deposit = Transaction::new(
+        TransactionType::Deposit,
+        ClientId(1),
+        TransactionId(3),
+        None,
+    );
+    account.processed_transactions.insert(resolve.tid, resolve);
+    account.processed_transactions.insert(deposit.tid, deposit);
+    harness
+        .dispute(1, 2)
+        .await
+        .expect_rejected(TransactionError::TransactionNotDisputable {
+            tid: TransactionId(2),
+            ttype: TransactionType::Resolve,
+        })
+        .dispute(1, 3)
+        .await
+        .expect_rejected(TransactionError::MalformedInputData)
+        .dispute(1, 1)
+        .await
+        .expect_accepted()
+        .expect_held(1, "1.0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_transaction_type_aliases() -> AppResult<()> {
+    let aliases = [
+        ("credit", TransactionType::Deposit),
+        ("debit", TransactionType::Withdrawal),
+        ("deposit", TransactionType::Withdrawal),
+    ];
+    let mut transactor = Transactor::with_config(Config {
+        type_aliases: aliases
+            .iter()
+            .map(|(alias, ttype)| (alias.to_string(), *ttype))
+            .collect(),
+        ..Config::default()
+    });
+    let csv = "type,   client, tx, amount\n\
+               credit,      1,  1,    5.0\n\
+               debit,       1,  2,    1.5\n\
+               deposit,     1,  3,    1.0\n\
+               dispute,     1,  2,\n";
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    // NOTE: The names of the transaction types themselves can't be aliased.
+    assert_eq!(account.available, Currency::from_str("3.0")?);
+    assert_eq!(account.held, Currency::from_str("1.5")?);
+    let mut transactor = Transactor::new();
+    let result = transactor.process_csv_reader(csv.as_bytes()).await;
+    assert!(matches!(
+        result,
+        Err(AppError::UnknownTransactionType { line: 2, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn typed_transaction_constructors() -> AppResult<()> {
+    let (cid, tid) = (ClientId(1), TransactionId(2));
+    let amount = Currency::from_str("1.5")?;
+    let constructed = [
+        Transaction::deposit(cid, tid, amount),
+        Transaction::withdrawal(cid, tid, amount),
+        Transaction::dispute(cid, tid),
+        Transaction::resolve(cid, tid),
+        Transaction::chargeback(cid, tid),
+    ];
+    let expected = [
+        (TransactionType::Deposit, Some(amount)),
+        (TransactionType::Withdrawal, Some(amount)),
+        (TransactionType::Dispute, None),
+        (TransactionType::Resolve, None),
+        (TransactionType::Chargeback, None),
+    ];
+    for (t, (ttype, amount)) in constructed.iter().zip(expected) {
+        assert_eq!(*t, Transaction::new(ttype, cid, tid, amount));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejections_round_trip_through_serde() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let withdrawal =
+        Transaction::withdrawal(ClientId(1), TransactionId(1), Currency::from_str("1.5")?);
+    let _ = transactor.process_row(withdrawal).await;
+    let rejected: Vec<IgnoredTransaction> = transactor.rejected().cloned().collect();
+    assert_eq!(rejected.len(), 1);
+    let json = serde_json::to_string(&rejected)?;
+    assert_eq!(
+        serde_json::from_str::<Vec<IgnoredTransaction>>(&json)?,
+        rejected
+    );
+    let events = serde_json::to_value(transactor.account_events())?;
+    assert!(events.is_array());
+    Ok(())
+}
+
+#[test]
+fn construct_currencies() -> AppResult<()> {
+    let amount: Currency = "1.5".parse()?;
+    assert_eq!(amount, Currency::from_minor_units(15_000));
+    assert_eq!(amount, Currency::new(Decimal::new(15, 1)));
+    assert_eq!(amount.as_decimal(), Decimal::new(15, 1));
+    assert!("1.5.0".parse::<Currency>().is_err());
+    let t = Transaction::new(
+        TransactionType::Deposit,
+        ClientId(1),
+        TransactionId(2),
+        Some(amount),
+    );
+    assert_eq!(
+        (t.ttype(), t.cid(), t.tid(), t.amount()),
+        (
+            TransactionType::Deposit,
+            ClientId(1),
+            TransactionId(2),
+            Some(amount)
+        )
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn process_a_stream() -> AppResult<()> {
+    let amount = Currency::from_str("2.0")?;
+    let transactions = vec![
+        Ok(Transaction::deposit(ClientId(1), TransactionId(1), amount)),
+        Ok(Transaction::withdrawal(
+            ClientId(1),
+            TransactionId(2),
+            amount + amount,
+        )),
+        Ok(Transaction::deposit(ClientId(2), TransactionId(3), amount)),
+        Err(std::io::Error::other("connection reset")),
+        Ok(Transaction::deposit(ClientId(3), TransactionId(4), amount)),
+    ];
+    let mut transactor = Transactor::new();
+    let result = transactor
+        .process_stream(tokio_stream::iter(transactions))
+        .await;
+    assert!(matches!(result, Err(AppError::IoError(_))));
+    // NOTE: The rejected withdrawal doesn't end the stream, the error does.
+    assert_eq!(transactor.rejected().count(), 1);
+    assert_eq!(
+        transactor.accounts.keys().copied().collect::<Vec<_>>(),
+        [ClientId(1), ClientId(2)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn evict_the_oldest_settled_transactions() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let mut transactor = Transactor::with_config(Config {
+        retention: RetentionPolicy {
+            max_settled_per_account: Some(2),
+            ..RetentionPolicy::default()
+        },
+        dispute_grace_rows: 10,
+        ..Config::default()
+    });
+    for t in [
+        Transaction::deposit(cid(1), tid(1), amount("1.0")?),
+        Transaction::deposit(cid(1), tid(2), amount("2.0")?),
+        Transaction::dispute(cid(1), tid(2)),
+        Transaction::deposit(cid(1), tid(3), amount("3.0")?),
+        Transaction::withdrawal(cid(1), tid(4), amount("0.5")?),
+        Transaction::deposit(cid(1), tid(5), amount("4.0")?),
+        Transaction::deposit(cid(2), tid(6), amount("1.0")?),
+    ] {
+        transactor.process_row(t).await?;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    // NOTE: The disputed transaction 2 doesn't count towards the cap.
+    assert_eq!(
+        account.processed_transactions.keys().collect::<Vec<_>>(),
+        [&TransactionId(4), &TransactionId(5)]
+    );
+    assert_eq!(account.total, amount("9.5")?);
+    assert_eq!(transactor.resident_transactions, 4);
+    // NOTE: Recent transactions can still be disputed, and a dispute of an
+    //       evicted one is rejected outright rather than parked.
+    transactor
+        .process_row(Transaction::dispute(cid(1), tid(5)))
+        .await?;
+    transactor
+        .process_row(Transaction::resolve(cid(1), tid(2)))
+        .await?;
+    assert_eq!(
+        transactor
+            .process_row(Transaction::dispute(cid(1), tid(3)))
+            .await,
+        Err(TransactionError::TransactionEvicted {
+            tid: tid(3),
+            cid: cid(1)
+        })
+    );
+    assert_eq!(
+        transactor
+            .process_row(Transaction::dispute(cid(1), tid(7)))
+            .await,
+        Ok(())
+    );
+    assert_eq!(transactor.parked_transactions.len(), 1);
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.held, amount("4.0")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn keep_no_history_without_dispute_support() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let mut transactor = Transactor::with_config(Config {
+        retention: RetentionPolicy {
+            history_free: true,
+            ..RetentionPolicy::default()
+        },
+        dispute_grace_rows: 10,
+        ..Config::default()
+    });
+    for t in [
+        Transaction::deposit(cid(1), tid(1), amount("3.0")?),
+        Transaction::withdrawal(cid(1), tid(2), amount("1.0")?),
+    ] {
+        transactor.process_row(t).await?;
+    }
+    let account = transactor.accounts.get(&ClientId(1)).unwrap();
+    assert!(account.processed_transactions.is_empty());
+    assert_eq!(account.total, amount("2.0")?);
+    assert_eq!(transactor.resident_transactions, 0);
+    // NOTE: Disputes are rejected outright rather than parked.
+    assert_eq!(
+        transactor
+            .process_row(Transaction::dispute(cid(1), tid(1)))
+            .await,
+        Err(TransactionError::DisputesNotSupported {
+            tid: tid(1),
+            cid: cid(1)
+        })
+    );
+    assert!(transactor.parked_transactions.is_empty());
+    Ok(())
+}
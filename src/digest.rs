@@ -0,0 +1,198 @@
+//! This module defines per-client digests of a run, as notification
+//! payloads for a messaging service to deliver to the clients.
+//!
+//! A `ClientDigest` summarizes what happened to the account of a single
+//! client during the run: how many of its transactions were accepted and
+//! rejected, how its disputes ended, and when its account was frozen or
+//! unfrozen, along with its funds afterwards. Only clients with activity in
+//! the run get a digest.
+//!
+//! Digests are written to a directory, one file per client, either as
+//! `JSON` or as text rendered from a `DigestTemplate`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{AccountChange, ClientId, Currency, FreezeTrigger, Transactor, UnfreezeReason};
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The activity of a single client during a run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ClientDigest {
+    pub client: ClientId,
+    /// The number of transactions that were applied.
+    pub accepted: u64,
+    /// The number of transactions that were rejected.
+    pub rejected: u64,
+    pub disputes_opened: u64,
+    pub disputes_resolved: u64,
+    pub disputes_charged_back: u64,
+    /// The freezes and unfreezes of the account, in order.
+    pub account_changes: Vec<AccountChange>,
+    pub available: Currency,
+    pub held: Currency,
+    pub total: Currency,
+    pub locked: bool,
+}
+
+/// The format of a text digest. Every occurrence of the placeholders
+/// `{client}`, `{accepted}`, `{rejected}`, `{disputes_opened}`,
+/// `{disputes_resolved}`, `{disputes_charged_back}`, `{account_changes}`,
+/// `{available}`, `{held}`, `{total}` and `{locked}` is replaced by the
+/// corresponding field of the digest. `{account_changes}` is a sentence per
+/// change, or `none`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigestTemplate(String);
+
+impl DigestTemplate {
+    #[inline(always)]
+    pub fn new(template: impl Into<String>) -> Self {
+        Self(template.into())
+    }
+
+    /// Render the text digest of `digest`.
+    pub fn render(&self, digest: &ClientDigest) -> String {
+        let changes = match digest.account_changes.as_slice() {
+            [] => "none".to_string(),
+            changes => changes
+                .iter()
+                .map(describe_change)
+                .collect::<Vec<_>>()
+                .join("; "),
+        };
+        self.0
+            .replace("{client}", &digest.client.to_string())
+            .replace("{accepted}", &digest.accepted.to_string())
+            .replace("{rejected}", &digest.rejected.to_string())
+            .replace("{disputes_opened}", &digest.disputes_opened.to_string())
+            .replace("{disputes_resolved}", &digest.disputes_resolved.to_string())
+            .replace(
+                "{disputes_charged_back}",
+                &digest.disputes_charged_back.to_string(),
+            )
+            .replace("{account_changes}", &changes)
+            .replace("{available}", &digest.available.to_output_string())
+            .replace("{held}", &digest.held.to_output_string())
+            .replace("{total}", &digest.total.to_output_string())
+            .replace("{locked}", &digest.locked.to_string())
+    }
+}
+
+impl Default for DigestTemplate {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(
+            "Client {client}\n\
+             Transactions: {accepted} accepted, {rejected} rejected\n\
+             Disputes: {disputes_opened} opened, {disputes_resolved} resolved, \
+             {disputes_charged_back} charged back\n\
+             Account changes: {account_changes}\n\
+             Funds: {available} available, {held} held, {total} total\n\
+             Locked: {locked}\n",
+        )
+    }
+}
+
+/// A sentence that describes the account `change`.
+#[rustfmt::skip]
+fn describe_change(change: &AccountChange) -> String {
+    match change {
+        AccountChange::Frozen(FreezeTrigger::Chargeback { tid }) =>
+            format!("frozen after the chargeback of tx {}", tid),
+        AccountChange::Frozen(FreezeTrigger::RejectedWithdrawals { count }) =>
+            format!("frozen after {} rejected withdrawals in a row", count),
+        AccountChange::Frozen(FreezeTrigger::BalanceBelowFloor { floor }) =>
+            format!("frozen for total funds below {}", floor.to_output_string()),
+        AccountChange::Frozen(FreezeTrigger::Watchlist) =>
+            "frozen for being on the watchlist".to_string(),
+        AccountChange::Unfrozen(UnfreezeReason::CoolingOff) =>
+            "unfrozen after the cooling-off period".to_string(),
+        AccountChange::Unfrozen(UnfreezeReason::MatchingResolve { tid }) =>
+            format!("unfrozen by the resolve of tx {}", tid),
+        AccountChange::Unfrozen(UnfreezeReason::Manual) =>
+            "unfrozen by an operator".to_string(),
+    }
+}
+
+/// The format of the digest files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DigestFormat {
+    /// Text rendered from a `DigestTemplate`, in `<client>.txt`
+    Text(DigestTemplate),
+    /// A `JSON` object, in `<client>.json`
+    Json,
+}
+
+impl Default for DigestFormat {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Text(DigestTemplate::default())
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// The digests of the clients with activity in this run, ordered by
+    /// `ClientId`.
+    pub fn client_digests(&self) -> Vec<ClientDigest> {
+        let mut changes: BTreeMap<ClientId, Vec<AccountChange>> = BTreeMap::new();
+        for event in self.account_events() {
+            changes
+                .entry(event.cid)
+                .or_default()
+                .push(event.change.clone());
+        }
+        let mut cids: Vec<ClientId> = self.client_stats.keys().copied().collect();
+        cids.extend(changes.keys().copied());
+        cids.sort();
+        cids.dedup();
+        let mut digests = Vec::with_capacity(cids.len());
+        for cid in cids {
+            let stats = self.client_stats(cid).cloned().unwrap_or_default();
+            let account = self.accounts.get(&cid);
+            digests.push(ClientDigest {
+                client: cid,
+                accepted: stats.accepted,
+                rejected: stats.rejected.values().sum(),
+                disputes_opened: stats.disputes_opened,
+                disputes_resolved: stats.disputes_resolved,
+                disputes_charged_back: stats.disputes_charged_back,
+                account_changes: changes.remove(&cid).unwrap_or_default(),
+                available: account.map_or(Currency::ZERO, |account| account.available),
+                held: account.map_or(Currency::ZERO, |account| account.held),
+                total: account.map_or(Currency::ZERO, |account| account.total),
+                locked: account.is_some_and(|account| account.is_locked),
+            });
+        }
+        digests
+    }
+
+    /// Write the digest of every client with activity in this run to its
+    /// own file in the directory `dirpath`, in the given `format`, creating
+    /// the directory if needed. Returns the number of digests written.
+    pub async fn write_client_digests(
+        &self,
+        dirpath: PathBuf,
+        format: &DigestFormat,
+    ) -> AppResult<usize> {
+        tokio::fs::create_dir_all(&dirpath).await?;
+        let digests = self.client_digests();
+        for digest in &digests {
+            let (filename, payload) = match format {
+                DigestFormat::Text(template) => (
+                    format!("{}.txt", digest.client),
+                    template.render(digest).into_bytes(),
+                ),
+                DigestFormat::Json => (
+                    format!("{}.json", digest.client),
+                    serde_json::to_vec(digest)?,
+                ),
+            };
+            tokio::fs::write(dirpath.join(filename), payload).await?;
+        }
+        Ok(digests.len())
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+use crate::core::{Transaction, TransactionId};
+use crate::test_util::temp::TempPath;
+
+async fn active_transactor() -> AppResult<Transactor> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let mut transactor = Transactor::new();
+    for t in [
+        Transaction::deposit(cid(1), tid(1), amount("5.0")?),
+        Transaction::deposit(cid(1), tid(2), amount("1.0")?),
+        Transaction::dispute(cid(1), tid(1)),
+        Transaction::resolve(cid(1), tid(1)),
+        Transaction::chargeback(cid(1), tid(1)),
+        Transaction::deposit(cid(2), tid(3), amount("2.0")?),
+        Transaction::withdrawal(cid(2), tid(4), amount("3.0")?),
+    ] {
+        let _ = transactor.process_row(t).await;
+    }
+    Ok(transactor)
+}
+
+#[tokio::test]
+async fn digest_the_activity_of_each_client() -> AppResult<()> {
+    let mut transactor = active_transactor().await?;
+    transactor.unlock_account(ClientId(1))?;
+    let digests = transactor.client_digests();
+    assert_eq!(
+        digests,
+        vec![
+            ClientDigest {
+                client: ClientId(1),
+                accepted: 5,
+                rejected: 0,
+                disputes_opened: 1,
+                disputes_resolved: 1,
+                disputes_charged_back: 1,
+                account_changes: vec![
+                    AccountChange::Frozen(FreezeTrigger::Chargeback {
+                        tid: TransactionId(1)
+                    }),
+                    AccountChange::Unfrozen(UnfreezeReason::Manual),
+                ],
+                available: Currency::from_str("6.0")?,
+                held: Currency::from_str("-5.0")?,
+                total: Currency::from_str("1.0")?,
+                locked: false,
+            },
+            ClientDigest {
+                client: ClientId(2),
+                accepted: 1,
+                rejected: 1,
+                disputes_opened: 0,
+                disputes_resolved: 0,
+                disputes_charged_back: 0,
+                account_changes: vec![],
+                available: Currency::from_str("2.0")?,
+                held: Currency::ZERO,
+                total: Currency::from_str("2.0")?,
+                locked: false,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn render_the_default_template() -> AppResult<()> {
+    let transactor = active_transactor().await?;
+    let digests = transactor.client_digests();
+    assert_eq!(
+        DigestTemplate::default().render(&digests[0]),
+        "Client 1\n\
+         Transactions: 5 accepted, 0 rejected\n\
+         Disputes: 1 opened, 1 resolved, 1 charged back\n\
+         Account changes: frozen after the chargeback of tx 1\n\
+         Funds: 6.0000 available, -5.0000 held, 1.0000 total\n\
+         Locked: true\n"
+    );
+    let template = DigestTemplate::new("{client}: {account_changes}, {total}");
+    assert_eq!(template.render(&digests[1]), "2: none, 2.0000");
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_a_file_per_client() -> AppResult<()> {
+    let transactor = active_transactor().await?;
+    let dir = TempPath::new("digests");
+    let text = transactor
+        .write_client_digests(dir.to_path_buf(), &DigestFormat::default())
+        .await?;
+    let json = transactor
+        .write_client_digests(dir.to_path_buf(), &DigestFormat::Json)
+        .await?;
+    assert_eq!((text, json), (2, 2));
+    let mut files: Vec<_> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["1.json", "1.txt", "2.json", "2.txt"]);
+    let payload = std::fs::read_to_string(dir.join("2.json"))?;
+    let payload: serde_json::Value = serde_json::from_str(&payload)?;
+    assert_eq!(payload["client"], 2);
+    assert_eq!(payload["rejected"], 1);
+    assert_eq!(payload["account_changes"], serde_json::json!([]));
+    Ok(())
+}
@@ -1,25 +1,199 @@
-//!
+//! This module defines the error types used throughout the crate.
 
-use crate::core::{ClientId, TransactionId};
+use crate::core::{ClientId, TransactionId, TransactionType};
+use crate::rebalance::RebalanceTotals;
 use csv_async::Error as CsvAsyncError;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Error as SerdeJsonError;
 use std::io::Error as IoError;
 use std::num::ParseIntError;
+use std::path::PathBuf;
+use std::process::ExitStatus;
 use std::str::Utf8Error;
 use tokio::task::JoinError as TokioJoinError;
 
+#[cfg(feature = "object-store")]
+use object_store::Error as ObjectStoreError;
+#[cfg(feature = "iso20022")]
+use quick_xml::Error as XmlError;
+#[cfg(feature = "http")]
+use reqwest::Error as HttpError;
+
 pub type AppResult<T> = std::result::Result<T, AppError>;
 
 #[derive(Debug)]
 pub enum AppError {
+    /// Another client id is mapped to the client id `new` already.
+    ClientIdMappingCollision {
+        new: ClientId,
+    },
     CsvAsyncError(CsvAsyncError),
-    FailedToParseDecimal { decimal: String },
+    /// The client id `old` is mapped to another client id already.
+    DuplicateClientIdMapping {
+        old: ClientId,
+    },
+    FailedToParseDecimal {
+        decimal: String,
+    },
+    /// The sum of the funds of all accounts can't be represented.
+    FundsOverflow,
+    /// Downloading an input failed, or the server responded with an error
+    /// status.
+    #[cfg(feature = "http")]
+    HttpError(HttpError),
+    /// Reading or writing an object in an object store failed.
+    #[cfg(feature = "object-store")]
+    ObjectStoreError(ObjectStoreError),
+    /// `url` isn't the URL of an object in a supported object store.
+    #[cfg(feature = "object-store")]
+    InvalidObjectUrl {
+        url: String,
+    },
+    /// A bundle has no `entry`, e.g. because it was truncated.
+    IncompleteBundle {
+        entry: &'static str,
+    },
     IoError(IoError),
-    NoFileNameCliArgFound,
+    /// A line of a client id map file isn't an `old,new` pair.
+    MalformedClientIdMapping {
+        line: String,
+    },
+    /// The row at line number `line` of a `CSV` input has a client id or
+    /// transaction id that isn't an unsigned integer.
+    MalformedRow {
+        line: u64,
+    },
+    /// A line of a shard assignment table isn't a `client,shard` pair.
+    MalformedShardAssignment {
+        line: String,
+    },
+    /// The trailer line of an account summary can't be parsed.
+    MalformedTrailer {
+        line: String,
+    },
+    /// The entry with index `entry` in an OFX or QIF statement has no
+    /// amount, or can't be assigned a transaction id.
+    InvalidStatementEntry {
+        entry: usize,
+    },
+    /// A required element of an ISO 20022 message is missing or invalid.
+    #[cfg(feature = "iso20022")]
+    MalformedIso20022Message {
+        element: &'static str,
+    },
+    /// The merge key of the row at line number `line` of the merged source
+    /// at `path` isn't an unsigned integer.
+    InvalidMergeKey {
+        path: PathBuf,
+        line: u64,
+        value: String,
+    },
+    /// The merged source at `path` has no merge key `column`.
+    MissingMergeKeyColumn {
+        path: PathBuf,
+        column: String,
+    },
+    /// The row at line number `line` of the merged source at `path` has a
+    /// lower merge key than the row before it.
+    UnorderedMergeSource {
+        path: PathBuf,
+        line: u64,
+    },
+    /// Sharded processing was asked for, while the `Transactor` uses a
+    /// `feature` that needs to see the rows of all clients in order.
+    UnsupportedWhenSharded {
+        feature: &'static str,
+    },
+    /// The input `input` is a URL, while `flag` only supports files and
+    /// `stdin`.
+    UnsupportedForUrls {
+        input: String,
+        flag: &'static str,
+    },
+    /// Validating the input found `count` issues.
+    InvalidInput {
+        count: usize,
+    },
+    /// The row at line number `line` has a type that is neither a
+    /// transaction type nor an alias of one.
+    UnknownTransactionType {
+        line: u64,
+        name: String,
+    },
+    /// The header line of a `CSV` input has a column named `header`, which
+    /// isn't a column of transactions.
+    UnknownCsvHeader {
+        header: String,
+    },
+    /// `count` rows of the input were rejected, while running with
+    /// `--strict`.
+    RowsRejected {
+        count: usize,
+    },
+    /// The audit run with `--audit` found `count` discrepancies in the
+    /// state of the engine.
+    AuditFailed {
+        count: usize,
+    },
+    /// The older binary run by `compare-versions` exited unsuccessfully.
+    OldBinaryFailed {
+        status: ExitStatus,
+    },
     ParseIntError(ParseIntError),
+    SerdeJsonError(SerdeJsonError),
     TokioJoinError(TokioJoinError),
+    /// The rows of an account summary don't match its trailer, i.e. the
+    /// summary was truncated or corrupted.
+    TrailerMismatch {
+        expected: String,
+        actual: String,
+    },
     TransactionError(TransactionError),
+    /// Processing the row at line number `line` of a `CSV` input failed
+    /// with a fatal `error`.
+    TransactionErrorAtLine {
+        line: u64,
+        error: TransactionError,
+    },
+    /// A rebalance was asked for without any snapshots to write the new
+    /// shards to.
+    NoRebalanceOutputs,
+    /// Rebalancing shard snapshots would change their `expected` totals to
+    /// the `actual` ones, e.g. because a snapshot was corrupted on write.
+    RebalanceMismatch {
+        expected: Box<RebalanceTotals>,
+        actual: Box<RebalanceTotals>,
+    },
+    /// The account of the client with id `cid` is in more than one of the
+    /// shard snapshots to rebalance.
+    AccountInSeveralShards {
+        cid: ClientId,
+    },
+    /// A snapshot has a layout version that this version of the crate
+    /// can't migrate, e.g. because a newer version wrote it.
+    UnsupportedSnapshotVersion {
+        version: String,
+    },
     Utf8Error(Utf8Error),
+    #[cfg(feature = "iso20022")]
+    XmlError(XmlError),
+}
+
+impl AppError {
+    /// Whether the error is about a single row of the input that can't be
+    /// read as a transaction, as opposed to about the input as a whole.
+    pub fn is_row_error(&self) -> bool {
+        match self {
+            Self::CsvAsyncError(e) => !matches!(
+                e.kind(),
+                csv_async::ErrorKind::Io(_) | csv_async::ErrorKind::Seek
+            ),
+            Self::FailedToParseDecimal { .. }
+            | Self::MalformedRow { .. }
+            | Self::UnknownTransactionType { .. } => true,
+            _ => false,
+        }
+    }
 }
 
 impl From<CsvAsyncError> for AppError {
@@ -43,6 +217,13 @@ impl From<ParseIntError> for AppError {
     }
 }
 
+impl From<SerdeJsonError> for AppError {
+    #[inline(always)]
+    fn from(e: SerdeJsonError) -> Self {
+        Self::SerdeJsonError(e)
+    }
+}
+
 impl From<TokioJoinError> for AppError {
     #[inline(always)]
     fn from(e: TokioJoinError) -> Self {
@@ -64,13 +245,37 @@ impl From<Utf8Error> for AppError {
     }
 }
 
+#[cfg(feature = "http")]
+impl From<HttpError> for AppError {
+    #[inline(always)]
+    fn from(e: HttpError) -> Self {
+        Self::HttpError(e)
+    }
+}
+
+#[cfg(feature = "object-store")]
+impl From<ObjectStoreError> for AppError {
+    #[inline(always)]
+    fn from(e: ObjectStoreError) -> Self {
+        Self::ObjectStoreError(e)
+    }
+}
+
+#[cfg(feature = "iso20022")]
+impl From<XmlError> for AppError {
+    #[inline(always)]
+    fn from(e: XmlError) -> Self {
+        Self::XmlError(e)
+    }
+}
+
 pub type TransactionResult<T> = std::result::Result<T, TransactionError>;
 
 // NOTE: `TransactionError`s have been split off into their own error type
 // rather than being incorporated directly into AppError, because these errors
 // can derive additional useful traits that some of the AppError variants (and
 // therefore the AppError type as a whole) cannot.
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum TransactionError {
     AccountBalanceInvariantViolated {
         cid: ClientId,
@@ -81,13 +286,95 @@ pub enum TransactionError {
     AccountIsLocked {
         cid: ClientId,
     },
+    /// An account that isn't frozen can't be unlocked.
+    AccountIsNotLocked {
+        cid: ClientId,
+    },
+    /// The amount of the transaction with id `tid` has `scale` decimal
+    /// places, while the `AmountScalePolicy` allows at most `max_scale`.
+    AmountScaleExceeded {
+        tid: TransactionId,
+        scale: u32,
+        max_scale: u32,
+    },
+    /// The funds of the account of the client with the given `ClientId`
+    /// can't represent the result of the transaction.
+    AmountOverflow {
+        cid: ClientId,
+    },
+    /// The client id `cid` isn't mapped by the `ClientIdMap`, but another
+    /// client id is mapped to it.
+    ClientIdCollision {
+        cid: ClientId,
+    },
     MalformedInputData,
+    /// The transaction with id `tid` of the client with the given `ClientId`
+    /// belongs to a closed accounting period, and can't be changed.
+    TransactionSealed {
+        tid: TransactionId,
+        cid: ClientId,
+    },
+    /// A deposit or withdrawal reuses the id `tid` of a transaction of the
+    /// client with the given `ClientId` that has been disputed since.
+    TransactionIdReused {
+        tid: TransactionId,
+        cid: ClientId,
+    },
+    /// The transaction with id `tid` that a dispute, resolve or chargeback
+    /// refers to is of type `ttype`, rather than a deposit or withdrawal.
+    TransactionNotDisputable {
+        tid: TransactionId,
+        ttype: TransactionType,
+    },
+    /// A deposit or withdrawal arrived after one with a higher id `max_tid`,
+    /// while the `OrderingPolicy` demands monotonically increasing ids.
+    TransactionIdOutOfOrder {
+        tid: TransactionId,
+        max_tid: TransactionId,
+    },
+    /// Opening another account would exceed the configured maximum.
+    TooManyAccounts {
+        max: usize,
+    },
+    /// Another open dispute for the client account with the given
+    /// `ClientId` would exceed the configured maximum.
+    TooManyOpenDisputes {
+        cid: ClientId,
+        max: usize,
+    },
+    /// Storing another transaction would exceed the configured maximum.
+    TooManyResidentTransactions {
+        max: usize,
+    },
+    /// The `ManualUnlockPolicy` forbids unlocking the account of the client
+    /// with the given `ClientId`.
+    UnlockNotPermitted {
+        cid: ClientId,
+    },
+    /// There is no account for the client with the given `ClientId`, and
+    /// the `UnknownClientPolicy` forbids opening one for this transaction.
+    UnknownClient {
+        cid: ClientId,
+    },
     /// There is no processed transaction with the given `TransactionId` for the
     /// client account with the given `ClientId`.
     NoSuchProcessedTransactionForClient {
         tid: TransactionId,
         cid: ClientId,
     },
+    /// The transaction with id `tid` of the client with the given `ClientId`
+    /// may have been evicted from the history of the account by the
+    /// `RetentionPolicy`, so it can't be disputed.
+    TransactionEvicted {
+        tid: TransactionId,
+        cid: ClientId,
+    },
+    /// The `RetentionPolicy` keeps no history, so the transaction with id
+    /// `tid` of the client with the given `ClientId` can't be disputed.
+    DisputesNotSupported {
+        tid: TransactionId,
+        cid: ClientId,
+    },
     /// There is no disputed transaction with the given `TransactionId` for the
     /// client account with the given `ClientId`.
     NoSuchDisputedTransactionForClient {
@@ -101,3 +388,57 @@ pub enum TransactionError {
         cid: ClientId,
     },
 }
+
+impl TransactionError {
+    /// Returns `true` if the error means that processing can't continue,
+    /// as opposed to only the transaction at hand being rejected.
+    /// This is the case when a configured resource limit is exceeded.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::TooManyAccounts { .. }
+                | Self::TooManyOpenDisputes { .. }
+                | Self::TooManyResidentTransactions { .. }
+        )
+    }
+
+    #[rustfmt::skip]
+    /// A short, stable name for the kind of error, for use in statistics.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::AccountBalanceInvariantViolated { .. }      => "balance_invariant_violated",
+            Self::AccountHasInsufficientFundsAvailable { .. } => "insufficient_funds",
+            Self::AccountIsLocked { .. }                      => "account_locked",
+            Self::AccountIsNotLocked { .. }                   => "account_not_locked",
+            Self::AmountScaleExceeded { .. }                  => "amount_scale_exceeded",
+            Self::AmountOverflow { .. }                       => "amount_overflow",
+            Self::ClientIdCollision { .. }                    => "client_id_collision",
+            Self::MalformedInputData                          => "malformed_input",
+            Self::TransactionIdOutOfOrder { .. }              => "out_of_order",
+            Self::TransactionIdReused { .. }                  => "transaction_id_reused",
+            Self::TransactionNotDisputable { .. }             => "not_disputable",
+            Self::TransactionSealed { .. }                    => "transaction_sealed",
+            Self::TransactionEvicted { .. }                   => "transaction_evicted",
+            Self::DisputesNotSupported { .. }                 => "disputes_not_supported",
+            Self::TooManyAccounts { .. }                      => "too_many_accounts",
+            Self::TooManyOpenDisputes { .. }                  => "too_many_open_disputes",
+            Self::TooManyResidentTransactions { .. }          => "too_many_resident_transactions",
+            Self::UnlockNotPermitted { .. }                   => "unlock_not_permitted",
+            Self::UnknownClient { .. }                        => "unknown_client",
+            Self::NoSuchProcessedTransactionForClient { .. }  => "no_such_processed_transaction",
+            Self::NoSuchDisputedTransactionForClient { .. }   => "no_such_disputed_transaction",
+            Self::NoSuchResolvedTransactionForClient { .. }   => "no_such_resolved_transaction",
+        }
+    }
+
+    /// Returns `true` if the error means that a dispute, resolve or
+    /// chargeback refers to a transaction that might still arrive later.
+    pub fn refers_to_unknown_transaction(&self) -> bool {
+        matches!(
+            self,
+            Self::NoSuchProcessedTransactionForClient { .. }
+                | Self::NoSuchDisputedTransactionForClient { .. }
+                | Self::NoSuchResolvedTransactionForClient { .. }
+        )
+    }
+}
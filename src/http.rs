@@ -0,0 +1,37 @@
+//! This module defines `HTTP(S)` URLs as a source of `CSV` input, so that
+//! remote files can be processed without downloading them first.
+//!
+//! The body of the response is streamed into the engine while it downloads,
+//! and decompressed going by the extension of the path of the URL, unless
+//! `Config::compression` says otherwise.
+
+#[cfg(test)]
+mod tests;
+
+use crate::compression::Compression;
+use crate::core::Transactor;
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use std::path::Path;
+
+/// Whether `input` is an `HTTP(S)` URL, rather than a path.
+#[inline]
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Download the `CSV` file at `url`, and process the transactions in
+    /// it in the same way as those in a local file. A response with an
+    /// error status fails before any row is processed.
+    pub async fn process_csv_url(&mut self, url: &str) -> AppResult<()> {
+        let response = reqwest::get(url).await?.error_for_status()?;
+        let compression = self
+            .config
+            .compression
+            .unwrap_or_else(|| Compression::detect(Path::new(response.url().path())));
+        let body = response.bytes_stream();
+        self.process_byte_stream(body, compression, url.to_string())
+            .await
+    }
+}
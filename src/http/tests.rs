@@ -0,0 +1,64 @@
+use super::*;
+use crate::error::AppError;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const CSV: &str = "type,client,tx,amount
+deposit,1,1,1.0
+deposit,2,2,2.0
+withdrawal,1,3,0.5
+";
+
+/// Serve a single request with `status` and `body`, and return the URL of
+/// the `CSV` file served.
+async fn serve_once(status: &'static str, body: &'static str) -> AppResult<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/transactions.csv", listener.local_addr()?);
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    Ok(url)
+}
+
+#[test]
+fn tell_urls_from_paths() {
+    assert!(is_url("https://bucket.example.com/transactions.csv"));
+    assert!(is_url("http://localhost:8080/transactions.csv"));
+    assert!(!is_url("transactions.csv"));
+    assert!(!is_url("-"));
+}
+
+#[tokio::test]
+async fn process_a_remote_file() -> AppResult<()> {
+    let url = serve_once("200 OK", CSV).await?;
+    let mut transactor = Transactor::new();
+    transactor.process_csv_url(&url).await?;
+    let mut expected = Transactor::new();
+    expected.process_csv_reader(CSV.as_bytes()).await?;
+    assert_eq!(
+        transactor.account_summaries().collect::<Vec<_>>(),
+        expected.account_summaries().collect::<Vec<_>>()
+    );
+    assert_eq!(transactor.data_quality()[0].source, url);
+    Ok(())
+}
+
+#[tokio::test]
+async fn fail_on_an_error_status() -> AppResult<()> {
+    let url = serve_once("404 Not Found", "").await?;
+    let mut transactor = Transactor::new();
+    let result = transactor.process_csv_url(&url).await;
+    assert!(matches!(result, Err(AppError::HttpError(_))));
+    assert_eq!(transactor.rows_processed, 0);
+    Ok(())
+}
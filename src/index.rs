@@ -0,0 +1,140 @@
+//! This module defines the secondary indexes over the transaction histories
+//! of all accounts, which answer `Transactor::search()` without scanning
+//! every history.
+//!
+//! Once `Transactor::index_histories()` is called, the indexes are kept up
+//! to date while transactions are processed: each deposit or withdrawal
+//! that enters a history is added to them, each dispute, resolve,
+//! chargeback or void moves its transaction to its new state, and each
+//! evicted transaction is removed from them. Restoring a snapshot rebuilds
+//! them from the histories, and sharded processing keeps an index per
+//! shard that is merged back afterwards.
+//!
+//! A search starts from the most selective index that the query can use,
+//! i.e. the dispute state, the type or the amount range, in that order, and
+//! filters the transactions it yields by the rest of the query.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{
+    Account, ClientId, Currency, Transaction, TransactionId, TransactionQuery, TransactionState,
+    TransactionType,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+
+/// A transaction in the histories, by the client it belongs to.
+type Key = (ClientId, TransactionId);
+
+/// The secondary indexes over the transactions in the histories of all
+/// accounts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TransactionIndex {
+    /// The state of each transaction.
+    states: BTreeMap<Key, TransactionState>,
+    by_state: BTreeMap<TransactionState, BTreeSet<Key>>,
+    by_type: BTreeMap<TransactionType, BTreeSet<Key>>,
+    by_amount: BTreeSet<(Currency, ClientId, TransactionId)>,
+}
+
+impl TransactionIndex {
+    /// The indexes over the histories of `accounts`.
+    pub(crate) fn build<'a>(accounts: impl Iterator<Item = &'a Account>) -> Self {
+        let mut index = Self::default();
+        for account in accounts {
+            for (state, transactions) in account.histories().iter() {
+                for t in transactions.values() {
+                    index.insert(t, *state);
+                }
+            }
+        }
+        index
+    }
+
+    /// Add the transaction `t` in state `state`, or move it to that state
+    /// if it was added before.
+    pub(crate) fn insert(&mut self, t: &Transaction, state: TransactionState) {
+        let key = (t.cid, t.tid);
+        if let Some(old) = self.states.insert(key, state) {
+            self.remove_state(key, old);
+        }
+        self.by_state.entry(state).or_default().insert(key);
+        self.by_type.entry(t.ttype).or_default().insert(key);
+        if let Some(amount) = t.amount {
+            self.by_amount.insert((amount, t.cid, t.tid));
+        }
+    }
+
+    /// Remove the transaction `t`, e.g. because it was evicted.
+    pub(crate) fn remove(&mut self, t: &Transaction) {
+        let key = (t.cid, t.tid);
+        if let Some(state) = self.states.remove(&key) {
+            self.remove_state(key, state);
+        }
+        if let Some(keys) = self.by_type.get_mut(&t.ttype) {
+            keys.remove(&key);
+        }
+        if let Some(amount) = t.amount {
+            self.by_amount.remove(&(amount, t.cid, t.tid));
+        }
+    }
+
+    fn remove_state(&mut self, key: Key, state: TransactionState) {
+        if let Some(keys) = self.by_state.get_mut(&state) {
+            keys.remove(&key);
+        }
+    }
+
+    /// Merge the indexes of another set of accounts into these ones.
+    pub(crate) fn extend(&mut self, other: Self) {
+        self.states.extend(other.states);
+        for (state, keys) in other.by_state {
+            self.by_state.entry(state).or_default().extend(keys);
+        }
+        for (ttype, keys) in other.by_type {
+            self.by_type.entry(ttype).or_default().extend(keys);
+        }
+        self.by_amount.extend(other.by_amount);
+    }
+
+    /// The transactions that may match `query`, along with their states,
+    /// ordered by client id first, and by transaction id second.
+    pub(crate) fn candidates(&self, query: &TransactionQuery) -> Vec<(Key, TransactionState)> {
+        let state_of = |key: &Key| self.states.get(key).map(|&state| (*key, state));
+        let mut candidates: Vec<(Key, TransactionState)> = if let Some(state) = query.state {
+            let keys = self.by_state.get(&state).into_iter().flatten();
+            keys.map(|&key| (key, state)).collect()
+        } else if let Some(ttype) = query.ttype {
+            let keys = self.by_type.get(&ttype).into_iter().flatten();
+            keys.filter_map(state_of).collect()
+        } else if query.min_amount.is_some() || query.max_amount.is_some() {
+            let lower = match query.min_amount {
+                Some(min) => Bound::Included((min, ClientId(0), TransactionId(0))),
+                None => Bound::Unbounded,
+            };
+            let upper = match query.max_amount {
+                Some(max) => Bound::Included((max, ClientId(u16::MAX), TransactionId(u32::MAX))),
+                None => Bound::Unbounded,
+            };
+            let range = self.by_amount.range((lower, upper));
+            let mut keys: Vec<_> = range
+                .filter_map(|&(_, cid, tid)| state_of(&(cid, tid)))
+                .collect();
+            keys.sort_unstable();
+            keys
+        } else {
+            self.states
+                .iter()
+                .map(|(&key, &state)| (key, state))
+                .collect()
+        };
+        // NOTE: The tid range can't be looked up across clients, as the
+        //       keys are ordered by client id first.
+        candidates.retain(|((_, tid), _)| {
+            query.min_tid.is_none_or(|min| min <= *tid)
+                && query.max_tid.is_none_or(|max| *tid <= max)
+        });
+        candidates
+    }
+}
@@ -0,0 +1,128 @@
+use super::*;
+use crate::config::{Config, RetentionPolicy};
+use crate::core::Transactor;
+use crate::error::AppResult;
+
+/// The queries that each test compares the indexed and scanning searches
+/// with.
+fn queries() -> AppResult<Vec<TransactionQuery>> {
+    let amount = Currency::from_str;
+    Ok(vec![
+        TransactionQuery::default(),
+        TransactionQuery {
+            state: Some(TransactionState::Disputed),
+            ..TransactionQuery::default()
+        },
+        TransactionQuery {
+            state: Some(TransactionState::ChargedBack),
+            ..TransactionQuery::default()
+        },
+        TransactionQuery {
+            ttype: Some(TransactionType::Withdrawal),
+            ..TransactionQuery::default()
+        },
+        TransactionQuery {
+            min_amount: Some(amount("2.0")?),
+            max_amount: Some(amount("5.0")?),
+            ..TransactionQuery::default()
+        },
+        TransactionQuery {
+            ttype: Some(TransactionType::Deposit),
+            min_tid: Some(TransactionId(3)),
+            max_tid: Some(TransactionId(8)),
+            ..TransactionQuery::default()
+        },
+    ])
+}
+
+/// Process deposits, withdrawals and disputes of every stage for 3
+/// clients, and void one of the withdrawals.
+async fn process(transactor: &mut Transactor) -> AppResult<()> {
+    use TransactionType::*;
+    let t = |ttype, cid, tid, amount: Option<&str>| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype,
+            cid: ClientId(cid),
+            tid: TransactionId(tid),
+            amount: amount.map(Currency::from_str).transpose()?,
+        })
+    };
+    for t in [
+        t(Deposit, 1, 1, Some("1.0"))?,
+        t(Deposit, 2, 2, Some("2.0"))?,
+        t(Deposit, 3, 3, Some("3.0"))?,
+        t(Deposit, 1, 4, Some("4.0"))?,
+        t(Withdrawal, 2, 5, Some("0.5"))?,
+        t(Deposit, 2, 6, Some("6.0"))?,
+        t(Dispute, 1, 4, None)?,
+        t(Dispute, 2, 6, None)?,
+        t(Resolve, 2, 6, None)?,
+        t(Deposit, 3, 7, Some("7.0"))?,
+        t(Withdrawal, 3, 8, Some("1.0"))?,
+        t(Dispute, 3, 7, None)?,
+        t(Resolve, 3, 7, None)?,
+        t(Chargeback, 3, 7, None)?,
+    ] {
+        transactor.process_transaction(t).await?;
+    }
+    transactor
+        .void_transaction(ClientId(2), TransactionId(5))
+        .await?;
+    Ok(())
+}
+
+/// Assert that the indexed search of `indexed` finds the same transactions
+/// as the scanning search of `scanned` for every query.
+fn assert_same_results(indexed: &Transactor, scanned: &Transactor) -> AppResult<()> {
+    assert!(indexed.index.is_some());
+    assert!(scanned.index.is_none());
+    for query in queries()? {
+        assert_eq!(
+            indexed.search(&query),
+            scanned.search(&query),
+            "{:?}",
+            query
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn indexed_search_matches_scanning() -> AppResult<()> {
+    let mut indexed = Transactor::new();
+    indexed.index_histories();
+    process(&mut indexed).await?;
+    let mut scanned = Transactor::new();
+    process(&mut scanned).await?;
+    assert_same_results(&indexed, &scanned)
+}
+
+#[tokio::test]
+async fn index_the_histories_processed_before() -> AppResult<()> {
+    let mut indexed = Transactor::new();
+    process(&mut indexed).await?;
+    indexed.index_histories();
+    let mut scanned = Transactor::new();
+    process(&mut scanned).await?;
+    assert_same_results(&indexed, &scanned)
+}
+
+#[tokio::test]
+async fn index_drops_evicted_transactions() -> AppResult<()> {
+    let config = Config {
+        retention: RetentionPolicy {
+            max_settled_per_account: Some(2),
+            ..RetentionPolicy::default()
+        },
+        ..Config::default()
+    };
+    let mut indexed = Transactor::with_config(config.clone());
+    indexed.index_histories();
+    process(&mut indexed).await?;
+    let mut scanned = Transactor::with_config(config);
+    process(&mut scanned).await?;
+    let index = indexed.index.as_ref().unwrap();
+    assert!(!index.states.contains_key(&(ClientId(3), TransactionId(3))));
+    assert_eq!(index, &TransactionIndex::build(indexed.accounts.values()));
+    assert_same_results(&indexed, &scanned)
+}
@@ -0,0 +1,200 @@
+//! This module defines read-only views of the state of a `Transactor`, for
+//! applications that embed the engine and do their own reporting.
+//!
+//! `Transactor::iter_accounts()` yields `AccountView`s, which borrow the
+//! accounts in place. `Transactor::snapshot()` copies the state into a
+//! `Snapshot`, which owns its data and can be kept around or sent to other
+//! tasks. Unlike the snapshot files of the `snapshot` module, a `Snapshot`
+//! can't be restored into a `Transactor`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{
+    Account, AccountSummary, ClientId, Currency, FreezeTrigger, Transaction, TransactionId,
+    TransactionState, Transactor,
+};
+use crate::error::{AppError, AppResult};
+use crate::quality::DataQuality;
+use crate::store::AccountStore;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+
+/// A read-only view of the account of a client.
+#[derive(Clone, Copy, Debug)]
+pub struct AccountView<'a> {
+    account: &'a Account,
+}
+
+impl<'a> AccountView<'a> {
+    #[inline(always)]
+    pub fn client(&self) -> ClientId {
+        self.account.id
+    }
+
+    #[inline(always)]
+    pub fn available(&self) -> Currency {
+        self.account.available
+    }
+
+    #[inline(always)]
+    pub fn held(&self) -> Currency {
+        self.account.held
+    }
+
+    #[inline(always)]
+    pub fn total(&self) -> Currency {
+        self.account.total
+    }
+
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.account.is_locked
+    }
+
+    /// What caused the account to be frozen, if it is.
+    #[inline(always)]
+    pub fn freeze_trigger(&self) -> Option<&'a FreezeTrigger> {
+        self.account.freeze_trigger.as_ref()
+    }
+
+    /// The part of the deposits that was swept against negative held funds.
+    #[inline(always)]
+    pub fn repaid(&self) -> Currency {
+        self.account.repaid
+    }
+
+    /// The transactions of the account that are in state `state`, ordered
+    /// by `TransactionId`.
+    #[rustfmt::skip]
+    pub fn transactions(&self, state: TransactionState) -> impl Iterator<Item = &'a Transaction> {
+        let account = self.account;
+        let history = match state {
+            TransactionState::Processed   => &account.processed_transactions,
+            TransactionState::Disputed    => &account.disputed_transactions,
+            TransactionState::Resolved    => &account.resolved_transactions,
+            TransactionState::ChargedBack => &account.charged_back_transactions,
+            TransactionState::Voided      => &account.voided_transactions,
+        };
+        history.values()
+    }
+
+    /// The number of transactions of the account that are disputed at the
+    /// moment.
+    #[inline(always)]
+    pub fn open_disputes(&self) -> usize {
+        self.account.disputed_transactions.len()
+    }
+}
+
+/// Counters that describe the state of a `Transactor` as a whole.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EngineStats {
+    /// The number of rows processed so far, including rejected ones.
+    pub rows_processed: u64,
+    /// The number of rows that were applied successfully.
+    pub rows_accepted: u64,
+    /// The number of rows that were rejected.
+    pub rows_rejected: usize,
+    /// The number of rejected rows, per `TransactionError::reason()`.
+    pub rejected_by_reason: BTreeMap<&'static str, u64>,
+    /// The number of accepted and rejected rows, per
+    /// `TransactionType::name()`. Parked rows are only counted once their
+    /// fate is decided.
+    pub rows_by_type: BTreeMap<&'static str, u64>,
+    pub accounts: usize,
+    pub locked_accounts: usize,
+    /// The number of transactions that are disputed at the moment.
+    pub open_disputes: usize,
+    /// The number of transactions kept in the account histories.
+    pub resident_transactions: usize,
+    /// The number of disputes, resolves and chargebacks that are waiting
+    /// for the transaction they refer to.
+    pub parked_transactions: usize,
+    /// The highest `TransactionId` seen so far.
+    pub max_tid: Option<TransactionId>,
+    pub periods_closed: u32,
+    /// The sum of the available funds of all accounts.
+    pub total_available: Currency,
+    /// The sum of the held funds of all accounts.
+    pub total_held: Currency,
+    /// The data quality of each input, in the order they were processed.
+    pub data_quality: Vec<DataQuality>,
+}
+
+/// A copy of the state of a `Transactor` at a point in time.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub stats: EngineStats,
+    /// The rows of the account summary, ordered by `ClientId`.
+    pub accounts: Vec<AccountSummary>,
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// The number of client accounts.
+    #[inline(always)]
+    pub fn len_accounts(&self) -> usize {
+        self.accounts.len()
+    }
+
+    /// The client accounts, ordered by `ClientId`.
+    pub fn iter_accounts(&self) -> impl Iterator<Item = AccountView<'_>> {
+        self.accounts
+            .values()
+            .map(|account| AccountView { account })
+    }
+
+    /// The account of the client with id `cid`, if there is one.
+    pub fn account(&self, cid: ClientId) -> Option<AccountView<'_>> {
+        self.accounts
+            .get(&cid)
+            .map(|account| AccountView { account })
+    }
+
+    /// Count the accounts, disputes and rows of the `Transactor`, and sum
+    /// the funds of its accounts. This visits every account, and fails if
+    /// the sums can't be represented.
+    pub fn stats(&self) -> AppResult<EngineStats> {
+        let mut stats = EngineStats {
+            rows_processed: self.rows_processed,
+            rows_rejected: self.ignored_transactions.len(),
+            accounts: self.accounts.len(),
+            resident_transactions: self.resident_transactions,
+            parked_transactions: self.parked_transactions.len(),
+            max_tid: self.max_tid,
+            periods_closed: self.periods_closed,
+            data_quality: self.data_quality.clone(),
+            ..EngineStats::default()
+        };
+        for account in self.accounts.values() {
+            stats.locked_accounts += account.is_locked as usize;
+            stats.open_disputes += account.disputed_transactions.len();
+            stats.total_available = stats
+                .total_available
+                .checked_add(account.available)
+                .ok_or(AppError::FundsOverflow)?;
+            stats.total_held = stats
+                .total_held
+                .checked_add(account.held)
+                .ok_or(AppError::FundsOverflow)?;
+        }
+        for client_stats in self.client_stats.values() {
+            stats.rows_accepted += client_stats.accepted;
+            for (reason, count) in &client_stats.rejected {
+                *stats.rejected_by_reason.entry(reason).or_default() += count;
+            }
+            for (ttype, count) in &client_stats.by_type {
+                *stats.rows_by_type.entry(ttype).or_default() += count;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Copy the stats and the account summary of the `Transactor`.
+    pub fn snapshot(&self) -> AppResult<Snapshot> {
+        Ok(Snapshot {
+            stats: self.stats()?,
+            accounts: self.account_summaries().collect(),
+        })
+    }
+}
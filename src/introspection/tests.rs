@@ -0,0 +1,105 @@
+use super::*;
+use crate::error::{AppError, AppResult, TransactionError};
+use crate::test_util::TestHarness;
+
+#[tokio::test]
+async fn introspect_accounts_and_stats() -> AppResult<()> {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(1, 1, "5.0")
+        .await
+        .deposit(1, 2, "3.0")
+        .await
+        .deposit(2, 3, "1.0")
+        .await
+        .withdraw(2, 4, "2.0")
+        .await
+        .expect_rejected(TransactionError::AccountHasInsufficientFundsAvailable {
+            cid: ClientId(2),
+        })
+        .dispute(1, 2)
+        .await
+        .deposit(3, 5, "1.0")
+        .await
+        .dispute(3, 5)
+        .await
+        .resolve(3, 5)
+        .await
+        .chargeback(3, 5)
+        .await
+        .expect_accepted();
+    let transactor = harness.transactor();
+    assert_eq!(transactor.len_accounts(), 3);
+    assert_eq!(
+        transactor
+            .iter_accounts()
+            .map(|account| account.client())
+            .collect::<Vec<_>>(),
+        [ClientId(1), ClientId(2), ClientId(3)]
+    );
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), Currency::from_str("5.0")?);
+    assert_eq!(account.held(), Currency::from_str("3.0")?);
+    assert_eq!(account.open_disputes(), 1);
+    assert_eq!(
+        account
+            .transactions(TransactionState::Processed)
+            .map(Transaction::tid)
+            .collect::<Vec<_>>(),
+        [TransactionId(1)]
+    );
+    assert!(transactor.account(ClientId(3)).unwrap().is_locked());
+    assert!(transactor.account(ClientId(4)).is_none());
+    let stats = transactor.stats()?;
+    assert_eq!(
+        stats,
+        EngineStats {
+            rows_processed: 9,
+            rows_accepted: 8,
+            rows_rejected: 1,
+            rejected_by_reason: BTreeMap::from([("insufficient_funds", 1)]),
+            rows_by_type: BTreeMap::from([
+                ("chargeback", 1),
+                ("deposit", 4),
+                ("dispute", 2),
+                ("resolve", 1),
+                ("withdrawal", 1),
+            ]),
+            accounts: 3,
+            locked_accounts: 1,
+            open_disputes: 1,
+            resident_transactions: stats.resident_transactions,
+            parked_transactions: 0,
+            max_tid: Some(TransactionId(5)),
+            periods_closed: 0,
+            total_available: Currency::from_str("7.0")?,
+            total_held: Currency::from_str("2.0")?,
+            data_quality: vec![],
+        }
+    );
+    let snapshot = transactor.snapshot()?;
+    assert_eq!(snapshot.stats, stats);
+    assert_eq!(
+        snapshot.accounts,
+        transactor.account_summaries().collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn report_funds_that_cant_be_summed() -> AppResult<()> {
+    let max = Currency::new(rust_decimal::Decimal::MAX).to_output_string();
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(1, 1, &max)
+        .await
+        .expect_accepted()
+        .deposit(2, 2, &max)
+        .await
+        .expect_accepted();
+    assert!(matches!(
+        harness.transactor().stats(),
+        Err(AppError::FundsOverflow)
+    ));
+    Ok(())
+}
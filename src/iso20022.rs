@@ -0,0 +1,133 @@
+//! This module defines an ingestion adapter for ISO 20022 bank-to-customer
+//! debit/credit notifications (`camt.054`) as produced by bank interfaces.
+//!
+//! Only the subset of the message needed to derive `Transaction`s is read:
+//! * The client is identified by the account's `Acct/Id/Othr/Id`, which
+//!   must be numeric.
+//! * Each booked `Ntry` becomes a deposit (`CRDT`) or a withdrawal (`DBIT`).
+//!   Its `NtryRef` is used as the transaction id, and must be numeric.
+//! * Returns are reversal entries. For those the `CdtDbtInd` already states
+//!   the direction of the reversal itself, so they need no special casing.
+//! * Pending and informational entries are skipped, since they don't
+//!   affect the balance.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Currency, Transaction, TransactionId, TransactionType, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::PathBuf;
+
+/// Parse the booked entries of a `camt.054` message into `Transaction`s,
+/// in document order.
+pub fn parse_camt054(xml: &str) -> AppResult<Vec<Transaction>> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut buf = vec![];
+    let mut path: Vec<Vec<u8>> = vec![];
+    let mut cid: Option<ClientId> = None;
+    let mut entry = Entry::default();
+    let mut transactions = vec![];
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(e) => {
+                path.push(e.local_name().to_vec());
+                if ends_with(&path, &["Ntry"]) {
+                    entry = Entry::default();
+                }
+            }
+            Event::End(_) => {
+                if ends_with(&path, &["Ntry"]) {
+                    if let Some(transaction) = entry.to_transaction(cid)? {
+                        transactions.push(transaction);
+                    }
+                }
+                let _ = path.pop();
+            }
+            Event::Text(e) => {
+                let text = e.unescape_and_decode(&reader)?;
+                if ends_with(&path, &["Ntfctn", "Acct", "Id", "Othr", "Id"]) {
+                    cid = Some(ClientId(text.parse()?));
+                } else if ends_with(&path, &["Ntry", "NtryRef"]) {
+                    entry.tid = Some(TransactionId(text.parse()?));
+                } else if ends_with(&path, &["Ntry", "Amt"]) {
+                    entry.amount = Some(Currency::from_str(&text)?);
+                } else if ends_with(&path, &["Ntry", "CdtDbtInd"]) {
+                    entry.direction = Some(text);
+                } else if ends_with(&path, &["Ntry", "Sts"])
+                    || ends_with(&path, &["Ntry", "Sts", "Cd"])
+                {
+                    entry.status = Some(text);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(transactions)
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Read a `camt.054` message from the file at `filepath`, and process
+    /// its booked entries in the same way as the rows of a `CSV` file.
+    pub async fn process_camt054_file(&mut self, filepath: PathBuf) -> AppResult<()> {
+        let xml = tokio::fs::read_to_string(filepath).await?;
+        for transaction in parse_camt054(&xml)? {
+            match self.process_row(transaction).await {
+                Ok(()) => {}
+                Err(e) if e.is_fatal() => return Err(e.into()),
+                Err(_transaction_error) => {
+                    // NOTE: Rejected transactions are ignored, exactly
+                    //       as in `Transactor::process_csv_file()`.
+                }
+            }
+        }
+        self.expire_parked_transactions(None);
+        Ok(())
+    }
+}
+
+/// The fields of an `Ntry` element collected so far.
+#[derive(Debug, Default)]
+struct Entry {
+    tid: Option<TransactionId>,
+    amount: Option<Currency>,
+    direction: Option<String>,
+    status: Option<String>,
+}
+
+impl Entry {
+    /// Convert a complete entry for the client with id `cid` to a
+    /// `Transaction`, or return `None` if the entry isn't booked.
+    fn to_transaction(&self, cid: Option<ClientId>) -> AppResult<Option<Transaction>> {
+        if self.status.as_deref() != Some("BOOK") {
+            return Ok(None);
+        }
+        let missing = |element: &'static str| AppError::MalformedIso20022Message { element };
+        let ttype = match self.direction.as_deref() {
+            Some("CRDT") => TransactionType::Deposit,
+            Some("DBIT") => TransactionType::Withdrawal,
+            _ => return Err(missing("CdtDbtInd")),
+        };
+        Ok(Some(Transaction {
+            ttype,
+            cid: cid.ok_or_else(|| missing("Acct"))?,
+            tid: self.tid.ok_or_else(|| missing("NtryRef"))?,
+            amount: Some(self.amount.ok_or_else(|| missing("Amt"))?),
+        }))
+    }
+}
+
+/// Returns `true` if the element `path` ends with the element names
+/// in `suffix`.
+fn ends_with(path: &[Vec<u8>], suffix: &[&str]) -> bool {
+    path.len() >= suffix.len()
+        && path[path.len() - suffix.len()..]
+            .iter()
+            .zip(suffix.iter())
+            .all(|(element, name)| element.as_slice() == name.as_bytes())
+}
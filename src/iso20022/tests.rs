@@ -0,0 +1,66 @@
+use super::*;
+use crate::error::AppResult;
+
+const CAMT054: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.054.001.08">
+  <BkToCstmrDbtCdtNtfctn>
+    <GrpHdr><MsgId>MSG-1</MsgId></GrpHdr>
+    <Ntfctn>
+      <Id>NTFCTN-1</Id>
+      <Acct><Id><Othr><Id>7</Id></Othr></Id></Acct>
+      <Ntry>
+        <NtryRef>1</NtryRef>
+        <Amt Ccy="EUR">10.50</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+      </Ntry>
+      <Ntry>
+        <NtryRef>2</NtryRef>
+        <Amt Ccy="EUR">3.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>PDNG</Cd></Sts>
+      </Ntry>
+      <Ntry>
+        <NtryRef>3</NtryRef>
+        <Amt Ccy="EUR">2.25</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <RvslInd>true</RvslInd>
+        <Sts>BOOK</Sts>
+      </Ntry>
+    </Ntfctn>
+  </BkToCstmrDbtCdtNtfctn>
+</Document>"#;
+
+#[test]
+fn parse_booked_entries() -> AppResult<()> {
+    let transactions = parse_camt054(CAMT054)?;
+    assert_eq!(
+        transactions,
+        vec![
+            Transaction {
+                ttype: TransactionType::Deposit,
+                cid: ClientId(7),
+                tid: TransactionId(1),
+                amount: Some(Currency::from_str("10.50")?),
+            },
+            Transaction {
+                ttype: TransactionType::Withdrawal,
+                cid: ClientId(7),
+                tid: TransactionId(3),
+                amount: Some(Currency::from_str("2.25")?),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_entry_without_direction() {
+    let xml = CAMT054.replace("<CdtDbtInd>CRDT</CdtDbtInd>", "");
+    assert!(matches!(
+        parse_camt054(&xml),
+        Err(AppError::MalformedIso20022Message {
+            element: "CdtDbtInd"
+        })
+    ));
+}
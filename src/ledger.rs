@@ -0,0 +1,217 @@
+//! This module defines per-client account statements, and the export of
+//! the ledger of all clients.
+//!
+//! When `Config::keep_statements` is set, every transaction that changes an
+//! account is recorded as a `StatementLine`, along with the balances of the
+//! account right after it. `Transactor::statement()` returns the lines of a
+//! client in the order they were recorded, i.e. chronologically.
+//!
+//! The ledger holds every deposit and withdrawal in the account histories,
+//! across all clients, along with its final state. It's available whether
+//! or not statements are kept, and is written for audits with
+//! `Transactor::write_ledger()`. The results of a search are written in the
+//! same layout with `Transactor::write_search_results()`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{
+    ClientId, Currency, HistoryEntry, Transaction, TransactionId, TransactionQuery, Transactor,
+};
+use crate::error::AppResult;
+use crate::period::state_name;
+use crate::store::AccountStore;
+use serde_derive::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// The layout of an exported ledger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerFormat {
+    /// `CSV`, in `client,tx,type,amount,state` form
+    Csv,
+    /// One `JSON` object per transaction, with the same fields as `Csv`
+    JsonLines,
+}
+
+impl Default for LedgerFormat {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Csv
+    }
+}
+
+/// A line of a ledger exported as JSON Lines.
+#[derive(Serialize)]
+struct LedgerRow<'a> {
+    client: ClientId,
+    tx: TransactionId,
+    #[serde(rename = "type")]
+    ttype: &'a str,
+    amount: &'a str,
+    state: &'a str,
+}
+
+/// A transaction in the statement of a client, along with the running
+/// balances of the account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StatementLine {
+    /// The number of rows processed when the line was recorded.
+    pub row: u64,
+    pub transaction: Transaction,
+    /// Whether an operator voided `transaction`, as opposed to the
+    /// transaction being applied.
+    pub voided: bool,
+    pub available: Currency,
+    pub held: Currency,
+    pub total: Currency,
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Return the statement of the client with id `cid`, in chronological
+    /// order. It's empty unless `Config::keep_statements` was set while the
+    /// transactions of the client were processed.
+    pub fn statement(&self, cid: ClientId) -> &[StatementLine] {
+        self.statements
+            .get(&cid)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Write the statement of the client with id `cid` to `sink` as `CSV`,
+    /// in `row,type,tx,amount,available,held,total` form. Voided
+    /// transactions have `void` as their type.
+    pub async fn write_statement<W>(&self, cid: ClientId, sink: &mut W) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let rounding = self.config.amount_scale.on_violation;
+        let mut csv = String::from("row,type,tx,amount,available,held,total\n");
+        for line in self.statement(cid) {
+            let t = &line.transaction;
+            let ttype = if line.voided {
+                "void"
+            } else {
+                t.ttype().name()
+            };
+            let amount = t
+                .amount()
+                .map(|amount| amount.rounded_for_output(rounding).to_output_string())
+                .unwrap_or_default();
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                line.row,
+                ttype,
+                t.tid(),
+                amount,
+                line.available
+                    .rounded_for_output(rounding)
+                    .to_output_string(),
+                line.held.rounded_for_output(rounding).to_output_string(),
+                line.total.rounded_for_output(rounding).to_output_string(),
+            ));
+        }
+        sink.write_all(csv.as_bytes()).await?;
+        sink.flush().await?;
+        Ok(())
+    }
+
+    /// Record `t` in the statement of its client, if statements are kept.
+    pub(crate) fn record_statement_line(&mut self, t: &Transaction, voided: bool) {
+        if !self.config.keep_statements {
+            return;
+        }
+        if let Some(account) = self.accounts.get(&t.cid()) {
+            let line = StatementLine {
+                row: self.rows_processed,
+                transaction: *t,
+                voided,
+                available: account.available,
+                held: account.held,
+                total: account.total,
+            };
+            self.statements.entry(t.cid()).or_default().push(line);
+        }
+    }
+
+    /// The transactions of all clients, in their final state, ordered by
+    /// `TransactionId` and then by `ClientId`. `TransactionId`s are taken
+    /// to be assigned chronologically.
+    pub fn ledger(&self) -> Vec<HistoryEntry> {
+        let mut entries = self.search(&TransactionQuery::default());
+        // NOTE: The sort is stable, so entries with the same `TransactionId`
+        //       stay ordered by `ClientId`, as `search()` returns them.
+        entries.sort_by_key(|entry| entry.transaction.tid());
+        entries
+    }
+
+    /// Write the ledger to `sink` in the given `format`.
+    pub async fn write_ledger<W>(&self, sink: &mut W, format: LedgerFormat) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.write_entries(self.ledger(), sink, format).await
+    }
+
+    /// Write the transactions matching `query`, in the order `search()`
+    /// returns them, to `sink` in the given `format`.
+    pub async fn write_search_results<W>(
+        &self,
+        query: &TransactionQuery,
+        sink: &mut W,
+        format: LedgerFormat,
+    ) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.write_entries(self.search(query), sink, format).await
+    }
+
+    /// Write the `entries` to `sink` in the given `format`.
+    async fn write_entries<W>(
+        &self,
+        entries: Vec<HistoryEntry>,
+        sink: &mut W,
+        format: LedgerFormat,
+    ) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let rounding = self.config.amount_scale.on_violation;
+        let mut out = match format {
+            LedgerFormat::Csv => String::from("client,tx,type,amount,state\n"),
+            LedgerFormat::JsonLines => String::new(),
+        };
+        for entry in entries {
+            let t = &entry.transaction;
+            let amount = t
+                .amount()
+                .map(|amount| amount.rounded_for_output(rounding).to_output_string())
+                .unwrap_or_default();
+            let state = state_name(entry.state);
+            match format {
+                LedgerFormat::Csv => out.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    t.cid(),
+                    t.tid(),
+                    t.ttype().name(),
+                    amount,
+                    state
+                )),
+                LedgerFormat::JsonLines => {
+                    let line = LedgerRow {
+                        client: t.cid(),
+                        tx: t.tid(),
+                        ttype: t.ttype().name(),
+                        amount: &amount,
+                        state,
+                    };
+                    out.push_str(&serde_json::to_string(&line)?);
+                    out.push('\n');
+                }
+            }
+        }
+        sink.write_all(out.as_bytes()).await?;
+        sink.flush().await?;
+        Ok(())
+    }
+}
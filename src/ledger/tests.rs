@@ -0,0 +1,153 @@
+use super::*;
+use crate::config::Config;
+use crate::core::{TransactionId, TransactionState};
+use crate::test_util::TestHarness;
+
+#[tokio::test]
+async fn statement_has_running_balances() -> AppResult<()> {
+    let config = Config {
+        keep_statements: true,
+        ..Config::default()
+    };
+    let mut harness = TestHarness::with_config(config);
+    harness
+        .deposit(1, 1, "5.0")
+        .await
+        .deposit(2, 2, "1.0")
+        .await
+        .withdraw(1, 3, "9.0")
+        .await
+        .withdraw(1, 4, "2.0")
+        .await
+        .dispute(1, 1)
+        .await
+        .resolve(1, 1)
+        .await;
+    let transactor = harness.transactor_mut();
+    transactor
+        .void_transaction(ClientId(1), TransactionId(4))
+        .await?;
+    // NOTE: The rejected withdrawal of transaction 3 isn't on the statement.
+    let lines = transactor
+        .statement(ClientId(1))
+        .iter()
+        .map(|line| {
+            (
+                line.row,
+                line.transaction.tid(),
+                line.voided,
+                line.available.to_output_string(),
+                line.held.to_output_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        (1, 1, false, "5.0000", "0.0000"),
+        (4, 4, false, "3.0000", "0.0000"),
+        (5, 1, false, "-2.0000", "5.0000"),
+        (6, 1, false, "3.0000", "0.0000"),
+        (6, 4, true, "5.0000", "0.0000"),
+    ]
+    .iter()
+    .map(|&(row, tid, voided, available, held)| {
+        (
+            row,
+            TransactionId(tid),
+            voided,
+            available.to_string(),
+            held.to_string(),
+        )
+    })
+    .collect::<Vec<_>>();
+    assert_eq!(lines, expected);
+    assert_eq!(transactor.statement(ClientId(2)).len(), 1);
+    assert!(transactor.statement(ClientId(3)).is_empty());
+    let mut csv = vec![];
+    transactor.write_statement(ClientId(2), &mut csv).await?;
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "row,type,tx,amount,available,held,total\n2,deposit,2,1.0000,1.0000,0.0000,1.0000\n"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn statements_are_off_by_default() -> AppResult<()> {
+    let mut harness = TestHarness::new();
+    harness.deposit(1, 1, "5.0").await.expect_accepted();
+    assert!(harness.transactor().statement(ClientId(1)).is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn export_ledger_in_both_formats() -> AppResult<()> {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(2, 1, "5.0")
+        .await
+        .deposit(1, 2, "3.0")
+        .await
+        .withdraw(2, 3, "1.0")
+        .await
+        .dispute(1, 2)
+        .await
+        .dispute(2, 1)
+        .await
+        .resolve(2, 1)
+        .await;
+    let transactor = harness.transactor();
+    let mut csv = vec![];
+    transactor.write_ledger(&mut csv, LedgerFormat::Csv).await?;
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "client,tx,type,amount,state\n\
+         2,1,deposit,5.0000,resolved\n\
+         1,2,deposit,3.0000,disputed\n\
+         2,3,withdrawal,1.0000,processed\n"
+    );
+    let mut jsonl = vec![];
+    transactor
+        .write_ledger(&mut jsonl, LedgerFormat::JsonLines)
+        .await?;
+    let jsonl = String::from_utf8(jsonl).unwrap();
+    let first = jsonl.lines().next().unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(first)?,
+        serde_json::json!({
+            "client": 2,
+            "tx": 1,
+            "type": "deposit",
+            "amount": "5.0000",
+            "state": "resolved",
+        })
+    );
+    assert_eq!(jsonl.lines().count(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_search_results_as_csv() -> AppResult<()> {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(2, 1, "5.0")
+        .await
+        .deposit(1, 2, "3.0")
+        .await
+        .dispute(1, 2)
+        .await;
+    let query = TransactionQuery {
+        state: Some(TransactionState::Disputed),
+        ..TransactionQuery::default()
+    };
+    let mut csv = vec![];
+    harness
+        .transactor()
+        .write_search_results(&query, &mut csv, LedgerFormat::Csv)
+        .await?;
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "client,tx,type,amount,state\n\
+         1,2,deposit,3.0000,disputed\n"
+    );
+    Ok(())
+}
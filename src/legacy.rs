@@ -0,0 +1,111 @@
+//! This module defines a migration aid that mirrors every accepted
+//! transaction to a legacy system, so that `giant-squid` can run in
+//! parallel with the system it replaces during cutover.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{Transaction, Transactor};
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use std::fmt;
+use std::io::{Error as IoError, Write};
+
+/// The format of a legacy record. Every occurrence of the placeholders
+/// `{type}`, `{client}`, `{tx}` and `{amount}` is replaced by the
+/// corresponding field of the transaction. `{amount}` is empty for
+/// transactions without an amount.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegacyTemplate(String);
+
+impl LegacyTemplate {
+    #[inline(always)]
+    pub fn new(template: impl Into<String>) -> Self {
+        Self(template.into())
+    }
+
+    /// Render the legacy record for the transaction `t`.
+    pub fn render(&self, t: &Transaction) -> String {
+        let amount = t
+            .amount
+            .map_or(String::new(), |amount| format!("{:?}", amount));
+        self.0
+            .replace("{type}", t.ttype.name())
+            .replace("{client}", &t.cid.to_string())
+            .replace("{tx}", &t.tid.to_string())
+            .replace("{amount}", &amount)
+    }
+}
+
+impl Default for LegacyTemplate {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new("{type},{client},{tx},{amount}")
+    }
+}
+
+/// A secondary sink that receives a legacy record, one per line, for
+/// every transaction that the `Transactor` accepts.
+pub struct LegacySink {
+    template: LegacyTemplate,
+    writer: Box<dyn Write + Send + Sync>,
+    /// The first write error, after which nothing more is written
+    error: Option<IoError>,
+}
+
+impl LegacySink {
+    #[inline(always)]
+    pub fn new(template: LegacyTemplate, writer: Box<dyn Write + Send + Sync>) -> Self {
+        Self {
+            template,
+            writer,
+            error: None,
+        }
+    }
+
+    /// Write the legacy record for the transaction `t`.
+    /// A write error is kept until `finish()` is called, since the
+    /// transaction itself has already been applied by then.
+    pub(crate) fn emit(&mut self, t: &Transaction) {
+        if self.error.is_none() {
+            let record = self.template.render(t);
+            if let Err(e) = writeln!(self.writer, "{}", record) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Flush the sink, and return the first error that occurred, if any.
+    pub fn finish(&mut self) -> AppResult<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e.into());
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LegacySink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LegacySink")
+            .field("template", &self.template)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Mirror every transaction accepted from now on to `sink`.
+    pub fn set_legacy_sink(&mut self, sink: LegacySink) {
+        self.legacy_sink = Some(sink);
+    }
+
+    /// Flush the legacy sink, if any, and report whether all records
+    /// were written successfully.
+    pub fn finish_legacy_sink(&mut self) -> AppResult<()> {
+        match self.legacy_sink.as_mut() {
+            Some(sink) => sink.finish(),
+            None => Ok(()),
+        }
+    }
+}
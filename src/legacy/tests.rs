@@ -0,0 +1,73 @@
+use super::*;
+use crate::core::{ClientId, Currency, TransactionId, TransactionType};
+use std::sync::{Arc, Mutex};
+
+/// A writer whose output can still be inspected after it was handed off.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn render_template() -> AppResult<()> {
+    let template = LegacyTemplate::new("TX|{tx}|{client}|{type}|{amount}|");
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(2),
+        tid: TransactionId(7),
+        amount: Some(Currency::from_str("1.5")?),
+    };
+    assert_eq!(template.render(&deposit), "TX|7|2|deposit|1.5000|");
+    let dispute = Transaction {
+        ttype: TransactionType::Dispute,
+        amount: None,
+        ..deposit
+    };
+    assert_eq!(template.render(&dispute), "TX|7|2|dispute||");
+    Ok(())
+}
+
+#[tokio::test]
+async fn mirror_accepted_transactions() -> AppResult<()> {
+    let buffer = SharedBuffer::default();
+    let mut transactor = Transactor::new();
+    transactor.set_legacy_sink(LegacySink::new(
+        LegacyTemplate::default(),
+        Box::new(buffer.clone()),
+    ));
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("5.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_row(transaction).await;
+    }
+    transactor.finish_legacy_sink()?;
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(output, "deposit,1,1,1.0000\ndispute,1,1,\n");
+    Ok(())
+}
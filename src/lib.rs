@@ -1,4 +1,54 @@
+//! A streaming transaction engine that applies deposits, withdrawals and
+//! disputes to client accounts.
 //!
+//! The engine can be embedded by feeding a `Transactor` either whole `CSV`
+//! files with `Transactor::process_csv_file()`, or individual transactions
+//! with `Transactor::process_row()`, and then rendering the account summary
+//! with `Transactor::write_output()`.
 
+pub mod audit;
+#[cfg(feature = "sync")]
+pub mod blocking;
+#[cfg(feature = "object-store")]
+pub mod bucket;
+pub mod bundle;
+pub mod compare;
+pub mod compression;
+pub mod config;
 pub mod core;
+pub mod digest;
 pub mod error;
+#[cfg(feature = "http")]
+pub mod http;
+pub(crate) mod index;
+pub mod introspection;
+#[cfg(feature = "iso20022")]
+pub mod iso20022;
+pub mod ledger;
+pub mod legacy;
+pub mod merge;
+pub mod metrics;
+pub mod migrations;
+pub mod partition;
+pub mod period;
+pub mod quality;
+pub mod rebalance;
+#[cfg(any(feature = "http", feature = "object-store"))]
+mod remote;
+pub mod rolling;
+pub mod run_log;
+pub mod shard;
+pub mod simulation;
+pub mod snapshot;
+pub mod statement;
+pub mod store;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod trailer;
+pub mod validate;
+
+pub use crate::config::Config;
+pub use crate::core::{
+    AccountSummary, ClientId, Currency, Transaction, TransactionId, TransactionType, Transactor,
+};
+pub use crate::error::{AppError, AppResult, TransactionError, TransactionResult};
@@ -0,0 +1,195 @@
+//! This module defines the merging of several ordered `CSV` sources, e.g.
+//! per-region files, into a single totally ordered stream of transactions.
+//!
+//! Each source must already be ordered by its merge key: a column with an
+//! unsigned integer such as a timestamp or a sequence number. The sources
+//! are read concurrently, each by its own task, and merged with a k-way
+//! merge on the key. Rows with equal keys are arbitrated by the position of
+//! their source in the list of sources, and then by their order within
+//! the source, so the merged order is the same on every run.
+//!
+//! As a result, the account summary of a merge is byte for byte the same
+//! as that of processing the merged rows in order from a single file, no
+//! matter how the reading tasks are scheduled. Only the line numbers of
+//! rejected rows differ, as they refer to the rows' own sources.
+
+#[cfg(test)]
+mod tests;
+
+use crate::compression::{decompress, Compression};
+use crate::core::{resolve_type_alias, Transaction, TransactionType, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use csv_async::AsyncReaderBuilder;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+/// The merge key used when none is given: the transaction id, which is
+/// taken to be assigned chronologically.
+pub const DEFAULT_MERGE_KEY: &str = "tx";
+
+/// The number of rows that each source reads ahead of the merge.
+const READ_AHEAD_ROWS: usize = 1024;
+
+/// A row read from one of the merged sources.
+#[derive(Debug)]
+struct MergeRow {
+    key: u64,
+    source: usize,
+    line: u64,
+    transaction: Transaction,
+}
+
+impl MergeRow {
+    #[inline(always)]
+    fn position(&self) -> (u64, usize, u64) {
+        (self.key, self.source, self.line)
+    }
+}
+
+impl PartialEq for MergeRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.position() == other.position()
+    }
+}
+
+impl Eq for MergeRow {}
+
+impl PartialOrd for MergeRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.position().cmp(&other.position())
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Process the transactions in the `CSV` files at `filepaths` in the
+    /// order of their `key_column`, as if they were a single file. Each
+    /// file must be ordered by `key_column` already. The line numbers of
+    /// rejected rows are those within their own file.
+    pub async fn process_merged_csv_files(
+        &mut self,
+        filepaths: &[PathBuf],
+        key_column: &str,
+    ) -> AppResult<()> {
+        let mut sources = Vec::with_capacity(filepaths.len());
+        for (source, filepath) in filepaths.iter().enumerate() {
+            let (sender, receiver) = mpsc::channel(READ_AHEAD_ROWS);
+            tokio::spawn(read_source(
+                source,
+                filepath.clone(),
+                self.config.compression_of(filepath),
+                key_column.to_string(),
+                self.config.type_aliases.clone(),
+                sender,
+            ));
+            sources.push(receiver);
+        }
+        let mut heads = BinaryHeap::with_capacity(sources.len());
+        for receiver in sources.iter_mut() {
+            if let Some(row) = receiver.recv().await {
+                heads.push(Reverse(row?));
+            }
+        }
+        while let Some(Reverse(row)) = heads.pop() {
+            let source = row.source;
+            self.process_csv_row(row.transaction, Some(row.line))
+                .await?;
+            if let Some(next) = sources[source].recv().await {
+                heads.push(Reverse(next?));
+            }
+        }
+        self.expire_parked_transactions(None);
+        Ok(())
+    }
+}
+
+/// Read the rows of the source with index `source` and send them to the
+/// merge, followed by the error that ended the source early, if any.
+async fn read_source(
+    source: usize,
+    filepath: PathBuf,
+    compression: Compression,
+    key_column: String,
+    aliases: BTreeMap<String, TransactionType>,
+    sender: mpsc::Sender<AppResult<MergeRow>>,
+) {
+    let rows = read_source_rows(
+        source,
+        filepath,
+        compression,
+        &key_column,
+        &aliases,
+        &sender,
+    )
+    .await;
+    if let Err(e) = rows {
+        let _ = sender.send(Err(e)).await;
+    }
+}
+
+async fn read_source_rows(
+    source: usize,
+    filepath: PathBuf,
+    compression: Compression,
+    key_column: &str,
+    aliases: &BTreeMap<String, TransactionType>,
+    sender: &mpsc::Sender<AppResult<MergeRow>>,
+) -> AppResult<()> {
+    let file = tokio::fs::File::open(&filepath).await?;
+    let mut reader = AsyncReaderBuilder::new()
+        .trim(csv_async::Trim::All)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .create_reader(decompress(file, compression));
+    let headers = reader.headers().await?.clone();
+    let type_column = headers.iter().position(|header| header == "type");
+    let key_idx = headers
+        .iter()
+        .position(|header| header == key_column)
+        .ok_or_else(|| AppError::MissingMergeKeyColumn {
+            path: filepath.clone(),
+            column: key_column.to_string(),
+        })?;
+    let mut records = reader.into_records();
+    let mut last_key = 0;
+    while let Some(record) = records.next().await {
+        let record = resolve_type_alias(record?, type_column, aliases);
+        let line = record.position().map_or(0, csv_async::Position::line);
+        let transaction = record.deserialize::<Transaction>(Some(&headers))?;
+        let value = record.get(key_idx).unwrap_or_default();
+        let key = value
+            .parse::<u64>()
+            .map_err(|_| AppError::InvalidMergeKey {
+                path: filepath.clone(),
+                line,
+                value: value.to_string(),
+            })?;
+        if key < last_key {
+            return Err(AppError::UnorderedMergeSource {
+                path: filepath,
+                line,
+            });
+        }
+        last_key = key;
+        let row = MergeRow {
+            key,
+            source,
+            line,
+            transaction,
+        };
+        if sender.send(Ok(row)).await.is_err() {
+            // NOTE: The merge ended early because of a fatal error.
+            break;
+        }
+    }
+    Ok(())
+}
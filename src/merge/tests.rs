@@ -0,0 +1,217 @@
+use super::*;
+use crate::config::Config;
+use crate::core::{ClientId, Currency, TransactionId};
+use crate::test_util::corpus::Case;
+use crate::test_util::temp::TempPath;
+
+/// Write each of `sources` to a file in a new temporary directory.
+fn write_sources(name: &str, sources: &[&str]) -> AppResult<(TempPath, Vec<PathBuf>)> {
+    let dir = TempPath::dir(name)?;
+    let mut paths = vec![];
+    for (idx, source) in sources.iter().enumerate() {
+        let path = dir.join(format!("source-{}.csv", idx));
+        std::fs::write(&path, source)?;
+        paths.push(path);
+    }
+    Ok((dir, paths))
+}
+
+#[tokio::test]
+async fn sources_are_merged_by_key() -> AppResult<()> {
+    let (_dir, paths) = write_sources(
+        "merge-by-key",
+        &[
+            "ts,type,client,tx,amount\n\
+             1,deposit,1,1,5.0\n\
+             5,withdrawal,1,3,4.0\n",
+            "ts,type,client,tx,amount\n\
+             3,withdrawal,1,2,3.0\n",
+        ],
+    )?;
+    let mut transactor = Transactor::new();
+    transactor.process_merged_csv_files(&paths, "ts").await?;
+    let rejected: Vec<_> = transactor
+        .rejected()
+        .map(|r| r.transaction().tid())
+        .collect();
+    assert_eq!(rejected, [TransactionId(3)]);
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), Currency::from_str("2.0")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn equal_keys_are_ordered_by_source() -> AppResult<()> {
+    let (_dir, paths) = write_sources(
+        "merge-ties",
+        &[
+            "seq,type,client,tx,amount\n7,withdrawal,1,2,1.0\n",
+            "seq,type,client,tx,amount\n7,deposit,1,1,1.0\n",
+        ],
+    )?;
+    let mut transactor = Transactor::new();
+    transactor.process_merged_csv_files(&paths, "seq").await?;
+    // NOTE: The withdrawal comes first, because its source is listed first.
+    assert_eq!(transactor.rejected().count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn merging_by_transaction_id() -> AppResult<()> {
+    let (_dir, paths) = write_sources(
+        "merge-default",
+        &[
+            "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,1,4,1.0\n",
+            "type,client,tx,amount\ndeposit,2,2,1.0\ndeposit,2,3,1.0\n",
+        ],
+    )?;
+    let mut transactor = Transactor::new();
+    transactor
+        .process_merged_csv_files(&paths, DEFAULT_MERGE_KEY)
+        .await?;
+    assert_eq!(transactor.stats()?.rows_accepted, 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unordered_sources_are_fatal() -> AppResult<()> {
+    let (_dir, paths) = write_sources(
+        "merge-unordered",
+        &["ts,type,client,tx,amount\n2,deposit,1,1,1.0\n1,deposit,1,2,1.0\n"],
+    )?;
+    let mut transactor = Transactor::new();
+    let result = transactor.process_merged_csv_files(&paths, "ts").await;
+    let missing = transactor.process_merged_csv_files(&paths, "time").await;
+    match result {
+        Err(AppError::UnorderedMergeSource { path, line: 3 }) => assert_eq!(path, paths[0]),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match missing {
+        Err(AppError::MissingMergeKeyColumn { column, .. }) => assert_eq!(column, "time"),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    Ok(())
+}
+
+/// Every output format of the account summary of `transactor`.
+async fn render_outputs<S: AccountStore>(transactor: &Transactor<S>) -> AppResult<Vec<Vec<u8>>> {
+    let (mut csv, mut trailer, mut jsonl) = (vec![], vec![], vec![]);
+    transactor.write_output(&mut csv).await?;
+    transactor.write_output_with_trailer(&mut trailer).await?;
+    transactor.write_output_jsonl(&mut jsonl).await?;
+    Ok(vec![csv, trailer, jsonl])
+}
+
+/// Split the rows of `csv` round-robin over `count` sources, each with a
+/// `seq` column that holds the index of the row in `csv`.
+fn split_round_robin(csv: &str, count: usize) -> Vec<String> {
+    let mut lines = csv.lines();
+    let header = format!("seq,{}\n", lines.next().unwrap_or_default());
+    let mut sources = vec![header; count];
+    for (seq, line) in lines.enumerate() {
+        sources[seq % count].push_str(&format!("{},{}\n", seq, line));
+    }
+    sources
+}
+
+// NOTE: Downstream diffs account summaries across environments, so the
+//       concurrent modes must produce the very same bytes as processing
+//       the input in order on a single task, whatever the scheduling.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn merged_output_is_identical_to_sequential_output() -> AppResult<()> {
+    for case in Case::generate_all(7) {
+        let mut sequential = Transactor::new();
+        sequential.process_csv_reader(case.csv.as_bytes()).await?;
+        let expected = render_outputs(&sequential).await?;
+        assert_eq!(expected[0], case.expected_output.as_bytes());
+        for count in [1, 3, 8] {
+            let sources = split_round_robin(&case.csv, count);
+            let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+            let (_dir, paths) = write_sources(&format!("determinism-{}", case.name()), &sources)?;
+            for _ in 0..3 {
+                let mut merged = Transactor::new();
+                merged.process_merged_csv_files(&paths, "seq").await?;
+                assert_eq!(
+                    render_outputs(&merged).await?,
+                    expected,
+                    "case {} merged from {} sources",
+                    case.name(),
+                    count
+                );
+                assert_eq!(merged.rejected().count(), case.expected_rejections);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn reading_ahead_doesnt_change_the_output() -> AppResult<()> {
+    for case in Case::generate_all(11) {
+        let mut outputs = vec![];
+        for prefetch_rows in [0, 1, 7, 4096] {
+            let config = Config {
+                prefetch_rows,
+                ..Config::default()
+            };
+            let mut transactor = Transactor::with_config(config);
+            transactor.process_csv_reader(case.csv.as_bytes()).await?;
+            outputs.push(render_outputs(&transactor).await?);
+        }
+        assert!(
+            outputs.windows(2).all(|pair| pair[0] == pair[1]),
+            "case {}",
+            case.name()
+        );
+    }
+    Ok(())
+}
+
+// NOTE: Reading files with `tokio-uring` requires its own runtime.
+#[cfg(not(feature = "async_file_reads"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn pipelining_doesnt_change_the_output() -> AppResult<()> {
+    for case in Case::generate_all(5) {
+        let (_dir, paths) = write_sources(&format!("pipeline-{}", case.name()), &[&case.csv])?;
+        let mut outputs = vec![];
+        for pipeline_depth in [0, 1, 64] {
+            let config = Config {
+                pipeline_depth,
+                ..Config::default()
+            };
+            let mut transactor = Transactor::with_config(config);
+            transactor.process_csv_file(paths[0].clone()).await?;
+            outputs.push(render_outputs(&transactor).await?);
+        }
+        assert!(
+            outputs.windows(2).all(|pair| pair[0] == pair[1]),
+            "case {}",
+            case.name()
+        );
+    }
+    Ok(())
+}
+
+// NOTE: Reading files with `tokio-uring` requires its own runtime.
+#[cfg(not(feature = "async_file_reads"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pipelined_parse_errors_end_processing_at_the_same_row() -> AppResult<()> {
+    let (_dir, paths) = write_sources(
+        "pipeline-error",
+        &["type,client,tx,amount\n\
+           deposit,1,1,1.0\n\
+           deposit,1,2,abc\n\
+           deposit,1,3,1.0\n"],
+    )?;
+    let config = Config {
+        pipeline_depth: 8,
+        ..Config::default()
+    };
+    let mut transactor = Transactor::with_config(config);
+    let result = transactor.process_csv_file(paths[0].clone()).await;
+    assert!(result.is_err());
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), Currency::from_str("1.0")?);
+    assert_eq!(transactor.data_quality()[0].malformed_rows, 1);
+    Ok(())
+}
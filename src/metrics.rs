@@ -0,0 +1,373 @@
+//! This module defines the operational metrics of a `Transactor`:
+//! * Per `TransactionType`, the number of accepted and rejected
+//!   transactions, and a histogram of their processing latencies.
+//!   Throughput is the rate at which the counters increase.
+//! * The durations of disputes, from the dispute to its resolve or
+//!   chargeback.
+//! * Per input source, the number of rows, the number of rows with each
+//!   kind of data quality problem, and the data quality score.
+//! * Per `BalanceAlert` rule and bound, the number of times an account
+//!   breached the bound, and the number of accounts beyond it right now.
+//!
+//! The metrics can be rendered in the Prometheus text exposition format,
+//! e.g. for the textfile collector of a Prometheus node exporter.
+
+#[cfg(test)]
+mod tests;
+
+use crate::config::{AlertBound, BalanceAlert};
+use crate::core::{ClientId, Currency, Transaction, TransactionId, TransactionType, Transactor};
+use crate::error::TransactionResult;
+use crate::quality::DataQuality;
+use crate::store::AccountStore;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// The upper bounds of the histogram buckets, in seconds.
+const BUCKET_BOUNDS: [f64; 8] = [1e-6, 1e-5, 1e-4, 1e-3, 1e-2, 1e-1, 1.0, 10.0];
+
+/// A histogram of durations, with the buckets in `BUCKET_BOUNDS`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    /// The number of observations per bucket. The last bucket holds the
+    /// observations that exceed all bounds.
+    buckets: [u64; BUCKET_BOUNDS.len() + 1],
+    sum: Duration,
+}
+
+impl Histogram {
+    /// Add `duration` to the histogram.
+    pub fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = BUCKET_BOUNDS
+            .iter()
+            .position(|&bound| seconds <= bound)
+            .unwrap_or(BUCKET_BOUNDS.len());
+        self.buckets[bucket] += 1;
+        self.sum += duration;
+    }
+
+    /// Add the observations of `other` to the histogram.
+    fn absorb(&mut self, other: &Histogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *bucket += count;
+        }
+        self.sum += other.sum;
+    }
+
+    /// The number of observations.
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// The sum of all observations.
+    #[inline(always)]
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Write the histogram named `name` with the labels `labels` in the
+    /// Prometheus text exposition format.
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKET_BOUNDS.iter().zip(self.buckets.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let count = self.count();
+        let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, count);
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum.as_secs_f64());
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, count);
+    }
+}
+
+/// The metrics of a single `TransactionType`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TypeMetrics {
+    pub accepted: u64,
+    pub rejected: u64,
+    /// The processing latencies of both accepted and rejected transactions
+    pub latency: Histogram,
+}
+
+/// How a dispute ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DisputeOutcome {
+    Resolved,
+    ChargedBack,
+}
+
+impl DisputeOutcome {
+    #[inline]
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Resolved => "resolved",
+            Self::ChargedBack => "charged_back",
+        }
+    }
+}
+
+/// The metrics collected by a `Transactor`.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    per_type: BTreeMap<TransactionType, TypeMetrics>,
+    /// The durations of disputes, per outcome
+    disputes: BTreeMap<DisputeOutcome, Histogram>,
+    /// When each disputed transaction was disputed
+    // NOTE: A chargeback follows the resolve of the same dispute, so the
+    //       start of a dispute is kept until it is charged back.
+    dispute_starts: HashMap<(ClientId, TransactionId), Instant>,
+    /// The number of breaches, per alert rule name and bound
+    alerts: BTreeMap<(String, AlertBound), u64>,
+    /// The accounts that are beyond a bound right now, per alert rule name
+    breaches: BTreeMap<String, BTreeMap<ClientId, AlertBound>>,
+    /// The data quality of the inputs, summed per source
+    data_quality: BTreeMap<String, DataQuality>,
+}
+
+impl Metrics {
+    /// Record the processing of the transaction `t`, which took `elapsed`.
+    pub(crate) fn record(
+        &mut self,
+        t: &Transaction,
+        elapsed: Duration,
+        result: &TransactionResult<()>,
+    ) {
+        let metrics = self.per_type.entry(t.ttype).or_default();
+        metrics.latency.observe(elapsed);
+        if result.is_err() {
+            metrics.rejected += 1;
+            return;
+        }
+        metrics.accepted += 1;
+        let key = (t.cid, t.tid);
+        let outcome = match t.ttype {
+            TransactionType::Dispute => {
+                let _ = self.dispute_starts.insert(key, Instant::now());
+                return;
+            }
+            TransactionType::Resolve => DisputeOutcome::Resolved,
+            TransactionType::Chargeback => DisputeOutcome::ChargedBack,
+            TransactionType::Deposit | TransactionType::Withdrawal => return,
+        };
+        let start = if outcome == DisputeOutcome::ChargedBack {
+            self.dispute_starts.remove(&key)
+        } else {
+            self.dispute_starts.get(&key).copied()
+        };
+        if let Some(start) = start {
+            self.disputes
+                .entry(outcome)
+                .or_default()
+                .observe(start.elapsed());
+        }
+    }
+
+    /// Check the `total` funds of the account of client `cid` against the
+    /// alert `rules`, after a transaction changed them. An alert is raised
+    /// when the account goes beyond a bound, and not again until it comes
+    /// back within the bounds or goes beyond the other one.
+    pub(crate) fn check_balance_alerts(
+        &mut self,
+        rules: &[BalanceAlert],
+        cid: ClientId,
+        total: Currency,
+    ) {
+        for rule in rules {
+            let breaches = self.breaches.entry(rule.name.clone()).or_default();
+            let bound = match rule.breached_bound(total) {
+                Some(bound) => bound,
+                None => {
+                    breaches.remove(&cid);
+                    continue;
+                }
+            };
+            if breaches.insert(cid, bound) != Some(bound) {
+                *self.alerts.entry((rule.name.clone(), bound)).or_default() += 1;
+            }
+        }
+    }
+
+    /// The number of times an account breached the `bound` of the alert
+    /// rule named `rule`.
+    pub fn alerts_raised(&self, rule: &str, bound: AlertBound) -> u64 {
+        self.alerts
+            .get(&(rule.to_string(), bound))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The number of accounts that are beyond the `bound` of the alert rule
+    /// named `rule` right now.
+    pub fn accounts_in_breach(&self, rule: &str, bound: AlertBound) -> usize {
+        self.breaches.get(rule).map_or(0, |breaches| {
+            breaches.values().filter(|&&b| b == bound).count()
+        })
+    }
+
+    /// Add the metrics of `other`, which were collected by another
+    /// `Transactor` for other clients, to these.
+    pub(crate) fn absorb(&mut self, other: Metrics) {
+        for (ttype, metrics) in other.per_type {
+            let total = self.per_type.entry(ttype).or_default();
+            total.accepted += metrics.accepted;
+            total.rejected += metrics.rejected;
+            total.latency.absorb(&metrics.latency);
+        }
+        for (outcome, durations) in other.disputes {
+            self.disputes.entry(outcome).or_default().absorb(&durations);
+        }
+        self.dispute_starts.extend(other.dispute_starts);
+        for (key, count) in other.alerts {
+            *self.alerts.entry(key).or_default() += count;
+        }
+        for (rule, accounts) in other.breaches {
+            self.breaches.entry(rule).or_default().extend(accounts);
+        }
+        for quality in other.data_quality.values() {
+            self.record_data_quality(quality);
+        }
+    }
+
+    /// Add the data `quality` of an input to that of earlier inputs from
+    /// the same source.
+    pub(crate) fn record_data_quality(&mut self, quality: &DataQuality) {
+        let total = self
+            .data_quality
+            .entry(quality.source.clone())
+            .or_insert_with(|| DataQuality {
+                source: quality.source.clone(),
+                ..DataQuality::default()
+            });
+        total.rows += quality.rows;
+        total.malformed_rows += quality.malformed_rows;
+        total.unknown_types += quality.unknown_types;
+        total.duplicate_ids += quality.duplicate_ids;
+        total.out_of_order_rows += quality.out_of_order_rows;
+        total.rows_with_problems += quality.rows_with_problems;
+        total.update_score();
+    }
+
+    /// The metrics of the transactions of type `ttype`.
+    #[inline]
+    pub fn transaction_type(&self, ttype: TransactionType) -> Option<&TypeMetrics> {
+        self.per_type.get(&ttype)
+    }
+
+    /// The durations of the disputes that ended in `outcome`.
+    #[inline]
+    pub fn dispute_durations(&self, outcome: DisputeOutcome) -> Option<&Histogram> {
+        self.disputes.get(&outcome)
+    }
+
+    /// Render the metrics in the Prometheus text exposition format.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# TYPE giant_squid_transactions_total counter\n");
+        for (ttype, metrics) in &self.per_type {
+            for (outcome, count) in &[
+                ("accepted", metrics.accepted),
+                ("rejected", metrics.rejected),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "giant_squid_transactions_total{{type=\"{}\",outcome=\"{}\"}} {}",
+                    ttype.name(),
+                    outcome,
+                    count
+                );
+            }
+        }
+        let name = "giant_squid_transaction_duration_seconds";
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (ttype, metrics) in &self.per_type {
+            let labels = format!("type=\"{}\"", ttype.name());
+            metrics.latency.render(&mut out, name, &labels);
+        }
+        let name = "giant_squid_dispute_duration_seconds";
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (outcome, histogram) in &self.disputes {
+            let labels = format!("outcome=\"{}\"", outcome.name());
+            histogram.render(&mut out, name, &labels);
+        }
+        let _ = writeln!(out, "# TYPE giant_squid_input_rows_total counter");
+        for (source, quality) in &self.data_quality {
+            let source = escape_label_value(source);
+            let _ = writeln!(
+                out,
+                "giant_squid_input_rows_total{{source=\"{}\"}} {}",
+                source, quality.rows
+            );
+        }
+        let _ = writeln!(out, "# TYPE giant_squid_input_problems_total counter");
+        for (source, quality) in &self.data_quality {
+            let source = escape_label_value(source);
+            for (problem, count) in &[
+                ("malformed", quality.malformed_rows),
+                ("unknown_type", quality.unknown_types),
+                ("duplicate_id", quality.duplicate_ids),
+                ("out_of_order", quality.out_of_order_rows),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "giant_squid_input_problems_total{{source=\"{}\",problem=\"{}\"}} {}",
+                    source, problem, count
+                );
+            }
+        }
+        let _ = writeln!(out, "# TYPE giant_squid_input_quality_score gauge");
+        for (source, quality) in &self.data_quality {
+            let source = escape_label_value(source);
+            let _ = writeln!(
+                out,
+                "giant_squid_input_quality_score{{source=\"{}\"}} {}",
+                source, quality.score
+            );
+        }
+        let name = "giant_squid_balance_alerts_total";
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for ((rule, bound), count) in &self.alerts {
+            let labels = alert_labels(rule, *bound);
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, count);
+        }
+        // NOTE: Every rule and bound that ever fired gets a gauge, so that
+        //       it drops back to 0 once all accounts have recovered.
+        let name = "giant_squid_balance_alert_accounts";
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for (rule, bound) in self.alerts.keys() {
+            let labels = alert_labels(rule, *bound);
+            let accounts = self.accounts_in_breach(rule, *bound);
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, accounts);
+        }
+        out
+    }
+}
+
+/// Escape a label `value` as the Prometheus text exposition format
+/// requires.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The labels of the metrics of the `bound` of the alert rule named `rule`.
+fn alert_labels(rule: &str, bound: AlertBound) -> String {
+    let rule = escape_label_value(rule);
+    format!("rule=\"{}\",bound=\"{}\"", rule, bound.name())
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// The metrics collected so far.
+    #[inline(always)]
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+use crate::core::Currency;
+use crate::error::AppResult;
+
+#[test]
+fn histogram_buckets() {
+    let mut histogram = Histogram::default();
+    histogram.observe(Duration::from_nanos(500));
+    histogram.observe(Duration::from_micros(50));
+    histogram.observe(Duration::from_secs(60));
+    assert_eq!(histogram.count(), 3);
+    assert_eq!(histogram.buckets[0], 1);
+    assert_eq!(histogram.buckets[2], 1);
+    assert_eq!(histogram.buckets[BUCKET_BOUNDS.len()], 1);
+    assert_eq!(
+        histogram.sum(),
+        Duration::from_nanos(500) + Duration::from_micros(50) + Duration::from_secs(60)
+    );
+}
+
+#[tokio::test]
+async fn record_per_type_and_disputes() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let transactions = [
+        (TransactionType::Deposit, 1, Some("10.0")),
+        (TransactionType::Deposit, 2, Some("5.0")),
+        (TransactionType::Withdrawal, 3, Some("100.0")),
+        (TransactionType::Dispute, 1, None),
+        (TransactionType::Resolve, 1, None),
+        (TransactionType::Chargeback, 1, None),
+        (TransactionType::Resolve, 2, None),
+    ];
+    for (ttype, tid, amount) in transactions.iter() {
+        let _ = transactor
+            .process_transaction(Transaction {
+                ttype: *ttype,
+                cid: ClientId(1),
+                tid: TransactionId(*tid),
+                amount: amount.map(Currency::from_str).transpose()?,
+            })
+            .await;
+    }
+    let metrics = transactor.metrics();
+    let deposits = metrics.transaction_type(TransactionType::Deposit).unwrap();
+    assert_eq!((deposits.accepted, deposits.rejected), (2, 0));
+    assert_eq!(deposits.latency.count(), 2);
+    let withdrawals = metrics
+        .transaction_type(TransactionType::Withdrawal)
+        .unwrap();
+    assert_eq!((withdrawals.accepted, withdrawals.rejected), (0, 1));
+    let resolves = metrics.transaction_type(TransactionType::Resolve).unwrap();
+    assert_eq!((resolves.accepted, resolves.rejected), (1, 1));
+    let resolved = metrics.dispute_durations(DisputeOutcome::Resolved).unwrap();
+    assert_eq!(resolved.count(), 1);
+    let charged_back = metrics
+        .dispute_durations(DisputeOutcome::ChargedBack)
+        .unwrap();
+    assert_eq!(charged_back.count(), 1);
+    assert!(charged_back.sum() >= resolved.sum());
+    assert!(metrics.dispute_starts.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn render_prometheus() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    transactor
+        .process_transaction(Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0")?),
+        })
+        .await?;
+    let rendered = transactor.metrics().render_prometheus();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines.contains(&"# TYPE giant_squid_transactions_total counter"));
+    assert!(
+        lines.contains(&r#"giant_squid_transactions_total{type="deposit",outcome="accepted"} 1"#)
+    );
+    assert!(
+        lines.contains(&r#"giant_squid_transactions_total{type="deposit",outcome="rejected"} 0"#)
+    );
+    assert!(lines.contains(
+        &r#"giant_squid_transaction_duration_seconds_bucket{type="deposit",le="+Inf"} 1"#
+    ));
+    assert!(lines.contains(&r#"giant_squid_transaction_duration_seconds_count{type="deposit"} 1"#));
+    assert!(lines.contains(&"# TYPE giant_squid_dispute_duration_seconds histogram"));
+    assert!(!rendered.contains("outcome=\"resolved\""));
+    Ok(())
+}
+
+#[tokio::test]
+async fn balance_alerts() -> AppResult<()> {
+    let config = crate::config::Config {
+        balance_alerts: vec![BalanceAlert {
+            name: "range".to_string(),
+            floor: Some(Currency::from_str("1.0")?),
+            ceiling: Some(Currency::from_str("100.0")?),
+        }],
+        ..Default::default()
+    };
+    let mut transactor = Transactor::with_config(config);
+    let transactions = [
+        (TransactionType::Deposit, 1, 1, "0.5"),
+        (TransactionType::Deposit, 1, 2, "0.1"),
+        (TransactionType::Deposit, 1, 3, "200.0"),
+        (TransactionType::Withdrawal, 1, 4, "150.0"),
+        (TransactionType::Deposit, 2, 5, "500.0"),
+    ];
+    for (ttype, cid, tid, amount) in transactions.iter() {
+        transactor
+            .process_transaction(Transaction {
+                ttype: *ttype,
+                cid: ClientId(*cid),
+                tid: TransactionId(*tid),
+                amount: Some(Currency::from_str(amount)?),
+            })
+            .await?;
+    }
+    // NOTE: The second deposit keeps client 1 below the floor, which
+    //       doesn't raise another alert.
+    let metrics = transactor.metrics();
+    assert_eq!(metrics.alerts_raised("range", AlertBound::Floor), 1);
+    assert_eq!(metrics.alerts_raised("range", AlertBound::Ceiling), 2);
+    assert_eq!(metrics.accounts_in_breach("range", AlertBound::Floor), 0);
+    assert_eq!(metrics.accounts_in_breach("range", AlertBound::Ceiling), 1);
+    let rendered = metrics.render_prometheus();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines.contains(&r#"giant_squid_balance_alerts_total{rule="range",bound="floor"} 1"#));
+    assert!(lines.contains(&r#"giant_squid_balance_alert_accounts{rule="range",bound="floor"} 0"#));
+    assert!(
+        lines.contains(&r#"giant_squid_balance_alert_accounts{rule="range",bound="ceiling"} 1"#)
+    );
+    Ok(())
+}
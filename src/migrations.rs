@@ -0,0 +1,117 @@
+//! This module defines the migrations that upgrade persisted state written
+//! by older versions of the crate, so that deployments can be upgraded
+//! without replaying their history.
+//!
+//! Every snapshot records the `SNAPSHOT_VERSION` of its layout in its
+//! `version` field. Snapshots without one predate versioning, and have
+//! version 1. Restoring a snapshot applies the `MIGRATIONS` from its
+//! version onwards, one step at a time, to its `JSON` representation.
+//!
+//! To change the snapshot layout, bump `SNAPSHOT_VERSION`, and add a step
+//! that upgrades snapshots of the previous version, along with a test.
+
+#[cfg(test)]
+mod tests;
+
+use crate::error::{AppError, AppResult};
+use serde_json::{Map, Value};
+
+/// The version of the snapshot layout written by this version of the crate.
+pub const SNAPSHOT_VERSION: u64 = 8;
+
+/// The name of the field that holds the version of a snapshot.
+pub(crate) const VERSION_FIELD: &str = "version";
+
+/// A single step that upgrades a snapshot from version `from` to `from + 1`.
+pub struct Migration {
+    pub from: u64,
+    /// What the step changes, for the release notes
+    pub description: &'static str,
+    pub migrate: fn(&mut Map<String, Value>) -> AppResult<()>,
+}
+
+/// All migration steps, ordered by the version they upgrade from.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        description: "Record the snapshot version in the snapshot",
+        migrate: |_snapshot| Ok(()),
+    },
+    Migration {
+        from: 2,
+        description: "Persist the dispute timeline of every disputed transaction",
+        // NOTE: The timelines of the disputes in older snapshots weren't
+        //       recorded, so they're restored without one.
+        migrate: |_snapshot| Ok(()),
+    },
+    Migration {
+        from: 3,
+        description: "Persist how much of the deposits was swept against amounts owed",
+        // NOTE: Accounts without the amount are restored as having repaid
+        //       nothing, which is all that can be known about them.
+        migrate: |_snapshot| Ok(()),
+    },
+    Migration {
+        from: 4,
+        description: "Persist the closed accounting periods and their sealed transactions",
+        // NOTE: No period could be closed before, so older snapshots are
+        //       restored without sealed transactions or adjustments.
+        migrate: |_snapshot| Ok(()),
+    },
+    Migration {
+        from: 5,
+        description: "Persist the statement of every client",
+        // NOTE: Older snapshots didn't keep statements, so the statements
+        //       restored from them start with the rows after the restore.
+        migrate: |_snapshot| Ok(()),
+    },
+    Migration {
+        from: 6,
+        description: "Persist the transactions parked for the transaction they refer to",
+        // NOTE: Older snapshots had no parked transactions to persist.
+        migrate: |_snapshot| Ok(()),
+    },
+    Migration {
+        from: 7,
+        description: "Persist the highest transaction id evicted from every history",
+        // NOTE: Which transactions older snapshots evicted wasn't recorded,
+        //       so disputes of them are rejected as if they never existed.
+        migrate: |_snapshot| Ok(()),
+    },
+];
+
+/// The version of the layout of `snapshot`.
+pub fn snapshot_version(snapshot: &Value) -> AppResult<u64> {
+    match snapshot.get(VERSION_FIELD) {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| AppError::UnsupportedSnapshotVersion {
+                version: version.to_string(),
+            }),
+    }
+}
+
+/// Upgrade `snapshot` to the current `SNAPSHOT_VERSION`.
+pub fn migrate_snapshot(snapshot: &mut Value) -> AppResult<()> {
+    let mut version = snapshot_version(snapshot)?;
+    let unsupported = |version: u64| AppError::UnsupportedSnapshotVersion {
+        version: version.to_string(),
+    };
+    if version > SNAPSHOT_VERSION {
+        return Err(unsupported(version));
+    }
+    let fields = snapshot
+        .as_object_mut()
+        .ok_or_else(|| unsupported(version))?;
+    while version < SNAPSHOT_VERSION {
+        let step = MIGRATIONS
+            .iter()
+            .find(|step| step.from == version)
+            .ok_or_else(|| unsupported(version))?;
+        (step.migrate)(fields)?;
+        version += 1;
+        let _ = fields.insert(VERSION_FIELD.to_string(), Value::from(version));
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use super::*;
+use crate::core::{
+    ClientId, Currency, Transaction, TransactionId, TransactionState, TransactionType, Transactor,
+};
+use crate::error::TransactionError;
+use serde_json::json;
+
+/// A `Transactor` restored from the hand-written `snapshot`.
+fn restore(mut snapshot: Value) -> AppResult<Transactor> {
+    migrate_snapshot(&mut snapshot)?;
+    Ok(serde_json::from_value(snapshot)?)
+}
+
+#[test]
+fn migrations_cover_every_version() {
+    for (idx, step) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(step.from, idx as u64 + 1, "{}", step.description);
+    }
+    assert_eq!(MIGRATIONS.len() as u64, SNAPSHOT_VERSION - 1);
+}
+
+#[test]
+fn migrate_unversioned_snapshot() -> AppResult<()> {
+    let mut snapshot = json!({ "accounts": {}, "max_tid": 3 });
+    assert_eq!(snapshot_version(&snapshot)?, 1);
+    migrate_snapshot(&mut snapshot)?;
+    assert_eq!(
+        snapshot,
+        json!({ "accounts": {}, "max_tid": 3, "version": SNAPSHOT_VERSION })
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_unsupported_versions() {
+    for version in [json!(SNAPSHOT_VERSION + 1), json!("2")] {
+        let mut snapshot = json!({ "accounts": {}, "version": version });
+        assert!(matches!(
+            migrate_snapshot(&mut snapshot),
+            Err(AppError::UnsupportedSnapshotVersion { .. })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn restore_disputes_without_timelines() -> AppResult<()> {
+    let deposit = json!({ "type": "deposit", "client": 1, "tx": 1, "amount": "2.0" });
+    let mut transactor = restore(json!({
+        "accounts": {
+            "1": {
+                "id": 1, "available": "0", "held": "2.0", "total": "2.0",
+                "disputed_transactions": { "1": deposit }
+            }
+        },
+        "version": 2
+    }))?;
+    let (cid, tid) = (ClientId(1), TransactionId(1));
+    assert!(transactor.dispute_timeline(cid, tid).is_empty());
+    let resolve = Transaction {
+        ttype: TransactionType::Resolve,
+        cid,
+        tid,
+        amount: None,
+    };
+    transactor.process_row(resolve).await?;
+    let timeline = transactor.dispute_timeline(cid, tid);
+    assert_eq!(timeline.len(), 1);
+    assert_eq!(timeline[0].to, TransactionState::Resolved);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_amounts_owed_without_repayments() -> AppResult<()> {
+    let transactor = restore(json!({
+        "accounts": {
+            "1": { "id": 1, "available": "1.0", "held": "-2.0", "total": "-1.0" }
+        },
+        "version": 3
+    }))?;
+    let owed = transactor.amounts_owed();
+    assert_eq!(owed.len(), 1);
+    assert_eq!(owed[0].owed, Currency::from_str("2.0")?);
+    assert_eq!(owed[0].repaid, Currency::ZERO);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_snapshots_without_closed_periods() -> AppResult<()> {
+    let deposit = json!({ "type": "deposit", "client": 1, "tx": 1, "amount": "2.0" });
+    let mut transactor = restore(json!({
+        "accounts": {
+            "1": {
+                "id": 1, "available": "2.0", "held": "0", "total": "2.0",
+                "processed_transactions": { "1": deposit }
+            }
+        },
+        "version": 4
+    }))?;
+    assert_eq!(transactor.periods_closed(), 0);
+    let period = transactor.close_period(None);
+    assert_eq!(period.number, 1);
+    assert_eq!(period.entries.len(), 1);
+    assert_eq!(period.adjustments, vec![]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_snapshots_without_statements() -> AppResult<()> {
+    let mut transactor = restore(json!({
+        "accounts": {
+            "1": { "id": 1, "available": "2.0", "held": "0", "total": "2.0" }
+        },
+        "version": 5
+    }))?;
+    transactor.config.keep_statements = true;
+    let cid = ClientId(1);
+    assert!(transactor.statement(cid).is_empty());
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid,
+        tid: TransactionId(2),
+        amount: Some(Currency::from_str("1.0")?),
+    };
+    transactor.process_row(deposit).await?;
+    let statement = transactor.statement(cid);
+    assert_eq!(statement.len(), 1);
+    assert_eq!(statement[0].total, Currency::from_str("3.0")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_histories_without_evictions() -> AppResult<()> {
+    let deposit = json!({ "type": "deposit", "client": 1, "tx": 2, "amount": "2.0" });
+    let mut transactor = restore(json!({
+        "accounts": {
+            "1": {
+                "id": 1, "available": "2.0", "held": "0", "total": "2.0",
+                "processed_transactions": { "2": deposit }
+            }
+        },
+        "max_tid": 2,
+        "version": 7
+    }))?;
+    let (cid, tid) = (ClientId(1), TransactionId(1));
+    assert_eq!(transactor.accounts.get(&cid).unwrap().evicted_through, None);
+    assert_eq!(
+        transactor.process_row(Transaction::dispute(cid, tid)).await,
+        Err(TransactionError::NoSuchProcessedTransactionForClient { tid, cid })
+    );
+    Ok(())
+}
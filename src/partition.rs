@@ -0,0 +1,150 @@
+//! This module defines how the clients of an input are partitioned over the
+//! shards of sharded processing.
+//!
+//! A `ShardAssignment` decides which shard owns each client. There are
+//! three of them:
+//! * `HashAssignment`, the default, spreads the clients evenly by taking
+//!   their id modulo the number of shards.
+//! * `RangeAssignment` gives each shard a contiguous range of client ids.
+//! * `TableAssignment` looks the shard of each client up in a table, e.g.
+//!   to co-locate related clients on the same shard. Tables can be read
+//!   from and written to `CSV` files, and generated from other assignments
+//!   by rebalancing tools.
+//!
+//! Clients are assigned by the id they are remapped to by
+//! `Config::client_ids`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Decides which shard owns each client.
+pub trait ShardAssignment: fmt::Debug + Send + Sync {
+    /// The index of the shard that owns the client with id `cid`, out of
+    /// `shards` shards. Indices of `shards` and up wrap around.
+    fn shard_of(&self, cid: ClientId, shards: usize) -> usize;
+}
+
+/// Assigns each client to the shard with the index of its id modulo the
+/// number of shards.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HashAssignment;
+
+impl ShardAssignment for HashAssignment {
+    #[inline(always)]
+    fn shard_of(&self, cid: ClientId, shards: usize) -> usize {
+        usize::from(cid.0) % shards
+    }
+}
+
+/// Assigns contiguous ranges of client ids to the shards, in order. Shard
+/// `i` owns the clients with ids up to and including `upper_bounds[i]`
+/// that aren't owned by a shard before it. The last shard also owns all
+/// clients with higher ids.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RangeAssignment {
+    pub upper_bounds: Vec<ClientId>,
+}
+
+impl ShardAssignment for RangeAssignment {
+    fn shard_of(&self, cid: ClientId, shards: usize) -> usize {
+        let shard = self.upper_bounds.partition_point(|&bound| bound < cid);
+        shard.min(shards - 1)
+    }
+}
+
+/// Assigns clients to shards as a table says. Clients that aren't in the
+/// table are assigned by the `HashAssignment`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableAssignment {
+    table: BTreeMap<ClientId, usize>,
+}
+
+impl TableAssignment {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign the client with id `cid` to the shard with index `shard`.
+    #[inline(always)]
+    pub fn insert(&mut self, cid: ClientId, shard: usize) {
+        self.table.insert(cid, shard);
+    }
+
+    /// The table of the clients with ids `cids`, as `assignment` assigns
+    /// them to `shards` shards.
+    pub fn generate(
+        cids: impl IntoIterator<Item = ClientId>,
+        assignment: &dyn ShardAssignment,
+        shards: usize,
+    ) -> Self {
+        let table = cids
+            .into_iter()
+            .map(|cid| (cid, assignment.shard_of(cid, shards) % shards))
+            .collect();
+        Self { table }
+    }
+
+    /// The assigned clients and their shards, by client id.
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = (ClientId, usize)> + '_ {
+        self.table.iter().map(|(&cid, &shard)| (cid, shard))
+    }
+
+    /// Read a table from the file at `filepath`. The file contains a
+    /// `client,shard` pair per line, optionally preceded by a
+    /// `client,shard` header. Empty lines and `#`-prefixed comments are
+    /// skipped.
+    pub async fn read_from(filepath: PathBuf) -> AppResult<Self> {
+        let contents = tokio::fs::read_to_string(filepath).await?;
+        let mut assignment = Self::new();
+        for (idx, line) in contents.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with('#') || (idx == 0 && line == "client,shard") {
+                continue;
+            }
+            let (cid, shard) =
+                line.split_once(',')
+                    .ok_or_else(|| AppError::MalformedShardAssignment {
+                        line: line.to_string(),
+                    })?;
+            let cid = ClientId(cid.trim().parse()?);
+            assignment.insert(cid, shard.trim().parse()?);
+        }
+        Ok(assignment)
+    }
+
+    /// Write the table to the file at `filepath`, in the format that
+    /// `read_from()` reads, replacing the file if it exists.
+    pub async fn write_to(&self, filepath: PathBuf) -> AppResult<()> {
+        let mut contents = String::from("client,shard\n");
+        for (cid, shard) in self.iter() {
+            contents.push_str(&format!("{},{}\n", cid.0, shard));
+        }
+        tokio::fs::write(filepath, contents).await?;
+        Ok(())
+    }
+}
+
+impl ShardAssignment for TableAssignment {
+    fn shard_of(&self, cid: ClientId, shards: usize) -> usize {
+        match self.table.get(&cid) {
+            Some(shard) => shard % shards,
+            None => HashAssignment.shard_of(cid, shards),
+        }
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Assign the clients to shards with `assignment` in sharded processing
+    /// from now on, rather than with the `HashAssignment`.
+    pub fn set_shard_assignment(&mut self, assignment: impl ShardAssignment + 'static) {
+        self.shard_assignment = Some(Box::new(assignment));
+    }
+}
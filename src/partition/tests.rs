@@ -0,0 +1,72 @@
+use super::*;
+use crate::test_util::corpus::Case;
+use crate::test_util::temp::TempPath;
+
+#[test]
+fn assign_ranges_of_client_ids() {
+    let assignment = RangeAssignment {
+        upper_bounds: vec![ClientId(10), ClientId(20)],
+    };
+    let shards: Vec<_> = [1, 10, 11, 20, 21, 500]
+        .iter()
+        .map(|&cid| assignment.shard_of(ClientId(cid), 3))
+        .collect();
+    assert_eq!(shards, [0, 0, 1, 1, 2, 2]);
+    // NOTE: With fewer shards, the last one owns the rest.
+    assert_eq!(assignment.shard_of(ClientId(21), 2), 1);
+}
+
+#[test]
+fn look_up_clients_in_a_table() {
+    let mut assignment = TableAssignment::new();
+    assignment.insert(ClientId(1), 2);
+    assignment.insert(ClientId(2), 2);
+    assignment.insert(ClientId(3), 7);
+    assert_eq!(assignment.shard_of(ClientId(1), 4), 2);
+    assert_eq!(assignment.shard_of(ClientId(2), 4), 2);
+    assert_eq!(assignment.shard_of(ClientId(3), 4), 3);
+    assert_eq!(assignment.shard_of(ClientId(5), 4), 1);
+}
+
+#[tokio::test]
+async fn write_and_read_a_generated_table() -> AppResult<()> {
+    let path = TempPath::new("shards.csv");
+    let cids = (1..=5).map(ClientId);
+    let generated = TableAssignment::generate(cids, &HashAssignment, 2);
+    generated.write_to(path.to_path_buf()).await?;
+    let read = TableAssignment::read_from(path.to_path_buf()).await?;
+    assert_eq!(read, generated);
+    assert_eq!(
+        read.iter().collect::<Vec<_>>(),
+        [
+            (ClientId(1), 1),
+            (ClientId(2), 0),
+            (ClientId(3), 1),
+            (ClientId(4), 0),
+            (ClientId(5), 1)
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn the_assignment_doesnt_change_the_output() -> AppResult<()> {
+    for case in Case::generate_all(7) {
+        let mut sequential = Transactor::new();
+        sequential.process_csv_reader(case.csv.as_bytes()).await?;
+        let mut sharded = Transactor::new();
+        sharded.set_shard_assignment(RangeAssignment {
+            upper_bounds: vec![ClientId(1), ClientId(2)],
+        });
+        sharded
+            .process_csv_reader_sharded(case.csv.as_bytes(), 3)
+            .await?;
+        assert_eq!(
+            sharded.account_summaries().collect::<Vec<_>>(),
+            sequential.account_summaries().collect::<Vec<_>>(),
+            "case {}",
+            case.name()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+//! This module defines the closing of accounting periods.
+//!
+//! Closing a period seals every transaction processed so far, or only
+//! those up to a cutoff transaction id, except those with an open dispute,
+//! which are carried over into the next period, as are the transactions
+//! after the cutoff, e.g. because they belong to the next day already. The
+//! sealed transactions are returned as a `ClosedPeriod`, to be archived by
+//! the caller, e.g. with `ClosedPeriod::write_csv()`.
+//!
+//! Sealed transactions can't be voided. What happens to a dispute, resolve
+//! or chargeback of a sealed transaction is determined by the
+//! `SealedTransactionPolicy`: it's either rejected, or applied as an
+//! `Adjustment` that is reported along with the next period to be closed.
+
+#[cfg(test)]
+mod tests;
+
+use crate::config::SealedTransactionPolicy;
+use crate::core::{
+    HistoryEntry, Transaction, TransactionId, TransactionState, TransactionType, Transactor,
+};
+use crate::error::{AppResult, TransactionError, TransactionResult};
+use crate::store::AccountStore;
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A dispute, resolve or chargeback that changed a transaction of a closed
+/// period, applied in the period that was open at the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Adjustment {
+    /// The number of rows processed when the adjustment was applied.
+    pub row: u64,
+    pub transaction: Transaction,
+}
+
+/// The transactions sealed by closing an accounting period.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClosedPeriod {
+    /// The number of the period, starting at 1.
+    pub number: u32,
+    /// The number of rows processed when the period was closed.
+    pub closed_at_row: u64,
+    /// The transactions sealed by closing the period, in the state they
+    /// were in at the time, ordered by `ClientId` and `TransactionId`.
+    pub entries: Vec<HistoryEntry>,
+    /// The adjustments to earlier periods that were applied in this one.
+    pub adjustments: Vec<Adjustment>,
+}
+
+impl ClosedPeriod {
+    /// Write the archive of the period to the file at `filepath` as `CSV`,
+    /// in `client,tx,type,amount,state` form. Adjustments follow the sealed
+    /// transactions, with `adjustment` as their state.
+    pub async fn write_csv(&self, filepath: PathBuf) -> AppResult<()> {
+        let mut csv = String::from("client,tx,type,amount,state\n");
+        let rows = self
+            .entries
+            .iter()
+            .map(|entry| (&entry.transaction, state_name(entry.state)))
+            .chain(
+                self.adjustments
+                    .iter()
+                    .map(|adjustment| (&adjustment.transaction, "adjustment")),
+            );
+        for (t, state) in rows {
+            let amount = t
+                .amount()
+                .map(|amount| amount.to_output_string())
+                .unwrap_or_default();
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                t.cid(),
+                t.tid(),
+                t.ttype().name(),
+                amount,
+                state
+            ));
+        }
+        tokio::fs::write(filepath, csv).await?;
+        Ok(())
+    }
+}
+
+#[rustfmt::skip]
+pub(crate) fn state_name(state: TransactionState) -> &'static str {
+    match state {
+        TransactionState::Processed   => "processed",
+        TransactionState::Disputed    => "disputed",
+        TransactionState::Resolved    => "resolved",
+        TransactionState::ChargedBack => "charged_back",
+        TransactionState::Voided      => "voided",
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Close the current accounting period, sealing all transactions that
+    /// were processed so far and aren't disputed at the moment. If `through`
+    /// is given, only the transactions with an id at or below it are sealed.
+    pub fn close_period(&mut self, through: Option<TransactionId>) -> ClosedPeriod {
+        let mut entries = vec![];
+        let mut sealed = vec![];
+        for account in self.accounts.values() {
+            let mut tids = vec![];
+            for (state, history) in account.histories().iter() {
+                if *state == TransactionState::Disputed {
+                    continue; // NOTE: Open disputes are carried over
+                }
+                let history = match through {
+                    Some(through) => history.range(..=through),
+                    None => history.range(..),
+                };
+                for (tid, t) in history {
+                    if !account.sealed_transactions.contains(tid) {
+                        entries.push(account.history_entry(t, *state));
+                        tids.push(*tid);
+                    }
+                }
+            }
+            if !tids.is_empty() {
+                sealed.push((account.id, tids));
+            }
+        }
+        for (cid, tids) in sealed {
+            if let Some(account) = self.accounts.get_mut(&cid) {
+                account.sealed_transactions.extend(tids);
+            }
+        }
+        entries.sort_by_key(|entry| (entry.transaction.cid(), entry.transaction.tid()));
+        self.periods_closed += 1;
+        ClosedPeriod {
+            number: self.periods_closed,
+            closed_at_row: self.rows_processed,
+            entries,
+            adjustments: std::mem::take(&mut self.adjustments),
+        }
+    }
+
+    /// The number of accounting periods closed so far.
+    #[inline(always)]
+    pub fn periods_closed(&self) -> u32 {
+        self.periods_closed
+    }
+
+    /// Ensure that `t` doesn't change a sealed transaction, unless the
+    /// `SealedTransactionPolicy` allows that. Returns `true` if `t` is to
+    /// be recorded as an `Adjustment` once it's applied.
+    pub(crate) fn ensure_not_sealed(&self, t: &Transaction) -> TransactionResult<bool> {
+        if matches!(
+            t.ttype(),
+            TransactionType::Deposit | TransactionType::Withdrawal
+        ) {
+            return Ok(false);
+        }
+        let (cid, tid) = (t.cid(), t.tid());
+        let is_sealed = self
+            .accounts
+            .get(&cid)
+            .is_some_and(|account| account.sealed_transactions.contains(&tid));
+        match (is_sealed, self.config.sealed_transactions) {
+            (false, _) => Ok(false),
+            (true, SealedTransactionPolicy::Reject) => {
+                Err(TransactionError::TransactionSealed { tid, cid })
+            }
+            (true, SealedTransactionPolicy::Adjust) => Ok(true),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use super::*;
+use crate::config::Config;
+use crate::core::{ClientId, TransactionId};
+use crate::test_util::temp::TempPath;
+use crate::test_util::TestHarness;
+
+#[tokio::test]
+async fn close_period_seals_transactions() -> AppResult<()> {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(1, 1, "5.0")
+        .await
+        .deposit(1, 2, "3.0")
+        .await
+        .withdraw(2, 3, "1.0")
+        .await
+        .deposit(2, 4, "2.0")
+        .await
+        .dispute(1, 2)
+        .await;
+    let period = harness.transactor_mut().close_period(None);
+    assert_eq!(period.number, 1);
+    assert_eq!(period.closed_at_row, 5);
+    // NOTE: The open dispute of transaction 2 is carried over.
+    assert_eq!(
+        period
+            .entries
+            .iter()
+            .map(|entry| (entry.transaction.tid(), entry.state))
+            .collect::<Vec<_>>(),
+        vec![
+            (TransactionId(1), TransactionState::Processed),
+            (TransactionId(4), TransactionState::Processed),
+        ]
+    );
+    harness
+        .dispute(1, 1)
+        .await
+        .expect_rejected(TransactionError::TransactionSealed {
+            tid: TransactionId(1),
+            cid: ClientId(1),
+        })
+        .expect_held(1, "3.0")
+        .resolve(1, 2)
+        .await
+        .expect_accepted()
+        .deposit(1, 5, "1.0")
+        .await;
+    let transactor = harness.transactor_mut();
+    assert_eq!(
+        transactor
+            .void_transaction(ClientId(2), TransactionId(4))
+            .await,
+        Err(TransactionError::TransactionSealed {
+            tid: TransactionId(4),
+            cid: ClientId(2),
+        })
+    );
+    let period = transactor.close_period(None);
+    assert_eq!(period.number, 2);
+    assert_eq!(
+        period
+            .entries
+            .iter()
+            .map(|entry| (entry.transaction.tid(), entry.state))
+            .collect::<Vec<_>>(),
+        vec![
+            (TransactionId(2), TransactionState::Resolved),
+            (TransactionId(5), TransactionState::Processed),
+        ]
+    );
+    assert_eq!(period.adjustments, vec![]);
+    assert_eq!(transactor.periods_closed(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_period_through_a_transaction() -> AppResult<()> {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(1, 1, "5.0")
+        .await
+        .deposit(2, 2, "3.0")
+        .await
+        .deposit(1, 3, "1.0")
+        .await
+        .withdraw(2, 4, "1.0")
+        .await;
+    let period = harness
+        .transactor_mut()
+        .close_period(Some(TransactionId(2)));
+    assert_eq!(
+        period
+            .entries
+            .iter()
+            .map(|entry| entry.transaction.tid())
+            .collect::<Vec<_>>(),
+        vec![TransactionId(1), TransactionId(2)]
+    );
+    // NOTE: The transactions after the cutoff are carried over.
+    harness
+        .dispute(1, 3)
+        .await
+        .expect_accepted()
+        .resolve(1, 3)
+        .await
+        .expect_accepted()
+        .dispute(2, 2)
+        .await
+        .expect_rejected(TransactionError::TransactionSealed {
+            tid: TransactionId(2),
+            cid: ClientId(2),
+        });
+    let period = harness.transactor_mut().close_period(None);
+    assert_eq!(
+        period
+            .entries
+            .iter()
+            .map(|entry| (entry.transaction.tid(), entry.state))
+            .collect::<Vec<_>>(),
+        vec![
+            (TransactionId(3), TransactionState::Resolved),
+            (TransactionId(4), TransactionState::Processed),
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn adjust_sealed_transactions() -> AppResult<()> {
+    let mut harness = TestHarness::with_config(Config {
+        sealed_transactions: SealedTransactionPolicy::Adjust,
+        ..Config::default()
+    });
+    harness.deposit(1, 1, "5.0").await;
+    let first = harness.transactor_mut().close_period(None);
+    harness
+        .dispute(1, 1)
+        .await
+        .expect_accepted()
+        .expect_held(1, "5.0");
+    let second = harness.transactor_mut().close_period(None);
+    assert_eq!(second.entries, vec![]);
+    assert_eq!(
+        second.adjustments,
+        vec![Adjustment {
+            row: 2,
+            transaction: Transaction::dispute(ClientId(1), TransactionId(1)),
+        }]
+    );
+    // NOTE: The archive of the first period is left as it was.
+    assert_eq!(first.entries[0].state, TransactionState::Processed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_period_archive() -> AppResult<()> {
+    let path = TempPath::new("period.csv");
+    let mut harness = TestHarness::with_config(Config {
+        sealed_transactions: SealedTransactionPolicy::Adjust,
+        ..Config::default()
+    });
+    harness
+        .deposit(1, 1, "5.0")
+        .await
+        .withdraw(1, 2, "1.5")
+        .await;
+    let _ = harness.transactor_mut().close_period(None);
+    harness.dispute(1, 2).await.deposit(2, 3, "1.0").await;
+    let period = harness.transactor_mut().close_period(None);
+    period.write_csv(path.to_path_buf()).await?;
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "client,tx,type,amount,state\n\
+         2,3,deposit,1.0000,processed\n\
+         1,2,dispute,,adjustment\n"
+    );
+    Ok(())
+}
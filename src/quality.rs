@@ -0,0 +1,130 @@
+//! This module defines the data quality of the inputs of a `Transactor`,
+//! measured while they are ingested, e.g. to hold partners to an SLA.
+//!
+//! Every input, i.e. every `CSV` file, reader or stream, gets a
+//! `DataQuality` with the number of its rows that have each of these
+//! problems:
+//! * malformed: the row can't be read as a transaction at all.
+//! * unknown type: the type of the row is neither a transaction type nor
+//!   one of the `Config::type_aliases`.
+//! * duplicate id: the row is a deposit or withdrawal with the same
+//!   `TransactionId` as an earlier one of the same client in the input.
+//! * out of order: the row is a deposit or withdrawal with a lower
+//!   `TransactionId` than an earlier one in the input.
+//!
+//! Malformed rows and rows of unknown types end the input, unless
+//! `Config::skip_malformed_rows` is set.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Transaction, TransactionId, TransactionType, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use serde_derive::Serialize;
+use std::collections::BTreeSet;
+
+/// The data quality of a single input.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DataQuality {
+    /// The name of the input, e.g. the path of a `CSV` file.
+    pub source: String,
+    /// The number of rows read, including those with problems.
+    pub rows: u64,
+    pub malformed_rows: u64,
+    pub unknown_types: u64,
+    pub duplicate_ids: u64,
+    pub out_of_order_rows: u64,
+    /// The number of rows with at least one problem.
+    pub rows_with_problems: u64,
+    /// The fraction of rows without problems, from 0.0 to 1.0. An input
+    /// without rows scores 1.0.
+    pub score: f64,
+}
+
+impl DataQuality {
+    #[inline]
+    pub(crate) fn update_score(&mut self) {
+        self.score = match self.rows {
+            0 => 1.0,
+            rows => 1.0 - self.rows_with_problems as f64 / rows as f64,
+        };
+    }
+}
+
+/// Measures the `DataQuality` of an input row by row.
+#[derive(Debug)]
+pub(crate) struct QualityTracker {
+    quality: DataQuality,
+    /// The deposits and withdrawals seen so far.
+    seen: BTreeSet<(ClientId, TransactionId)>,
+    max_tid: Option<TransactionId>,
+}
+
+impl QualityTracker {
+    pub(crate) fn new(source: impl Into<String>) -> Self {
+        Self {
+            quality: DataQuality {
+                source: source.into(),
+                score: 1.0,
+                ..DataQuality::default()
+            },
+            seen: BTreeSet::new(),
+            max_tid: None,
+        }
+    }
+
+    /// Account for a row of the input, which was read as `row`.
+    pub(crate) fn observe<T>(&mut self, row: &AppResult<(T, Transaction)>) {
+        let quality = &mut self.quality;
+        quality.rows += 1;
+        let has_problem = match row {
+            Err(AppError::UnknownTransactionType { .. }) => {
+                quality.unknown_types += 1;
+                true
+            }
+            Err(e) if e.is_row_error() => {
+                quality.malformed_rows += 1;
+                true
+            }
+            Err(_) => {
+                // NOTE: E.g. an I/O error, which isn't a row at all.
+                quality.rows -= 1;
+                false
+            }
+            Ok((_, t)) => match t.ttype() {
+                TransactionType::Deposit | TransactionType::Withdrawal => {
+                    let duplicate = !self.seen.insert((t.cid(), t.tid()));
+                    let out_of_order = self.max_tid.is_some_and(|max| t.tid() < max);
+                    self.max_tid = self.max_tid.max(Some(t.tid()));
+                    quality.duplicate_ids += duplicate as u64;
+                    quality.out_of_order_rows += out_of_order as u64;
+                    duplicate || out_of_order
+                }
+                _ => false,
+            },
+        };
+        quality.rows_with_problems += has_problem as u64;
+    }
+
+    pub(crate) fn finish(mut self) -> DataQuality {
+        self.quality.update_score();
+        self.quality
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// The data quality of every input processed so far, in the order in
+    /// which they were processed.
+    #[inline(always)]
+    pub fn data_quality(&self) -> &[DataQuality] {
+        &self.data_quality
+    }
+
+    /// Keep the data `quality` of an input that was processed, and report
+    /// it in the metrics.
+    pub(crate) fn record_data_quality(&mut self, quality: DataQuality) {
+        self.metrics.record_data_quality(&quality);
+        self.data_quality.push(quality);
+    }
+}
@@ -0,0 +1,86 @@
+use super::*;
+use crate::config::Config;
+use crate::core::{ClientId, Currency};
+
+#[tokio::test]
+async fn clean_input_scores_one() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               deposit,2,2,1.0\n\
+               dispute,1,1,\n";
+    let mut transactor = Transactor::new();
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    let quality = &transactor.data_quality()[0];
+    assert_eq!(quality.source, "reader");
+    assert_eq!(quality.rows, 3);
+    assert_eq!(quality.rows_with_problems, 0);
+    assert_eq!(quality.score, 1.0);
+    assert_eq!(transactor.stats()?.data_quality, transactor.data_quality());
+    Ok(())
+}
+
+#[tokio::test]
+async fn duplicate_and_out_of_order_rows_lower_the_score() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,2,1.0\n\
+               deposit,1,1,1.0\n\
+               deposit,1,2,1.0\n\
+               deposit,2,2,1.0\n\
+               dispute,1,1,\n";
+    let mut transactor = Transactor::new();
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    let quality = &transactor.data_quality()[0];
+    assert_eq!(quality.rows, 5);
+    assert_eq!(quality.duplicate_ids, 1);
+    assert_eq!(quality.out_of_order_rows, 1);
+    assert_eq!(quality.rows_with_problems, 2);
+    assert_eq!(quality.score, 0.6);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_types_end_the_input() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               transfer,1,2,1.0\n\
+               deposit,1,3,1.0\n";
+    let mut transactor = Transactor::new();
+    let result = transactor.process_csv_reader(csv.as_bytes()).await;
+    assert!(matches!(
+        result,
+        Err(AppError::UnknownTransactionType { line: 3, ref name }) if name == "transfer"
+    ));
+    // NOTE: The quality of an input that ended early is kept all the same.
+    let quality = &transactor.data_quality()[0];
+    assert_eq!(quality.rows, 2);
+    assert_eq!(quality.unknown_types, 1);
+    assert_eq!(quality.score, 0.5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn malformed_rows_can_be_skipped() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               transfer,1,2,1.0\n\
+               deposit,1,3,abc\n\
+               deposit,1,4,2.0\n";
+    let mut transactor = Transactor::with_config(Config {
+        skip_malformed_rows: true,
+        ..Config::default()
+    });
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), Currency::from_str("3.0")?);
+    let quality = &transactor.data_quality()[0];
+    assert_eq!(quality.rows, 4);
+    assert_eq!(quality.unknown_types, 1);
+    assert_eq!(quality.malformed_rows, 1);
+    assert_eq!(quality.score, 0.5);
+    let metrics = transactor.metrics().render_prometheus();
+    assert!(metrics.contains("giant_squid_input_rows_total{source=\"reader\"} 4\n"));
+    assert!(metrics
+        .contains("giant_squid_input_problems_total{source=\"reader\",problem=\"malformed\"} 1\n"));
+    assert!(metrics.contains("giant_squid_input_quality_score{source=\"reader\"} 0.5\n"));
+    Ok(())
+}
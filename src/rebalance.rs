@@ -0,0 +1,148 @@
+//! This module defines the rebalancing of the snapshots of sharded
+//! deployments, to scale them up or down without reprocessing their input.
+//!
+//! Rebalancing restores the snapshots of N shards, merges them, and splits
+//! the accounts, along with their histories, parked transactions,
+//! cooling-off periods, statements and adjustments, into M new shards as a
+//! `ShardAssignment` says. Each new shard is written as a snapshot of its
+//! own.
+//!
+//! The integrity of the result is verified twice: the `RebalanceTotals` of
+//! the new shards must match those of the old ones, and the snapshots are
+//! read back after writing them, and must match the shards they were
+//! written from. A client with an account in more than one of the old
+//! shards fails the rebalance, as its state would be ambiguous.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{Currency, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::partition::ShardAssignment;
+use crate::snapshot::SnapshotFormat;
+use serde_derive::Serialize;
+use std::path::PathBuf;
+
+/// The totals over the accounts of one or more shards that a rebalance
+/// preserves.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RebalanceTotals {
+    pub accounts: usize,
+    /// The number of transactions in the histories of the accounts.
+    pub transactions: usize,
+    pub parked_transactions: usize,
+    pub available: Currency,
+    pub held: Currency,
+    pub total: Currency,
+}
+
+impl RebalanceTotals {
+    /// The totals of `transactor`. Fails if the funds of its accounts
+    /// can't be summed.
+    pub fn of(transactor: &Transactor) -> AppResult<Self> {
+        let mut totals = Self {
+            accounts: transactor.accounts.len(),
+            parked_transactions: transactor.parked_transactions.len(),
+            ..Self::default()
+        };
+        for account in transactor.accounts.values() {
+            totals.add(&Self {
+                transactions: account.history_len(),
+                available: account.available,
+                held: account.held,
+                total: account.total,
+                ..Self::default()
+            })?;
+        }
+        Ok(totals)
+    }
+
+    fn add(&mut self, other: &Self) -> AppResult<()> {
+        let sum = |a: Currency, b: Currency| a.checked_add(b).ok_or(AppError::FundsOverflow);
+        self.available = sum(self.available, other.available)?;
+        self.held = sum(self.held, other.held)?;
+        self.total = sum(self.total, other.total)?;
+        self.accounts += other.accounts;
+        self.transactions += other.transactions;
+        self.parked_transactions += other.parked_transactions;
+        Ok(())
+    }
+}
+
+/// The outcome of a rebalance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RebalanceReport {
+    /// The totals over all shards, which are the same before and after.
+    pub totals: RebalanceTotals,
+    /// The totals of each new shard, in the order of the outputs.
+    pub shards: Vec<RebalanceTotals>,
+}
+
+/// Redistribute the state in the shard snapshots at `inputs` over as many
+/// new shards as there are `outputs`, as `assignment` says, and write the
+/// snapshot of each new shard in the given `format` to its output.
+pub async fn rebalance_snapshots(
+    inputs: &[PathBuf],
+    assignment: impl ShardAssignment + 'static,
+    outputs: &[PathBuf],
+    format: SnapshotFormat,
+) -> AppResult<RebalanceReport> {
+    if outputs.is_empty() {
+        return Err(AppError::NoRebalanceOutputs);
+    }
+    let mut merged = Transactor::new();
+    let mut expected = RebalanceTotals::default();
+    for input in inputs {
+        let mut shard = Transactor::new();
+        shard.restore_from(input.clone()).await?;
+        if let Some(&cid) = shard
+            .accounts
+            .keys()
+            .find(|cid| merged.accounts.contains_key(cid))
+        {
+            return Err(AppError::AccountInSeveralShards { cid });
+        }
+        expected.add(&RebalanceTotals::of(&shard)?)?;
+        merged.periods_closed = merged.periods_closed.max(shard.periods_closed);
+        merged.absorb_shard(shard);
+    }
+    merged.set_shard_assignment(assignment);
+    let mut split = merged.split_into_shards(outputs.len());
+    for adjustment in std::mem::take(&mut merged.adjustments) {
+        let shard = merged.shard_of(adjustment.transaction.cid(), outputs.len());
+        split[shard].adjustments.push(adjustment);
+    }
+    let mut actual = RebalanceTotals::default();
+    let mut shards = Vec::with_capacity(split.len());
+    for shard in split.iter_mut() {
+        shard.periods_closed = merged.periods_closed;
+        let totals = RebalanceTotals::of(shard)?;
+        actual.add(&totals)?;
+        shards.push(totals);
+    }
+    ensure_totals_match(&expected, &actual)?;
+    for ((shard, output), totals) in split.iter().zip(outputs).zip(&shards) {
+        shard
+            .snapshot_to_with_format(output.clone(), format)
+            .await?;
+        let mut written = Transactor::new();
+        written.restore_from(output.clone()).await?;
+        ensure_totals_match(totals, &RebalanceTotals::of(&written)?)?;
+    }
+    Ok(RebalanceReport {
+        totals: actual,
+        shards,
+    })
+}
+
+#[inline]
+fn ensure_totals_match(expected: &RebalanceTotals, actual: &RebalanceTotals) -> AppResult<()> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(AppError::RebalanceMismatch {
+            expected: Box::new(expected.clone()),
+            actual: Box::new(actual.clone()),
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use super::*;
+use crate::core::{ClientId, Transaction, TransactionId};
+use crate::partition::HashAssignment;
+use crate::test_util::temp::TempPath;
+
+/// The path of a snapshot named `name` in the directory `dir`.
+fn snapshot_path(dir: &TempPath, name: &str) -> PathBuf {
+    dir.join(format!("{}.snapshot", name))
+}
+
+/// A `Transactor` that processed `transactions`.
+async fn shard_of(transactions: &[Transaction]) -> AppResult<Transactor> {
+    let mut shard = Transactor::new();
+    for t in transactions {
+        shard.process_row(*t).await?;
+    }
+    Ok(shard)
+}
+
+#[tokio::test]
+async fn rebalance_two_shards_into_three() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let even = [
+        Transaction::deposit(cid(2), tid(1), amount("2.0")?),
+        Transaction::deposit(cid(4), tid(2), amount("4.0")?),
+        Transaction::dispute(cid(4), tid(2)),
+    ];
+    let odd = [
+        Transaction::deposit(cid(1), tid(3), amount("1.0")?),
+        Transaction::deposit(cid(3), tid(4), amount("3.0")?),
+        Transaction::deposit(cid(5), tid(5), amount("5.0")?),
+    ];
+    let dir = TempPath::dir("rebalance")?;
+    let inputs = [snapshot_path(&dir, "even"), snapshot_path(&dir, "odd")];
+    let outputs = [0, 1, 2].map(|idx| snapshot_path(&dir, &idx.to_string()));
+    shard_of(&even)
+        .await?
+        .snapshot_to(inputs[0].clone())
+        .await?;
+    shard_of(&odd).await?.snapshot_to(inputs[1].clone()).await?;
+    let report =
+        rebalance_snapshots(&inputs, HashAssignment, &outputs, SnapshotFormat::Compact).await?;
+    let mut restored = vec![];
+    for output in &outputs {
+        let mut shard = Transactor::new();
+        shard.restore_from(output.clone()).await?;
+        restored.push(shard);
+    }
+    assert_eq!(report.totals.accounts, 5);
+    assert_eq!(report.totals.transactions, 5);
+    assert_eq!(report.totals.total, amount("15.0")?);
+    assert_eq!(report.totals.held, amount("4.0")?);
+    for (idx, shard) in restored.iter().enumerate() {
+        assert!(shard
+            .accounts
+            .keys()
+            .all(|cid| usize::from(cid.0) % 3 == idx));
+        assert_eq!(shard.resident_transactions, report.shards[idx].transactions);
+    }
+    // NOTE: The open dispute moved along with client 4, so it can be resolved.
+    restored[1]
+        .process_row(Transaction::resolve(cid(4), tid(2)))
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn refuse_a_client_in_several_shards() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let dir = TempPath::dir("rebalance")?;
+    let inputs = [snapshot_path(&dir, "first"), snapshot_path(&dir, "second")];
+    let output = [snapshot_path(&dir, "merged")];
+    let deposit = Transaction::deposit(cid(1), tid(1), amount("1.0")?);
+    shard_of(&[deposit])
+        .await?
+        .snapshot_to(inputs[0].clone())
+        .await?;
+    shard_of(&[deposit])
+        .await?
+        .snapshot_to(inputs[1].clone())
+        .await?;
+    let result =
+        rebalance_snapshots(&inputs, HashAssignment, &output, SnapshotFormat::Compact).await;
+    assert!(matches!(
+        result,
+        Err(AppError::AccountInSeveralShards { cid: ClientId(1) })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn refuse_to_rebalance_into_no_shards() -> AppResult<()> {
+    let result = rebalance_snapshots(&[], HashAssignment, &[], SnapshotFormat::Compact).await;
+    assert!(matches!(result, Err(AppError::NoRebalanceOutputs)));
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! This module defines the processing of `CSV` input that arrives as a
+//! stream of bytes from elsewhere, such as the body of an `HTTP(S)` response
+//! or an object in an object store.
+//!
+//! The stream is forwarded to the `CSV` deserializer by a task of its own,
+//! so downloading overlaps with processing, and the input never has to fit
+//! in memory. Compressed input is decompressed as it's streamed.
+
+use crate::compression::{decompress, Compression};
+use crate::core::Transactor;
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use bytes::Bytes;
+use std::error::Error;
+use std::io;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::io::StreamReader;
+
+/// The number of chunks of the input that are downloaded ahead of parsing.
+const READ_AHEAD_CHUNKS: usize = 64;
+
+impl<S: AccountStore> Transactor<S> {
+    /// Process the transactions in the `CSV` data produced by `stream`,
+    /// which is compressed as `compression` says. The `source` names the
+    /// input in logs and errors, e.g. its URL.
+    pub(crate) async fn process_byte_stream<B, E>(
+        &mut self,
+        mut stream: B,
+        compression: Compression,
+        source: String,
+    ) -> AppResult<()>
+    where
+        B: Stream<Item = Result<Bytes, E>> + Send + Unpin + 'static,
+        E: Into<Box<dyn Error + Send + Sync>> + Send,
+    {
+        let (sender, receiver) = mpsc::channel(READ_AHEAD_CHUNKS);
+        let download = tokio::spawn(async move {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(io::Error::other);
+                let failed = chunk.is_err();
+                if sender.send(chunk).await.is_err() || failed {
+                    // NOTE: Processing ended early because of a fatal error,
+                    //       or the error ends the stream for the reader.
+                    break;
+                }
+            }
+        });
+        let reader = StreamReader::new(ReceiverStream::new(receiver));
+        let reader = decompress(reader, compression);
+        let result = self.process_named_csv_reader(reader, source).await;
+        download.await?;
+        result
+    }
+}
@@ -0,0 +1,155 @@
+//! This module defines log files that roll over, for the line-oriented file
+//! sinks that grow for as long as the engine runs, such as the run log, the
+//! legacy mirror and the rejected rows.
+//!
+//! A `RollingFile` writes to the file at its path until that file grows
+//! past `RollingPolicy::max_bytes`, or has been open for longer than
+//! `RollingPolicy::max_age`. It then renames the file to `<path>.1`,
+//! shifting older files to `<path>.2`, `<path>.3` and so on, deletes those
+//! beyond `RollingPolicy::keep`, and continues in a new file at its path.
+//! Files only roll over between lines, so no line is ever split across two
+//! files.
+
+#[cfg(test)]
+mod tests;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// When a `RollingFile` rolls over, and how many old files it keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RollingPolicy {
+    /// Roll over once the file holds at least this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Roll over once the file has been open for at least this long.
+    pub max_age: Option<Duration>,
+    /// The number of rolled over files to keep. Older ones are deleted.
+    pub keep: usize,
+}
+
+impl Default for RollingPolicy {
+    /// Never roll over.
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            max_age: None,
+            keep: 5,
+        }
+    }
+}
+
+/// A file that rolls over according to a `RollingPolicy`.
+#[derive(Debug)]
+pub struct RollingFile {
+    path: PathBuf,
+    policy: RollingPolicy,
+    file: BufWriter<File>,
+    /// The number of bytes in the current file.
+    len: u64,
+    opened: Instant,
+    /// Whether the last byte written ended a line.
+    at_line_start: bool,
+}
+
+impl RollingFile {
+    /// Create the file at `path`, truncating it if it exists.
+    pub fn create(path: impl Into<PathBuf>, policy: RollingPolicy) -> io::Result<Self> {
+        let path = path.into();
+        let file = File::create(&path)?;
+        Ok(Self::with_file(path, policy, file, 0))
+    }
+
+    /// Open the file at `path` for appending, creating it if it doesn't
+    /// exist. Its current contents count towards `RollingPolicy::max_bytes`.
+    pub fn append(path: impl Into<PathBuf>, policy: RollingPolicy) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self::with_file(path, policy, file, len))
+    }
+
+    fn with_file(path: PathBuf, policy: RollingPolicy, file: File, len: u64) -> Self {
+        Self {
+            path,
+            policy,
+            file: BufWriter::new(file),
+            len,
+            opened: Instant::now(),
+            at_line_start: true,
+        }
+    }
+
+    /// The path of the file with index `idx`, where index 0 is the current
+    /// file and index 1 the most recently rolled over one.
+    pub fn rolled_path(&self, idx: usize) -> PathBuf {
+        rolled_path(&self.path, idx)
+    }
+
+    fn is_due(&self) -> bool {
+        let too_big = self.policy.max_bytes.is_some_and(|max| self.len >= max);
+        let too_old = self
+            .policy
+            .max_age
+            .is_some_and(|max| self.opened.elapsed() >= max);
+        self.len > 0 && (too_big || too_old)
+    }
+
+    /// Close the current file, shift the rolled over files, and open a new
+    /// file at the path.
+    fn roll_over(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let keep = self.policy.keep;
+        remove_if_exists(&rolled_path(&self.path, keep))?;
+        for idx in (0..keep).rev() {
+            let from = rolled_path(&self.path, idx);
+            if from.exists() {
+                fs::rename(&from, rolled_path(&self.path, idx + 1))?;
+            }
+        }
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.len = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.at_line_start && self.is_due() {
+            self.roll_over()?;
+        }
+        let written = self.file.write(buf)?;
+        self.len += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[inline(always)]
+fn rolled_path(path: &Path, idx: usize) -> PathBuf {
+    if idx == 0 {
+        return path.to_path_buf();
+    }
+    let mut rolled = path.as_os_str().to_owned();
+    rolled.push(format!(".{}", idx));
+    PathBuf::from(rolled)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+use crate::error::AppResult;
+use crate::test_util::temp::TempPath;
+
+#[test]
+fn rolls_over_by_size_between_lines() -> AppResult<()> {
+    let dir = TempPath::dir("rolling-size")?;
+    let path = dir.join("run.log");
+    let policy = RollingPolicy {
+        max_bytes: Some(10),
+        keep: 2,
+        ..RollingPolicy::default()
+    };
+    let mut file = RollingFile::create(&path, policy)?;
+    // NOTE: The line is written in pieces, as `writeln!()` does.
+    for line in ["first", "second", "third", "fourth"] {
+        file.write_all(line.as_bytes())?;
+        file.write_all(b" line\n")?;
+    }
+    file.flush()?;
+    let read = |idx| fs::read_to_string(file.rolled_path(idx));
+    let (current, last, oldest) = (read(0)?, read(1)?, read(2)?);
+    let dropped = file.rolled_path(3).exists();
+    assert_eq!(current, "fourth line\n");
+    assert_eq!(last, "third line\n");
+    assert_eq!(oldest, "second line\n");
+    assert!(!dropped, "only 2 rolled over files are kept");
+    Ok(())
+}
+
+#[test]
+fn rolls_over_by_age() -> AppResult<()> {
+    let dir = TempPath::dir("rolling-age")?;
+    let path = dir.join("rejects.txt");
+    let policy = RollingPolicy {
+        max_age: Some(Duration::from_millis(20)),
+        ..RollingPolicy::default()
+    };
+    let mut file = RollingFile::create(&path, policy)?;
+    file.write_all(b"one\n")?;
+    file.write_all(b"two\n")?;
+    std::thread::sleep(Duration::from_millis(30));
+    file.write_all(b"three\n")?;
+    file.flush()?;
+    let (current, last) = (
+        fs::read_to_string(&path)?,
+        fs::read_to_string(file.rolled_path(1))?,
+    );
+    assert_eq!(current, "three\n");
+    assert_eq!(last, "one\ntwo\n");
+    Ok(())
+}
+
+#[test]
+fn appending_counts_existing_contents() -> AppResult<()> {
+    let dir = TempPath::dir("rolling-append")?;
+    let path = dir.join("run.log");
+    fs::write(&path, "earlier run\n")?;
+    let policy = RollingPolicy {
+        max_bytes: Some(10),
+        keep: 0,
+        ..RollingPolicy::default()
+    };
+    let mut file = RollingFile::append(&path, policy)?;
+    file.write_all(b"this run\n")?;
+    file.flush()?;
+    let current = fs::read_to_string(&path)?;
+    let kept = file.rolled_path(1).exists();
+    assert_eq!(current, "this run\n");
+    assert!(!kept, "no rolled over files are kept");
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! This module defines a machine-readable log of a run, so that runs of
+//! different versions of the engine can be compared row by row rather than
+//! only by their final account summaries.
+//!
+//! The log is in the `JSON` Lines format: one `JSON` object per row handed
+//! to `Transactor::process_row()`, e.g.
+//!
+//! `{"sequence":3,"tid":2,"cid":1,"type":"withdrawal","result":"insufficient_funds","balances":{"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}}`
+//!
+//! `result` is `accepted`, `parked` for transactions that wait for the
+//! transaction they refer to, or the reason the row was rejected, as in the
+//! per-client statistics. `balances` are those of the client's account after
+//! the row was processed, or `null` if the client has no account.
+
+#[cfg(test)]
+mod tests;
+
+use crate::config::ScaleViolation;
+use crate::core::{Account, ClientId, Currency, Transaction, TransactionId, Transactor};
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use serde_derive::Serialize;
+use std::fmt;
+use std::io::{Error as IoError, Write};
+
+/// A single line of the run log.
+#[derive(Debug, Serialize)]
+struct RunLogEntry {
+    sequence: u64,
+    tid: TransactionId,
+    cid: ClientId,
+    #[serde(rename = "type")]
+    ttype: &'static str,
+    result: &'static str,
+    balances: Option<Balances>,
+}
+
+/// The balances of an account, formatted as in the account summary.
+#[derive(Debug, Serialize)]
+struct Balances {
+    available: String,
+    held: String,
+    total: String,
+    locked: bool,
+}
+
+impl Balances {
+    fn new(account: &Account, rounding: ScaleViolation) -> Self {
+        let format = |amount: Currency| amount.rounded_for_output(rounding).to_output_string();
+        Self {
+            available: format(account.available),
+            held: format(account.held),
+            total: format(account.total),
+            locked: account.is_locked,
+        }
+    }
+}
+
+/// A sink that receives a run log entry for every row that the
+/// `Transactor` processes.
+pub struct RunLog {
+    writer: Box<dyn Write + Send + Sync>,
+    /// The first write error, after which nothing more is written
+    error: Option<IoError>,
+}
+
+impl RunLog {
+    #[inline(always)]
+    pub fn new(writer: Box<dyn Write + Send + Sync>) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Write the entry for the row with number `sequence`, holding the
+    /// transaction `t`, which resulted in `result`. The balances are
+    /// rounded as in the account summary.
+    pub(crate) fn emit(
+        &mut self,
+        sequence: u64,
+        t: &Transaction,
+        result: &'static str,
+        account: Option<&Account>,
+        rounding: ScaleViolation,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        let entry = RunLogEntry {
+            sequence,
+            tid: t.tid,
+            cid: t.cid,
+            ttype: t.ttype.name(),
+            result,
+            balances: account.map(|account| Balances::new(account, rounding)),
+        };
+        let written = serde_json::to_writer(&mut self.writer, &entry)
+            .map_err(IoError::from)
+            .and_then(|()| self.writer.write_all(b"\n"));
+        if let Err(e) = written {
+            self.error = Some(e);
+        }
+    }
+
+    /// Flush the log, and return the first error that occurred, if any.
+    pub fn finish(&mut self) -> AppResult<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e.into());
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RunLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RunLog")
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Log every row processed from now on to `run_log`.
+    pub fn set_run_log(&mut self, run_log: RunLog) {
+        self.run_log = Some(run_log);
+    }
+
+    /// Flush the run log, if any, and report whether all entries were
+    /// written successfully.
+    pub fn finish_run_log(&mut self) -> AppResult<()> {
+        match self.run_log.as_mut() {
+            Some(run_log) => run_log.finish(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::*;
+use crate::config::Config;
+use crate::core::{Currency, TransactionType};
+use std::sync::{Arc, Mutex};
+
+/// A writer whose output can still be inspected after it was handed off.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn log_every_row() -> AppResult<()> {
+    let buffer = SharedBuffer::default();
+    let mut transactor = Transactor::with_config(Config {
+        dispute_grace_rows: 1,
+        ..Config::default()
+    });
+    transactor.set_run_log(RunLog::new(Box::new(buffer.clone())));
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("1.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("5.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Dispute,
+            cid: ClientId(2),
+            tid: TransactionId(3),
+            amount: None,
+        },
+    ];
+    for transaction in transactions {
+        let _ = transactor.process_row(transaction).await;
+    }
+    transactor.finish_run_log()?;
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{"sequence":1,"tid":1,"cid":1,"type":"deposit","result":"accepted","balances":{"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}}"#,
+            r#"{"sequence":2,"tid":2,"cid":1,"type":"withdrawal","result":"insufficient_funds","balances":{"available":"1.0000","held":"0.0000","total":"1.0000","locked":false}}"#,
+            r#"{"sequence":3,"tid":3,"cid":2,"type":"dispute","result":"parked","balances":{"available":"0.0000","held":"0.0000","total":"0.0000","locked":false}}"#,
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,269 @@
+//! This module defines sharded processing, which spreads the clients of an
+//! input over several tokio tasks, to make use of more than one core.
+//!
+//! The transactions of different clients are independent of each other, so
+//! each row is routed by its `ClientId` to the shard that owns the client,
+//! over a bounded channel. Every shard is a `Transactor` of its own, run by
+//! its own task, and the shards are merged back into the `Transactor` once
+//! the input has been read. Each shard numbers its rows as if it had seen
+//! the rows of all clients, so grace periods and cooling-off periods end at
+//! the same rows as they would without sharding.
+//!
+//! As a result, the account summary is the same as that of processing the
+//! input on a single task. Rejected rows are ordered by their line number.
+//!
+//! Which shard owns a client is decided by the `ShardAssignment` set with
+//! `Transactor::set_shard_assignment()`. See the `partition` module.
+//!
+//! Features that need to see the rows of all clients in order can't be
+//! sharded: the run log, the legacy sink, `OrderingPolicy::MonotonicTid`
+//! and `Limits::max_resident_transactions`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::compression::{decompress, Compression};
+use crate::config::OrderingPolicy;
+use crate::core::{read_csv_rows, ClientId, Transaction, Transactor};
+use crate::error::{AppError, AppResult};
+use crate::index::TransactionIndex;
+use crate::partition::{HashAssignment, ShardAssignment};
+use crate::quality::QualityTracker;
+use std::path::PathBuf;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+/// The number of rows that can be queued for each shard.
+const SHARD_QUEUE_ROWS: usize = 1024;
+
+/// A row routed to a shard: its row number, its line number in the input,
+/// if known, and its transaction.
+type ShardRow = (u64, Option<u64>, Transaction);
+
+impl Transactor {
+    /// Read, deserialize and process the transactions in the `CSV` file at
+    /// `filepath`, spread over `shards` tasks by client.
+    pub async fn process_csv_file_sharded(
+        &mut self,
+        filepath: PathBuf,
+        shards: usize,
+    ) -> AppResult<()> {
+        let file = tokio::fs::File::open(&filepath).await?;
+        let reader = decompress(file, self.config.compression_of(&filepath));
+        let source = filepath.display().to_string();
+        self.process_named_csv_reader_sharded(reader, source, shards)
+            .await
+    }
+
+    /// Read `CSV` data from `stdin`, and process the transactions in it
+    /// spread over `shards` tasks by client.
+    pub async fn process_csv_stdin_sharded(&mut self, shards: usize) -> AppResult<()> {
+        let source = "-".to_string();
+        let compression = self.config.compression.unwrap_or(Compression::Uncompressed);
+        let reader = decompress(tokio::io::stdin(), compression);
+        self.process_named_csv_reader_sharded(reader, source, shards)
+            .await
+    }
+
+    /// Read, deserialize and process the transactions in the `CSV` data
+    /// produced by `reader`, spread over `shards` tasks by client.
+    pub async fn process_csv_reader_sharded<R>(&mut self, reader: R, shards: usize) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync,
+    {
+        let source = "reader".to_string();
+        self.process_named_csv_reader_sharded(reader, source, shards)
+            .await
+    }
+
+    async fn process_named_csv_reader_sharded<R>(
+        &mut self,
+        reader: R,
+        source: String,
+        shards: usize,
+    ) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync,
+    {
+        self.ensure_shardable()?;
+        let mut senders = Vec::with_capacity(shards.max(1));
+        let mut tasks = Vec::with_capacity(shards.max(1));
+        for shard in self.split_into_shards(shards.max(1)) {
+            let (sender, receiver) = mpsc::channel(SHARD_QUEUE_ROWS);
+            senders.push(sender);
+            tasks.push(tokio::spawn(run_shard(shard, receiver)));
+        }
+        let mut quality = QualityTracker::new(source);
+        let routed = self.route_rows(reader, &senders, &mut quality).await;
+        // NOTE: Closing the channels lets the shards finish.
+        drop(senders);
+        let first_rejected = self.ignored_transactions.len();
+        let mut shard_result = Ok(());
+        for task in tasks {
+            let (shard, result) = task.await?;
+            self.absorb_shard(shard);
+            shard_result = shard_result.and(result);
+        }
+        self.ignored_transactions[first_rejected..].sort_by_key(|ignored| ignored.line());
+        self.record_data_quality(quality.finish());
+        // NOTE: A shard can only fail on a row that was routed before the
+        //       row that the routing failed on, if any.
+        shard_result.and(routed)
+    }
+
+    /// Return an error if the `Transactor` uses a feature that can't be
+    /// sharded.
+    fn ensure_shardable(&self) -> AppResult<()> {
+        let feature = if self.run_log.is_some() {
+            "run log"
+        } else if self.legacy_sink.is_some() {
+            "legacy sink"
+        } else if matches!(self.config.ordering, OrderingPolicy::MonotonicTid(_)) {
+            "monotonic transaction ids"
+        } else if self.config.limits.max_resident_transactions.is_some() {
+            "resident transaction limit"
+        } else {
+            return Ok(());
+        };
+        Err(AppError::UnsupportedWhenSharded { feature })
+    }
+
+    /// The index of the shard that owns the client with id `cid`, out of
+    /// `shards` shards. Clients are routed by the id they are remapped to.
+    #[inline(always)]
+    pub(crate) fn shard_of(&self, cid: ClientId, shards: usize) -> usize {
+        let cid = self.config.client_ids.get(cid).unwrap_or(cid);
+        match &self.shard_assignment {
+            Some(assignment) => assignment.shard_of(cid, shards) % shards,
+            None => HashAssignment.shard_of(cid, shards),
+        }
+    }
+
+    /// Move the state of each client into the shard that owns it.
+    pub(crate) fn split_into_shards(&mut self, shards: usize) -> Vec<Transactor> {
+        let mut split: Vec<Transactor> = (0..shards)
+            .map(|_| {
+                let mut shard = Transactor::with_config(self.config.clone());
+                shard.rows_processed = self.rows_processed;
+                shard.max_tid = self.max_tid;
+                shard
+            })
+            .collect();
+        for (cid, account) in std::mem::take(&mut self.accounts) {
+            let shard = &mut split[self.shard_of(cid, shards)];
+            // NOTE: The transactions in the histories move along with the
+            //       account, so that evicting them later can't underflow.
+            let resident = account.history_len();
+            shard.resident_transactions += resident;
+            self.resident_transactions = self.resident_transactions.saturating_sub(resident);
+            shard.accounts.insert(cid, account);
+        }
+        for (cid, stats) in std::mem::take(&mut self.client_stats) {
+            split[self.shard_of(cid, shards)]
+                .client_stats
+                .insert(cid, stats);
+        }
+        for (cid, deadline) in std::mem::take(&mut self.cooling_off) {
+            split[self.shard_of(cid, shards)]
+                .cooling_off
+                .insert(cid, deadline);
+        }
+        for (cid, statement) in std::mem::take(&mut self.statements) {
+            split[self.shard_of(cid, shards)]
+                .statements
+                .insert(cid, statement);
+        }
+        for parked in std::mem::take(&mut self.parked_transactions) {
+            split[self.shard_of(parked.cid(), shards)]
+                .parked_transactions
+                .push(parked);
+        }
+        if let Some(index) = &mut self.index {
+            // NOTE: Each shard indexes the histories of its own clients, and
+            //       `absorb_shard()` merges their indexes back.
+            *index = TransactionIndex::default();
+            for shard in split.iter_mut() {
+                shard.index_histories();
+            }
+        }
+        split
+    }
+
+    /// Merge the state of a `shard` back into the `Transactor`.
+    pub(crate) fn absorb_shard(&mut self, shard: Transactor) {
+        self.accounts.extend(shard.accounts);
+        self.client_stats.extend(shard.client_stats);
+        self.cooling_off.extend(shard.cooling_off);
+        self.statements.extend(shard.statements);
+        self.parked_transactions.extend(shard.parked_transactions);
+        self.rows_processed = self.rows_processed.max(shard.rows_processed);
+        self.max_tid = self.max_tid.max(shard.max_tid);
+        self.resident_transactions += shard.resident_transactions;
+        self.slow_operations.extend(shard.slow_operations);
+        self.warnings.extend(shard.warnings);
+        self.account_events.extend(shard.account_events);
+        self.ignored_transactions.extend(shard.ignored_transactions);
+        self.adjustments.extend(shard.adjustments);
+        self.metrics.absorb(shard.metrics);
+        if let (Some(index), Some(shard_index)) = (&mut self.index, shard.index) {
+            index.extend(shard_index);
+        }
+    }
+
+    /// Read the rows of the `CSV` data produced by `reader`, and send each
+    /// to the shard that owns its client.
+    async fn route_rows<R>(
+        &self,
+        reader: R,
+        senders: &[mpsc::Sender<ShardRow>],
+        quality: &mut QualityTracker,
+    ) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync,
+    {
+        let rows = read_csv_rows(reader, self.config.type_aliases.clone()).await?;
+        tokio::pin!(rows);
+        let mut row = self.rows_processed;
+        while let Some(next) = rows.next().await {
+            quality.observe(&next);
+            let (line, t) = match next {
+                Ok(next) => next,
+                Err(e) if e.is_row_error() && self.config.skip_malformed_rows => continue,
+                Err(e) => return Err(e),
+            };
+            row += 1;
+            let shard = self.shard_of(t.cid(), senders.len());
+            if senders[shard].send((row, line, t)).await.is_err() {
+                // NOTE: The shard ended early because of a fatal error,
+                //       which is returned once the shard is joined.
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Process the rows routed to this shard, in order.
+    async fn process_shard_rows(&mut self, rows: &mut mpsc::Receiver<ShardRow>) -> AppResult<()> {
+        while let Some((row, line, t)) = rows.recv().await {
+            // NOTE: Catch up on the rows of the other shards first, as if
+            //       they had been processed here.
+            self.rows_processed = row - 1;
+            self.expire_parked_transactions(Some(self.rows_processed));
+            self.end_cooling_off_periods();
+            self.process_csv_row(t, line).await?;
+        }
+        self.expire_parked_transactions(None);
+        Ok(())
+    }
+}
+
+/// Run a `shard` on the rows it receives, and hand it back when they run
+/// out or processing fails.
+async fn run_shard(
+    mut shard: Transactor,
+    mut rows: mpsc::Receiver<ShardRow>,
+) -> (Transactor, AppResult<()>) {
+    let result = shard.process_shard_rows(&mut rows).await;
+    (shard, result)
+}
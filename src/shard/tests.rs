@@ -0,0 +1,171 @@
+use super::*;
+use crate::config::{Config, Limits, OrderingViolation};
+use crate::core::{Currency, TransactionId, TransactionQuery};
+use crate::test_util::corpus::Case;
+
+/// The account summary of `transactor` as `CSV`.
+async fn render_output(transactor: &Transactor) -> AppResult<Vec<u8>> {
+    let mut output = vec![];
+    transactor.write_output(&mut output).await?;
+    Ok(output)
+}
+
+/// The line numbers and transaction ids of the rows rejected by
+/// `transactor`, in order.
+fn rejected_rows(transactor: &Transactor) -> Vec<(Option<u64>, TransactionId)> {
+    transactor
+        .rejected()
+        .map(|r| (r.line(), r.transaction().tid()))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn sharded_output_is_identical_to_sequential_output() -> AppResult<()> {
+    for case in Case::generate_all(13) {
+        let mut sequential = Transactor::new();
+        sequential.process_csv_reader(case.csv.as_bytes()).await?;
+        let expected = render_output(&sequential).await?;
+        let mut expected_rejected = rejected_rows(&sequential);
+        expected_rejected.sort();
+        for shards in [1, 2, 5] {
+            let mut sharded = Transactor::new();
+            sharded
+                .process_csv_reader_sharded(case.csv.as_bytes(), shards)
+                .await?;
+            let message = format!("case {} on {} shards", case.name(), shards);
+            assert_eq!(render_output(&sharded).await?, expected, "{}", message);
+            assert_eq!(rejected_rows(&sharded), expected_rejected, "{}", message);
+            assert_eq!(sharded.stats()?, sequential.stats()?, "{}", message);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn grace_periods_span_the_rows_of_other_shards() -> AppResult<()> {
+    // NOTE: Client 1's dispute waits 2 rows for its deposit, so it's
+    //       rejected the first time, but not the second.
+    let csv = "type,client,tx,amount\n\
+               dispute,1,1,\n\
+               deposit,2,2,1.0\n\
+               deposit,3,3,1.0\n\
+               deposit,1,1,1.0\n\
+               dispute,1,4,\n\
+               deposit,2,5,1.0\n\
+               deposit,1,4,2.0\n";
+    let config = Config {
+        dispute_grace_rows: 2,
+        ..Config::default()
+    };
+    let mut sequential = Transactor::with_config(config.clone());
+    sequential.process_csv_reader(csv.as_bytes()).await?;
+    let mut sharded = Transactor::with_config(config);
+    sharded
+        .process_csv_reader_sharded(csv.as_bytes(), 3)
+        .await?;
+    assert_eq!(
+        render_output(&sharded).await?,
+        render_output(&sequential).await?
+    );
+    assert_eq!(rejected_rows(&sharded), [(Some(2), TransactionId(1))]);
+    let account = sharded.account(ClientId(1)).unwrap();
+    assert_eq!(account.held(), Currency::from_str("2.0")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn shards_are_merged_across_inputs() -> AppResult<()> {
+    let mut transactor = Transactor::new();
+    let first = "type,client,tx,amount\ndeposit,1,1,1.0\ndeposit,2,2,1.0\n";
+    let second = "type,client,tx,amount\nwithdrawal,1,3,0.5\ndispute,2,2,\n";
+    transactor
+        .process_csv_reader_sharded(first.as_bytes(), 2)
+        .await?;
+    transactor
+        .process_csv_reader_sharded(second.as_bytes(), 3)
+        .await?;
+    assert_eq!(
+        String::from_utf8(render_output(&transactor).await?).unwrap(),
+        "client,available,held,total,locked\n\
+         1,0.5000,0.0000,0.5000,false\n\
+         2,0.0000,1.0000,1.0000,false\n"
+    );
+    assert_eq!(transactor.stats()?.rows_processed, 4);
+    assert_eq!(transactor.data_quality().len(), 2);
+    Ok(())
+}
+
+// NOTE: A snapshot restored with a tighter `RetentionPolicy` holds more
+//       transactions than the policy keeps, until its accounts see new ones.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn shards_count_the_transactions_they_are_given() -> AppResult<()> {
+    let first = "type,client,tx,amount
+                 deposit,1,1,1.0
+                 deposit,1,2,1.0
+                 deposit,1,3,1.0
+";
+    let second = "type,client,tx,amount
+deposit,1,4,1.0
+";
+    let mut transactor = Transactor::new();
+    transactor.process_csv_reader(first.as_bytes()).await?;
+    transactor.config.retention.max_settled_per_account = Some(1);
+    transactor
+        .process_csv_reader_sharded(second.as_bytes(), 2)
+        .await?;
+    assert_eq!(transactor.resident_transactions, 1);
+    let account = transactor.account(ClientId(1)).unwrap();
+    assert_eq!(account.available(), Currency::from_str("4.0")?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn shards_keep_the_search_indexes() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               deposit,2,2,2.0\n\
+               deposit,3,3,3.0\n\
+               dispute,2,2,\n";
+    let mut scanned = Transactor::new();
+    scanned.process_csv_reader(csv.as_bytes()).await?;
+    let mut indexed = Transactor::new();
+    indexed.index_histories();
+    indexed
+        .process_csv_reader_sharded(csv.as_bytes(), 2)
+        .await?;
+    let query = TransactionQuery::default();
+    assert_eq!(indexed.search(&query), scanned.search(&query));
+    assert_eq!(
+        indexed.index,
+        Some(TransactionIndex::build(indexed.accounts.values()))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn features_that_need_all_rows_cant_be_sharded() -> AppResult<()> {
+    let configs = [
+        Config {
+            ordering: OrderingPolicy::MonotonicTid(OrderingViolation::Reject),
+            ..Config::default()
+        },
+        Config {
+            limits: Limits {
+                max_resident_transactions: Some(10),
+                ..Limits::default()
+            },
+            ..Config::default()
+        },
+    ];
+    for config in configs.iter() {
+        let mut transactor = Transactor::with_config(config.clone());
+        let result = transactor
+            .process_csv_reader_sharded("type,client,tx,amount\n".as_bytes(), 2)
+            .await;
+        assert!(matches!(
+            result,
+            Err(AppError::UnsupportedWhenSharded { .. })
+        ));
+    }
+    Ok(())
+}
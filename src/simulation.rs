@@ -0,0 +1,113 @@
+//! This module defines what-if simulations: processing a batch of
+//! transactions against the current state of the accounts without changing
+//! that state, e.g. to pre-authorize a batch of withdrawals.
+//!
+//! A simulation runs the batch on a scratch copy of the accounts that the
+//! batch involves, with the same `Config` as the `Transactor`, so the
+//! verdicts take every policy into account: limits, freeze triggers such
+//! as `FreezePolicy::balance_floor`, and the effect of earlier transactions
+//! in the batch on later ones.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Currency, Transaction, Transactor};
+use crate::error::TransactionError;
+use crate::store::AccountStore;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+
+/// Whether a single transaction of a simulated batch would be accepted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Verdict {
+    pub transaction: Transaction,
+    /// Why the transaction would be rejected, if it would be.
+    pub error: Option<TransactionError>,
+}
+
+impl Verdict {
+    #[inline(always)]
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The balance of an account after a simulated batch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ProjectedBalance {
+    pub available: Currency,
+    pub held: Currency,
+    pub total: Currency,
+    pub locked: bool,
+}
+
+/// The outcome of `Transactor::simulate()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Simulation {
+    /// A verdict per transaction, in the order of the batch.
+    pub verdicts: Vec<Verdict>,
+    /// The projected balance of each client with an account after the
+    /// batch, by client id. Clients without an account are left out.
+    pub balances: BTreeMap<ClientId, ProjectedBalance>,
+}
+
+impl Simulation {
+    /// Whether every transaction of the batch would be accepted.
+    #[inline(always)]
+    pub fn is_accepted(&self) -> bool {
+        self.verdicts.iter().all(Verdict::is_accepted)
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Simulate processing the transactions of `batch` in order, without
+    /// changing the state of the `Transactor`. The client ids of the batch
+    /// are remapped with `Config::client_ids` first, as those of rows are,
+    /// and the projected balances are by remapped client id.
+    pub async fn simulate(&self, batch: impl IntoIterator<Item = Transaction>) -> Simulation {
+        let mut scratch = Transactor::with_config(self.config.clone());
+        scratch.rows_processed = self.rows_processed;
+        scratch.max_tid = self.max_tid;
+        scratch.resident_transactions = self.resident_transactions;
+        scratch.cooling_off = self.cooling_off.clone();
+        let mut copied = 0;
+        let mut verdicts = vec![];
+        for transaction in batch {
+            let result = match self.config.client_ids.remap(transaction) {
+                Ok(t) => {
+                    if !scratch.accounts.contains_key(&t.cid) {
+                        if let Some(account) = self.accounts.get(&t.cid) {
+                            scratch.accounts.insert(account.id, account.clone());
+                            copied += 1;
+                        }
+                    }
+                    // NOTE: The scratch copy only holds the accounts of the
+                    //       batch, so count the others towards the limit.
+                    scratch.config.limits.max_accounts = self
+                        .config
+                        .limits
+                        .max_accounts
+                        .map(|max| (max + copied).saturating_sub(self.accounts.len()));
+                    scratch.process_transaction(t).await
+                }
+                Err(e) => Err(e),
+            };
+            let error = result.err();
+            verdicts.push(Verdict { transaction, error });
+        }
+        let balances = scratch
+            .accounts
+            .values()
+            .map(|account| {
+                let balance = ProjectedBalance {
+                    available: account.available,
+                    held: account.held,
+                    total: account.total,
+                    locked: account.is_locked,
+                };
+                (account.id, balance)
+            })
+            .collect();
+        Simulation { verdicts, balances }
+    }
+}
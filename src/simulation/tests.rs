@@ -0,0 +1,67 @@
+use super::*;
+use crate::config::Config;
+use crate::core::TransactionId;
+use crate::error::AppResult;
+
+#[tokio::test]
+async fn simulate_a_batch_of_withdrawals() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let mut transactor = Transactor::new();
+    transactor
+        .process_transaction(Transaction::deposit(cid(1), tid(1), amount("10.0")?))
+        .await?;
+    let batch = [
+        Transaction::withdrawal(cid(1), tid(2), amount("4.0")?),
+        Transaction::withdrawal(cid(1), tid(3), amount("7.0")?),
+        Transaction::withdrawal(cid(1), tid(4), amount("6.0")?),
+    ];
+    let simulation = transactor.simulate(batch).await;
+    let errors: Vec<_> = simulation
+        .verdicts
+        .iter()
+        .map(|verdict| verdict.error.as_ref().map(TransactionError::reason))
+        .collect();
+    assert_eq!(errors, vec![None, Some("insufficient_funds"), None]);
+    assert!(!simulation.is_accepted());
+    assert_eq!(
+        simulation.balances,
+        BTreeMap::from([(
+            cid(1),
+            ProjectedBalance {
+                available: amount("0.0")?,
+                held: amount("0.0")?,
+                total: amount("0.0")?,
+                locked: false,
+            }
+        )])
+    );
+    // NOTE: The simulation leaves the accounts as they were.
+    let account = transactor.iter_accounts().next().expect("an account");
+    assert_eq!(account.available(), amount("10.0")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn simulate_a_breach_of_the_balance_floor() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let mut config = Config::default();
+    config.freeze.balance_floor = Some(amount("5.0")?);
+    let mut transactor = Transactor::with_config(config);
+    transactor
+        .process_transaction(Transaction::deposit(cid(1), tid(1), amount("10.0")?))
+        .await?;
+    let batch = [
+        Transaction::withdrawal(cid(1), tid(2), amount("6.0")?),
+        Transaction::withdrawal(cid(1), tid(3), amount("1.0")?),
+    ];
+    let simulation = transactor.simulate(batch).await;
+    assert!(simulation.verdicts[0].is_accepted());
+    assert_eq!(
+        simulation.verdicts[1].error,
+        Some(TransactionError::AccountIsLocked { cid: cid(1) })
+    );
+    assert!(simulation.balances[&cid(1)].locked);
+    Ok(())
+}
@@ -0,0 +1,166 @@
+//! This module defines snapshots of the state of a `Transactor`, so that
+//! a long run can be checkpointed, and continued later with more input.
+//!
+//! A snapshot holds the client accounts, including their transaction
+//! histories, and the bookkeeping needed to keep processing new rows in
+//! the same way, such as the highest transaction id seen, pending
+//! cooling-off periods and the disputes, resolves and chargebacks that are
+//! parked until the transaction they refer to arrives. Together with the
+//! histories, that is the whole state of every dispute, so a restarted
+//! engine resolves and charges back disputes opened before the restart.
+//! The `Config`, the collected statistics, metrics and warnings, and any
+//! sinks are not part of a snapshot. Neither are the search indexes,
+//! which are rebuilt on restore if they are kept.
+//!
+//! Snapshots are stored as `JSON`, either compactly on a single line, or
+//! pretty-printed so that engineers can read and hand-edit them, e.g. to
+//! reduce the state needed to reproduce a bug. Restoring accepts either.
+//! The transaction histories and the freeze state of an account may be
+//! left out of a hand-written snapshot.
+//!
+//! Each snapshot records the version of its layout, so that snapshots
+//! written by older versions of the crate can be migrated on restore.
+//! See the `migrations` module.
+//!
+//! Serializing millions of accounts takes a while. `fork_snapshot()` only
+//! copies the state, which is much cheaper, and returns it as a
+//! `PendingSnapshot` that can be written by another task while the
+//! `Transactor` goes on processing rows.
+
+#[cfg(test)]
+mod tests;
+
+use crate::config::Config;
+use crate::core::Transactor;
+use crate::error::AppResult;
+use crate::migrations::{migrate_snapshot, SNAPSHOT_VERSION, VERSION_FIELD};
+use crate::store::AccountStore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// The layout of a snapshot file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// `JSON` on a single line
+    Compact,
+    /// Indented `JSON`, for reading and editing by hand
+    Json,
+}
+
+impl Default for SnapshotFormat {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Compact
+    }
+}
+
+impl<S> Transactor<S>
+where
+    S: AccountStore + Serialize + DeserializeOwned,
+{
+    /// Write a snapshot of the state of the `Transactor` to the file at
+    /// `filepath`, replacing the file if it exists.
+    #[inline(always)]
+    pub async fn snapshot_to(&self, filepath: PathBuf) -> AppResult<()> {
+        self.snapshot_to_with_format(filepath, SnapshotFormat::default())
+            .await
+    }
+
+    /// Write a snapshot of the state of the `Transactor` in the given
+    /// `format` to the file at `filepath`, replacing the file if it exists.
+    pub async fn snapshot_to_with_format(
+        &self,
+        filepath: PathBuf,
+        format: SnapshotFormat,
+    ) -> AppResult<()> {
+        let snapshot = self.serialize_snapshot(format)?;
+        tokio::fs::write(filepath, snapshot).await?;
+        Ok(())
+    }
+
+    pub(crate) fn serialize_snapshot(&self, format: SnapshotFormat) -> AppResult<Vec<u8>> {
+        let mut snapshot = serde_json::to_value(self)?;
+        if let Some(fields) = snapshot.as_object_mut() {
+            let _ = fields.insert(VERSION_FIELD.to_string(), Value::from(SNAPSHOT_VERSION));
+        }
+        Ok(match format {
+            SnapshotFormat::Compact => serde_json::to_vec(&snapshot)?,
+            SnapshotFormat::Json => serde_json::to_vec_pretty(&snapshot)?,
+        })
+    }
+
+    /// Replace the state of the `Transactor` by the snapshot in the file at
+    /// `filepath`, after migrating it if an older version of the crate
+    /// wrote it. The `Config` and sinks of the `Transactor` are kept.
+    pub async fn restore_from(&mut self, filepath: PathBuf) -> AppResult<()> {
+        let snapshot = tokio::fs::read(filepath).await?;
+        self.restore_from_slice(&snapshot)
+    }
+
+    /// Replace the state of the `Transactor` by the serialized `snapshot`.
+    pub(crate) fn restore_from_slice(&mut self, snapshot: &[u8]) -> AppResult<()> {
+        let mut snapshot: Value = serde_json::from_slice(snapshot)?;
+        migrate_snapshot(&mut snapshot)?;
+        let restored: Self = serde_json::from_value(snapshot)?;
+        self.accounts = restored.accounts;
+        self.rows_processed = restored.rows_processed;
+        self.max_tid = restored.max_tid;
+        self.resident_transactions = restored.resident_transactions;
+        self.cooling_off = restored.cooling_off;
+        self.parked_transactions = restored.parked_transactions;
+        self.periods_closed = restored.periods_closed;
+        self.adjustments = restored.adjustments;
+        self.statements = restored.statements;
+        if self.index.is_some() {
+            // NOTE: The indexes refer to the histories that were replaced.
+            self.index_histories();
+        }
+        Ok(())
+    }
+}
+
+impl<S> Transactor<S>
+where
+    S: AccountStore + Clone + Serialize + DeserializeOwned,
+{
+    /// Copy the state of the `Transactor` that goes into a snapshot, so that
+    /// it can be written without holding up the processing of new rows.
+    pub fn fork_snapshot(&self) -> PendingSnapshot<S> {
+        // NOTE: These are the fields that `restore_from()` restores.
+        let mut state = Transactor::with_store(Config::default(), self.accounts.clone());
+        state.rows_processed = self.rows_processed;
+        state.max_tid = self.max_tid;
+        state.resident_transactions = self.resident_transactions;
+        state.cooling_off = self.cooling_off.clone();
+        state.parked_transactions = self.parked_transactions.clone();
+        state.periods_closed = self.periods_closed;
+        state.adjustments = self.adjustments.clone();
+        state.statements = self.statements.clone();
+        PendingSnapshot { state }
+    }
+}
+
+/// The state of a `Transactor` as of a call to `Transactor::fork_snapshot()`,
+/// waiting to be written to a file.
+#[derive(Debug)]
+pub struct PendingSnapshot<S> {
+    state: Transactor<S>,
+}
+
+impl<S> PendingSnapshot<S>
+where
+    S: AccountStore + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// Write the snapshot in the given `format` to the file at `filepath`,
+    /// replacing the file if it exists. The serialization runs on a thread
+    /// meant for blocking work, so that it doesn't stall other tasks.
+    pub async fn write_to(self, filepath: PathBuf, format: SnapshotFormat) -> AppResult<()> {
+        let state = self.state;
+        let snapshot =
+            tokio::task::spawn_blocking(move || state.serialize_snapshot(format)).await??;
+        tokio::fs::write(filepath, snapshot).await?;
+        Ok(())
+    }
+}
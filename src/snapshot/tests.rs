@@ -0,0 +1,200 @@
+use super::*;
+use crate::core::{
+    ClientId, Currency, Transaction, TransactionId, TransactionQuery, TransactionType,
+};
+use crate::test_util::temp::TempPath;
+
+#[tokio::test]
+async fn snapshot_and_restore() -> AppResult<()> {
+    let path = TempPath::new("snapshot");
+    let transaction = |ttype, tid, amount: Option<&str>| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype,
+            cid: ClientId(1),
+            tid: TransactionId(tid),
+            amount: amount.map(Currency::from_str).transpose()?,
+        })
+    };
+    let mut transactor = Transactor::new();
+    for t in [
+        transaction(TransactionType::Deposit, 1, Some("2.5"))?,
+        transaction(TransactionType::Deposit, 2, Some("1.0"))?,
+        transaction(TransactionType::Dispute, 2, None)?,
+    ] {
+        transactor.process_row(t).await?;
+    }
+    transactor.snapshot_to(path.to_path_buf()).await?;
+    let mut restored = Transactor::new();
+    restored.restore_from(path.to_path_buf()).await?;
+    assert_eq!(restored.rows_processed, 3);
+    assert_eq!(restored.max_tid, Some(TransactionId(2)));
+    assert_eq!(
+        restored.account_summaries().collect::<Vec<_>>(),
+        transactor.account_summaries().collect::<Vec<_>>()
+    );
+    // NOTE: The open dispute survives the round trip, so it can be resolved.
+    restored
+        .process_row(transaction(TransactionType::Resolve, 2, None)?)
+        .await?;
+    let account = restored.accounts.get(&ClientId(1)).unwrap();
+    assert_eq!(account.available, Currency::from_str("3.5")?);
+    assert_eq!(account.held, Currency::ZERO);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_rebuilds_the_search_indexes() -> AppResult<()> {
+    let path = TempPath::new("indexed.snapshot");
+    let mut transactor = Transactor::new();
+    transactor
+        .process_row(Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("2.5")?),
+        })
+        .await?;
+    transactor.snapshot_to(path.to_path_buf()).await?;
+    let mut restored = Transactor::new();
+    restored.index_histories();
+    restored.restore_from(path.to_path_buf()).await?;
+    let query = TransactionQuery::default();
+    assert_eq!(restored.search(&query), transactor.search(&query));
+    assert_eq!(restored.search(&query).len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restore_hand_written_json() -> AppResult<()> {
+    let path = TempPath::new("snapshot.json");
+    let snapshot = r#"{
+        "accounts": {
+            "7": { "id": 7, "available": "1.5", "held": "0", "total": "1.5" }
+        },
+        "max_tid": 3
+    }"#;
+    std::fs::write(&path, snapshot)?;
+    let mut transactor = Transactor::new();
+    transactor.restore_from(path.to_path_buf()).await?;
+    transactor
+        .snapshot_to_with_format(path.to_path_buf(), SnapshotFormat::Json)
+        .await?;
+    let pretty = std::fs::read_to_string(&path)?;
+    assert!(pretty.contains("\n  \"accounts\": {\n"));
+    assert!(pretty.contains(&format!("\n  \"version\": {}\n", SNAPSHOT_VERSION)));
+    assert_eq!(transactor.max_tid, Some(TransactionId(3)));
+    let account = transactor.accounts.get(&ClientId(7)).unwrap();
+    assert_eq!(account.available, Currency::from_str("1.5")?);
+    assert!(!account.is_locked);
+    assert!(account.processed_transactions.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn closed_periods_survive_snapshots() -> AppResult<()> {
+    let path = TempPath::new("periods.snapshot");
+    let config = crate::Config {
+        sealed_transactions: crate::config::SealedTransactionPolicy::Adjust,
+        ..crate::Config::default()
+    };
+    let mut transactor = Transactor::with_config(config.clone());
+    let deposit = Transaction {
+        ttype: TransactionType::Deposit,
+        cid: ClientId(1),
+        tid: TransactionId(1),
+        amount: Some(Currency::from_str("2.0")?),
+    };
+    transactor.process_row(deposit).await?;
+    let _ = transactor.close_period(None);
+    let dispute = Transaction {
+        ttype: TransactionType::Dispute,
+        amount: None,
+        ..deposit
+    };
+    transactor.process_row(dispute).await?;
+    transactor.snapshot_to(path.to_path_buf()).await?;
+    let mut restored = Transactor::with_config(config);
+    restored.restore_from(path.to_path_buf()).await?;
+    let period = restored.close_period(None);
+    assert_eq!(period.number, 2);
+    assert_eq!(period.adjustments.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn forked_snapshot_is_written_while_processing() -> AppResult<()> {
+    let path = TempPath::new("fork.snapshot");
+    let deposit = |tid, amount: &str| -> AppResult<Transaction> {
+        Ok(Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(tid),
+            amount: Some(Currency::from_str(amount)?),
+        })
+    };
+    let mut transactor = Transactor::new();
+    transactor.process_row(deposit(1, "2.5")?).await?;
+    let expected = transactor.account_summaries().collect::<Vec<_>>();
+    let pending = transactor.fork_snapshot();
+    let writer = tokio::spawn(pending.write_to(path.to_path_buf(), SnapshotFormat::Json));
+    transactor.process_row(deposit(2, "1.0")?).await?;
+    writer.await??;
+    let mut restored = Transactor::new();
+    restored.restore_from(path.to_path_buf()).await?;
+    assert_eq!(restored.rows_processed, 1);
+    assert_eq!(restored.max_tid, Some(TransactionId(1)));
+    assert_eq!(restored.account_summaries().collect::<Vec<_>>(), expected);
+    Ok(())
+}
+
+// NOTE: A service is restarted from its last snapshot when it's killed, so
+//       every dispute that was open, or parked waiting for its deposit, must
+//       play out after a restart exactly as it would have without one.
+#[tokio::test]
+async fn disputes_resume_after_a_restart() -> AppResult<()> {
+    let (cid, tid) = (ClientId, TransactionId);
+    let amount = Currency::from_str;
+    let rows = [
+        Transaction::deposit(cid(1), tid(1), amount("5.0")?),
+        Transaction::dispute(cid(2), tid(3)),
+        Transaction::deposit(cid(1), tid(2), amount("1.0")?),
+        Transaction::dispute(cid(1), tid(1)),
+        Transaction::deposit(cid(2), tid(3), amount("2.0")?),
+        Transaction::resolve(cid(1), tid(1)),
+        Transaction::resolve(cid(2), tid(3)),
+        Transaction::chargeback(cid(2), tid(3)),
+        Transaction::deposit(cid(2), tid(4), amount("1.0")?),
+        Transaction::chargeback(cid(1), tid(1)),
+    ];
+    let config = crate::Config {
+        dispute_grace_rows: 5,
+        ..crate::Config::default()
+    };
+    let mut uninterrupted = Transactor::with_config(config.clone());
+    for &t in rows.iter() {
+        let _ = uninterrupted.process_row(t).await;
+    }
+    let expected: Vec<_> = uninterrupted.account_summaries().collect();
+    assert!(expected.iter().all(|summary| summary.locked));
+    for restart in 0..=rows.len() {
+        let path = TempPath::new(&format!("restart-{}.snapshot", restart));
+        let mut killed = Transactor::with_config(config.clone());
+        for &t in &rows[..restart] {
+            let _ = killed.process_row(t).await;
+        }
+        killed.snapshot_to(path.to_path_buf()).await?;
+        drop(killed);
+        let mut restarted = Transactor::with_config(config.clone());
+        restarted.restore_from(path.to_path_buf()).await?;
+        for &t in &rows[restart..] {
+            let _ = restarted.process_row(t).await;
+        }
+        assert_eq!(
+            restarted.account_summaries().collect::<Vec<_>>(),
+            expected,
+            "restarted after {} rows",
+            restart
+        );
+    }
+    Ok(())
+}
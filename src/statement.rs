@@ -0,0 +1,115 @@
+//! This module defines ingestion adapters for the statement formats that
+//! personal-finance software exports bank accounts to, i.e. OFX and QIF.
+//!
+//! A statement covers a single account, so all of its entries are mapped
+//! to the same client. Credits become deposits and debits withdrawals.
+//! Neither format carries numeric transaction ids, so the entries are
+//! numbered consecutively in statement order starting at a given id.
+//! Those ids can then be used to dispute individual entries.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{ClientId, Currency, Transaction, TransactionId, TransactionType};
+use crate::error::{AppError, AppResult};
+use std::convert::TryFrom;
+
+/// Parse the entries of a QIF statement into `Transaction`s for the client
+/// with id `cid`, numbered from `first_tid` onwards.
+///
+/// Each entry is a group of lines ended by a `^` line. Only the `T`
+/// (amount) line of an entry is used, and header lines are skipped.
+pub fn parse_qif(
+    input: &str,
+    cid: ClientId,
+    first_tid: TransactionId,
+) -> AppResult<Vec<Transaction>> {
+    let mut amounts = vec![];
+    let mut amount: Option<Currency> = None;
+    for line in input.lines().map(str::trim) {
+        match line.chars().next() {
+            Some('T') => amount = Some(parse_amount(&line[1..])?),
+            Some('^') => {
+                let entry = amounts.len();
+                amounts.push(
+                    amount
+                        .take()
+                        .ok_or(AppError::InvalidStatementEntry { entry })?,
+                );
+            }
+            _ => {}
+        }
+    }
+    to_transactions(amounts, cid, first_tid)
+}
+
+/// Parse the entries of an OFX statement into `Transaction`s for the client
+/// with id `cid`, numbered from `first_tid` onwards.
+///
+/// Both the SGML (OFX 1.x) and the XML (OFX 2.x) flavors are accepted,
+/// since only the `TRNAMT` of each `STMTTRN` aggregate is used, and
+/// closing tags of elements are ignored.
+pub fn parse_ofx(
+    input: &str,
+    cid: ClientId,
+    first_tid: TransactionId,
+) -> AppResult<Vec<Transaction>> {
+    let mut amounts = vec![];
+    let mut amount: Option<Currency> = None;
+    let mut in_entry = false;
+    for element in input.split('<').skip(1) {
+        let (tag, value) = element.split_at(element.find('>').unwrap_or(element.len()));
+        let value = value.trim_start_matches('>').trim();
+        match tag.trim().to_ascii_uppercase().as_str() {
+            "STMTTRN" => {
+                in_entry = true;
+                amount = None;
+            }
+            "TRNAMT" if in_entry => amount = Some(parse_amount(value)?),
+            "/STMTTRN" if in_entry => {
+                in_entry = false;
+                let entry = amounts.len();
+                amounts.push(
+                    amount
+                        .take()
+                        .ok_or(AppError::InvalidStatementEntry { entry })?,
+                );
+            }
+            _ => {}
+        }
+    }
+    to_transactions(amounts, cid, first_tid)
+}
+
+/// Parse a signed amount, which may contain thousands separators.
+fn parse_amount(amount: &str) -> AppResult<Currency> {
+    Currency::from_str(&amount.trim().replace(',', ""))
+}
+
+/// Convert the signed `amounts` of consecutive statement entries into
+/// deposits and withdrawals for the client with id `cid`.
+fn to_transactions(
+    amounts: Vec<Currency>,
+    cid: ClientId,
+    first_tid: TransactionId,
+) -> AppResult<Vec<Transaction>> {
+    let mut transactions = vec![];
+    for (entry, amount) in amounts.into_iter().enumerate() {
+        let tid = u32::try_from(entry)
+            .ok()
+            .and_then(|offset| first_tid.0.checked_add(offset))
+            .ok_or(AppError::InvalidStatementEntry { entry })?;
+        let ttype = if amount.0.is_sign_negative() {
+            TransactionType::Withdrawal
+        } else {
+            TransactionType::Deposit
+        };
+        transactions.push(Transaction {
+            ttype,
+            cid,
+            tid: TransactionId(tid),
+            amount: Some(Currency(amount.0.abs())),
+        });
+    }
+    Ok(transactions)
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+const QIF: &str = "!Type:Bank
+D03/03/2021
+T-1,379.00
+PCITY OF SPRINGFIELD
+^
+D03/04/2021
+T2,500.50
+PPAYCHECK
+^
+";
+
+const OFX: &str = "OFXHEADER:100
+DATA:OFXSGML
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20210303
+<TRNAMT>-1379.00
+<FITID>A1
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20210304
+<TRNAMT>2500.50
+<FITID>A2
+</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>1121.50</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+fn expected() -> AppResult<Vec<Transaction>> {
+    Ok(vec![
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(3),
+            tid: TransactionId(10),
+            amount: Some(Currency::from_str("1379.00")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(3),
+            tid: TransactionId(11),
+            amount: Some(Currency::from_str("2500.50")?),
+        },
+    ])
+}
+
+#[test]
+fn parse_qif_statement() -> AppResult<()> {
+    assert_eq!(parse_qif(QIF, ClientId(3), TransactionId(10))?, expected()?);
+    Ok(())
+}
+
+#[test]
+fn parse_ofx_statement() -> AppResult<()> {
+    assert_eq!(parse_ofx(OFX, ClientId(3), TransactionId(10))?, expected()?);
+    let xml = OFX
+        .replace("<TRNAMT>-1379.00", "<TRNAMT>-1379.00</TRNAMT>")
+        .replace("<TRNAMT>2500.50", "<TRNAMT>2500.50</TRNAMT>");
+    assert_eq!(
+        parse_ofx(&xml, ClientId(3), TransactionId(10))?,
+        expected()?
+    );
+    Ok(())
+}
+
+#[test]
+fn reject_entry_without_amount() {
+    let qif = QIF.replace("T2,500.50\n", "");
+    assert!(matches!(
+        parse_qif(&qif, ClientId(3), TransactionId(10)),
+        Err(AppError::InvalidStatementEntry { entry: 1 })
+    ));
+}
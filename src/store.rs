@@ -0,0 +1,87 @@
+//! This module defines the storage of the client accounts of a `Transactor`.
+//!
+//! A `Transactor` is generic over its `AccountStore`, so that persistent or
+//! concurrent stores can be plugged in without changing the engine itself.
+//! The default store is an in-memory `BTreeMap`.
+//!
+//! Stores backed by a database or another remote service can batch-load
+//! the accounts needed by the upcoming rows of a `CSV` input in
+//! `AccountStore::prefetch()`, rather than making a round trip per row.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{Account, ClientId};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Storage for client accounts, keyed by `ClientId`.
+pub trait AccountStore {
+    /// The account of the client with id `cid`, if there is one.
+    fn get(&self, cid: &ClientId) -> Option<&Account>;
+
+    /// The account of the client with id `cid`, if there is one, for
+    /// updating it in place.
+    fn get_mut(&mut self, cid: &ClientId) -> Option<&mut Account>;
+
+    /// Store `account`, replacing any account of the same client.
+    fn insert(&mut self, account: Account);
+
+    /// Returns `true` if there is an account for the client with id `cid`.
+    #[inline]
+    fn contains_key(&self, cid: &ClientId) -> bool {
+        self.get(cid).is_some()
+    }
+
+    /// The number of accounts in the store.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if there are no accounts in the store.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All accounts in the store, ordered by `ClientId`.
+    fn values(&self) -> Box<dyn Iterator<Item = &Account> + Send + '_>;
+
+    /// Remove the accounts for which `keep` returns `false`.
+    fn retain(&mut self, keep: &mut dyn FnMut(&Account) -> bool);
+
+    /// Load the accounts of the clients in `cids` ahead of their use, if
+    /// the store is backed by something slower than memory. This is a hint
+    /// only: the accounts may still be accessed if the store does nothing.
+    #[inline(always)]
+    fn prefetch(&mut self, _cids: &BTreeSet<ClientId>) {}
+}
+
+impl AccountStore for BTreeMap<ClientId, Account> {
+    #[inline(always)]
+    fn get(&self, cid: &ClientId) -> Option<&Account> {
+        BTreeMap::get(self, cid)
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, cid: &ClientId) -> Option<&mut Account> {
+        BTreeMap::get_mut(self, cid)
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, account: Account) {
+        let _ = BTreeMap::insert(self, account.id, account);
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    #[inline(always)]
+    fn values(&self) -> Box<dyn Iterator<Item = &Account> + Send + '_> {
+        Box::new(BTreeMap::values(self))
+    }
+
+    #[inline(always)]
+    fn retain(&mut self, keep: &mut dyn FnMut(&Account) -> bool) {
+        BTreeMap::retain(self, |_, account| keep(account))
+    }
+}
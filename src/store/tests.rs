@@ -0,0 +1,141 @@
+use super::*;
+use crate::config::Config;
+use crate::core::{Currency, Transaction, TransactionId, TransactionType, Transactor};
+use crate::error::AppResult;
+
+/// A `BTreeMap` store that logs the clients it was asked to prefetch.
+#[derive(Debug, Default)]
+struct PrefetchingStore {
+    accounts: BTreeMap<ClientId, Account>,
+    prefetched: Vec<Vec<ClientId>>,
+}
+
+impl AccountStore for PrefetchingStore {
+    fn get(&self, cid: &ClientId) -> Option<&Account> {
+        AccountStore::get(&self.accounts, cid)
+    }
+
+    fn get_mut(&mut self, cid: &ClientId) -> Option<&mut Account> {
+        AccountStore::get_mut(&mut self.accounts, cid)
+    }
+
+    fn insert(&mut self, account: Account) {
+        AccountStore::insert(&mut self.accounts, account)
+    }
+
+    fn len(&self) -> usize {
+        AccountStore::len(&self.accounts)
+    }
+
+    fn values(&self) -> Box<dyn Iterator<Item = &Account> + Send + '_> {
+        AccountStore::values(&self.accounts)
+    }
+
+    fn retain(&mut self, keep: &mut dyn FnMut(&Account) -> bool) {
+        AccountStore::retain(&mut self.accounts, keep)
+    }
+
+    fn prefetch(&mut self, cids: &BTreeSet<ClientId>) {
+        self.prefetched.push(cids.iter().copied().collect());
+    }
+}
+
+/// A store that keeps the accounts in a `Vec`, sorted by `ClientId`.
+#[derive(Debug, Default)]
+struct VecStore(Vec<Account>);
+
+impl AccountStore for VecStore {
+    fn get(&self, cid: &ClientId) -> Option<&Account> {
+        self.0.iter().find(|account| account.id == *cid)
+    }
+
+    fn get_mut(&mut self, cid: &ClientId) -> Option<&mut Account> {
+        self.0.iter_mut().find(|account| account.id == *cid)
+    }
+
+    fn insert(&mut self, account: Account) {
+        match self.0.binary_search_by_key(&account.id, |a| a.id) {
+            Ok(idx) => self.0[idx] = account,
+            Err(idx) => self.0.insert(idx, account),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn values(&self) -> Box<dyn Iterator<Item = &Account> + Send + '_> {
+        Box::new(self.0.iter())
+    }
+
+    fn retain(&mut self, keep: &mut dyn FnMut(&Account) -> bool) {
+        self.0.retain(|account| keep(account))
+    }
+}
+
+#[tokio::test]
+async fn plug_in_another_store() -> AppResult<()> {
+    let mut in_memory = Transactor::new();
+    let mut in_vec = Transactor::with_store(Config::default(), VecStore::default());
+    let transactions = vec![
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(2),
+            tid: TransactionId(1),
+            amount: Some(Currency::from_str("3.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(1),
+            tid: TransactionId(2),
+            amount: Some(Currency::from_str("1.0")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(2),
+            tid: TransactionId(3),
+            amount: Some(Currency::from_str("1.5")?),
+        },
+        Transaction {
+            ttype: TransactionType::Withdrawal,
+            cid: ClientId(3),
+            tid: TransactionId(4),
+            amount: Some(Currency::from_str("1.0")?),
+        },
+    ];
+    for transaction in transactions {
+        let expected = in_memory.process_row(transaction).await;
+        assert_eq!(in_vec.process_row(transaction).await, expected);
+    }
+    assert_eq!(in_vec.drop_empty_accounts(), 1);
+    assert_eq!(in_memory.drop_empty_accounts(), 1);
+    assert_eq!(in_vec.account_rows(), in_memory.account_rows());
+    assert_eq!(in_vec.audit(), vec![]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn prefetch_accounts_of_upcoming_rows() -> AppResult<()> {
+    let config = Config {
+        prefetch_rows: 2,
+        ..Config::default()
+    };
+    let mut transactor = Transactor::with_store(config, PrefetchingStore::default());
+    let csv = "type,client,tx,amount\n\
+               deposit,3,1,1.0\n\
+               deposit,1,2,1.0\n\
+               deposit,1,3,1.0\n\
+               deposit,1,4,1.0\n\
+               deposit,2,5,1.0\n";
+    transactor.process_csv_reader(csv.as_bytes()).await?;
+    assert_eq!(
+        transactor.accounts.prefetched,
+        vec![
+            vec![ClientId(1), ClientId(3)],
+            vec![ClientId(1)],
+            vec![ClientId(2)],
+        ]
+    );
+    assert_eq!(transactor.accounts.len(), 3);
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! This module defines a `TestHarness` for writing tests against the
+//! engine without spelling out every `Transaction` as a struct literal:
+//!
+//! ```ignore
+//! let mut harness = TestHarness::new();
+//! harness.deposit(1, 1, "10.0").await.withdraw(1, 2, "2.5").await;
+//! harness.expect_accepted().expect_available(1, "7.5");
+//! ```
+//!
+//! Transactions are processed with `Transactor::process_row()`, in the
+//! same way as the rows of a `CSV` file. The `expect_*` methods panic when
+//! the expectation isn't met, so they're meant for tests only.
+//!
+//! Downstream crates can use the harness by enabling the `test-util` feature.
+
+#[cfg(test)]
+mod tests;
+
+pub mod corpus;
+pub mod temp;
+
+use crate::config::Config;
+use crate::core::{Account, ClientId, Currency, Transaction, Transactor};
+use crate::error::{TransactionError, TransactionResult};
+
+/// A `Transactor` with fluent helpers to feed it transactions, and to
+/// check the resulting account state.
+#[derive(Debug)]
+pub struct TestHarness {
+    transactor: Transactor,
+    /// The result of the most recently processed transaction
+    last_result: Option<TransactionResult<()>>,
+}
+
+impl TestHarness {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    #[inline(always)]
+    pub fn with_config(config: Config) -> Self {
+        Self {
+            transactor: Transactor::with_config(config),
+            last_result: None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn transactor(&self) -> &Transactor {
+        &self.transactor
+    }
+
+    #[inline(always)]
+    pub fn transactor_mut(&mut self) -> &mut Transactor {
+        &mut self.transactor
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> Transactor {
+        self.transactor
+    }
+
+    /// Process a deposit of `amount` into the account of client `cid`.
+    pub async fn deposit(&mut self, cid: u16, tid: u32, amount: &str) -> &mut Self {
+        let t = Transaction::deposit(cid.into(), tid.into(), parse_amount(amount));
+        self.process(t).await
+    }
+
+    /// Process a withdrawal of `amount` from the account of client `cid`.
+    pub async fn withdraw(&mut self, cid: u16, tid: u32, amount: &str) -> &mut Self {
+        let t = Transaction::withdrawal(cid.into(), tid.into(), parse_amount(amount));
+        self.process(t).await
+    }
+
+    /// Process a dispute of the transaction `tid` of client `cid`.
+    pub async fn dispute(&mut self, cid: u16, tid: u32) -> &mut Self {
+        self.process(Transaction::dispute(cid.into(), tid.into()))
+            .await
+    }
+
+    /// Process a resolve of the transaction `tid` of client `cid`.
+    pub async fn resolve(&mut self, cid: u16, tid: u32) -> &mut Self {
+        self.process(Transaction::resolve(cid.into(), tid.into()))
+            .await
+    }
+
+    /// Process a chargeback of the transaction `tid` of client `cid`.
+    pub async fn chargeback(&mut self, cid: u16, tid: u32) -> &mut Self {
+        self.process(Transaction::chargeback(cid.into(), tid.into()))
+            .await
+    }
+
+    async fn process(&mut self, t: Transaction) -> &mut Self {
+        self.last_result = Some(self.transactor.process_row(t).await);
+        self
+    }
+
+    /// The result of the most recently processed transaction.
+    #[track_caller]
+    pub fn last_result(&self) -> &TransactionResult<()> {
+        self.last_result
+            .as_ref()
+            .expect("no transaction has been processed yet")
+    }
+
+    /// Expect the most recently processed transaction to be accepted.
+    #[track_caller]
+    pub fn expect_accepted(&mut self) -> &mut Self {
+        if let Err(e) = self.last_result() {
+            panic!(
+                "expected the last transaction to be accepted, but got {:?}",
+                e
+            );
+        }
+        self
+    }
+
+    /// Expect the most recently processed transaction to be rejected
+    /// with `error`.
+    #[track_caller]
+    pub fn expect_rejected(&mut self, error: TransactionError) -> &mut Self {
+        assert_eq!(self.last_result(), &Err(error), "last transaction");
+        self
+    }
+
+    /// Expect the available funds of client `cid` to equal `amount`.
+    #[track_caller]
+    pub fn expect_available(&mut self, cid: u16, amount: &str) -> &mut Self {
+        let actual = self.account(cid).available;
+        assert_eq!(
+            actual,
+            parse_amount(amount),
+            "available funds of client {}",
+            cid
+        );
+        self
+    }
+
+    /// Expect the held funds of client `cid` to equal `amount`.
+    #[track_caller]
+    pub fn expect_held(&mut self, cid: u16, amount: &str) -> &mut Self {
+        let actual = self.account(cid).held;
+        assert_eq!(actual, parse_amount(amount), "held funds of client {}", cid);
+        self
+    }
+
+    /// Expect the total funds of client `cid` to equal `amount`.
+    #[track_caller]
+    pub fn expect_total(&mut self, cid: u16, amount: &str) -> &mut Self {
+        let actual = self.account(cid).total;
+        assert_eq!(
+            actual,
+            parse_amount(amount),
+            "total funds of client {}",
+            cid
+        );
+        self
+    }
+
+    /// Expect the account of client `cid` to be locked or not.
+    #[track_caller]
+    pub fn expect_locked(&mut self, cid: u16, locked: bool) -> &mut Self {
+        let actual = self.account(cid).is_locked;
+        assert_eq!(actual, locked, "locked state of client {}", cid);
+        self
+    }
+
+    /// Expect client `cid` to have no account.
+    #[track_caller]
+    pub fn expect_no_account(&mut self, cid: u16) -> &mut Self {
+        let account = self.transactor.accounts.get(&ClientId::from(cid));
+        assert!(account.is_none(), "client {} has an account", cid);
+        self
+    }
+
+    #[track_caller]
+    fn account(&self, cid: u16) -> &Account {
+        self.transactor
+            .accounts
+            .get(&ClientId::from(cid))
+            .unwrap_or_else(|| panic!("client {} has no account", cid))
+    }
+}
+
+impl Default for TestHarness {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[track_caller]
+fn parse_amount(amount: &str) -> Currency {
+    Currency::from_str(amount).unwrap_or_else(|_| panic!("invalid amount '{}'", amount))
+}
@@ -0,0 +1,369 @@
+//! This module generates adversarial `CSV` inputs, along with the outcome
+//! that processing them is expected to have.
+//!
+//! Each `Pattern` targets input that is known to be tricky, such as
+//! disputes that arrive before the transaction they refer to, or amounts
+//! with more significant digits than an `f64` holds. The expected outcome
+//! of a `Case` is computed by a simple model of the accounts while the
+//! input is generated, rather than by the engine itself, so that the two
+//! can be checked against each other with `Case::check()`.
+//!
+//! Generation is deterministic for a given seed, so a failing case can be
+//! reproduced, and written to fixture files with `Case::write_fixture()`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::{Currency, Transactor};
+use crate::error::AppResult;
+use rust_decimal::prelude::Decimal;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A kind of input that is known to be tricky to process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Disputes and resolves that arrive before the deposit they refer to.
+    DisputeBeforeDeposit,
+    /// Deposits of different clients that share a transaction id, and
+    /// disputes of that id by each of the clients, and by one without it.
+    DuplicateTidsAcrossClients,
+    /// Amounts with up to 24 significant digits.
+    HugeAmounts,
+    /// Many clients whose deposits, withdrawals and disputes are densely
+    /// interleaved.
+    DenseInterleaving,
+    /// An extra column with quoted non-ASCII text, commas and quotes.
+    UnicodeInExtraColumns,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 5] = [
+        Pattern::DisputeBeforeDeposit,
+        Pattern::DuplicateTidsAcrossClients,
+        Pattern::HugeAmounts,
+        Pattern::DenseInterleaving,
+        Pattern::UnicodeInExtraColumns,
+    ];
+
+    #[rustfmt::skip]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::DisputeBeforeDeposit       => "dispute_before_deposit",
+            Pattern::DuplicateTidsAcrossClients => "duplicate_tids_across_clients",
+            Pattern::HugeAmounts                => "huge_amounts",
+            Pattern::DenseInterleaving          => "dense_interleaving",
+            Pattern::UnicodeInExtraColumns      => "unicode_in_extra_columns",
+        }
+    }
+}
+
+/// A generated `CSV` input, and the outcome of processing it with a
+/// `Transactor` with the default `Config`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    pub pattern: Pattern,
+    pub seed: u64,
+    pub csv: String,
+    /// The account summary, as written by `Transactor::write_output()`.
+    pub expected_output: String,
+    /// The number of rows that are to be rejected.
+    pub expected_rejections: usize,
+}
+
+impl Case {
+    /// Generate the case of `pattern` for `seed`.
+    pub fn generate(pattern: Pattern, seed: u64) -> Self {
+        let mut model = Model::new(seed, pattern == Pattern::UnicodeInExtraColumns);
+        match pattern {
+            Pattern::DisputeBeforeDeposit => model.dispute_before_deposit(),
+            Pattern::DuplicateTidsAcrossClients => model.duplicate_tids_across_clients(),
+            Pattern::HugeAmounts => model.huge_amounts(),
+            Pattern::DenseInterleaving | Pattern::UnicodeInExtraColumns => {
+                model.dense_interleaving()
+            }
+        }
+        Self {
+            pattern,
+            seed,
+            expected_output: model.expected_output(),
+            expected_rejections: model.rejections,
+            csv: model.csv,
+        }
+    }
+
+    /// Generate the cases of all patterns for `seed`.
+    pub fn generate_all(seed: u64) -> Vec<Self> {
+        Pattern::ALL
+            .iter()
+            .map(|&pattern| Self::generate(pattern, seed))
+            .collect()
+    }
+
+    /// The stem of the names of the fixture files of this case.
+    pub fn name(&self) -> String {
+        format!("{}-{}", self.pattern.name(), self.seed)
+    }
+
+    /// Process the input, and return the account summary and the number
+    /// of rejected rows.
+    pub async fn run(&self) -> AppResult<(String, usize)> {
+        let mut transactor = Transactor::new();
+        transactor.process_csv_reader(self.csv.as_bytes()).await?;
+        let mut output = vec![];
+        transactor.write_output(&mut output).await?;
+        let output = String::from_utf8_lossy(&output).into_owned();
+        Ok((output, transactor.rejected().count()))
+    }
+
+    /// Process the input, and panic if the outcome isn't the expected one.
+    pub async fn check(&self) -> AppResult<()> {
+        let (output, rejections) = self.run().await?;
+        assert_eq!(
+            output,
+            self.expected_output,
+            "output of case {}",
+            self.name()
+        );
+        assert_eq!(
+            rejections,
+            self.expected_rejections,
+            "rejections of case {}",
+            self.name()
+        );
+        Ok(())
+    }
+
+    /// Write the input to `<name>.csv` and the expected account summary
+    /// to `<name>.expected.csv` in the directory `dir`.
+    pub async fn write_fixture(&self, dir: &Path) -> AppResult<()> {
+        let name = self.name();
+        tokio::fs::write(dir.join(format!("{}.csv", name)), &self.csv).await?;
+        tokio::fs::write(
+            dir.join(format!("{}.expected.csv", name)),
+            &self.expected_output,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// The funds of a client, as the engine is expected to leave them.
+#[derive(Clone, Debug, Default)]
+struct ModelAccount {
+    available: Decimal,
+    held: Decimal,
+    /// The amounts of the deposits, and the dispute stage they're in
+    deposits: BTreeMap<u32, (Decimal, Stage)>,
+}
+
+/// The dispute stage of a deposit. Only processed deposits can be disputed,
+/// so a resolved deposit can't be disputed again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Processed,
+    Disputed,
+    Resolved,
+}
+
+/// Generates the rows of a case, and keeps track of their expected effect.
+struct Model {
+    rng: u64,
+    csv: String,
+    accounts: BTreeMap<u16, ModelAccount>,
+    rejections: usize,
+    next_tid: u32,
+    /// Whether rows have a `memo` column with tricky text
+    memos: bool,
+}
+
+impl Model {
+    fn new(seed: u64, memos: bool) -> Self {
+        let header = if memos {
+            "type,client,tx,amount,memo\n"
+        } else {
+            "type,client,tx,amount\n"
+        };
+        Self {
+            // NOTE: The state of xorshift must not be 0.
+            rng: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            csv: String::from(header),
+            accounts: BTreeMap::new(),
+            rejections: 0,
+            next_tid: 1,
+            memos,
+        }
+    }
+
+    /// A pseudo-random number in `0..bound`, from xorshift64.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng % bound
+    }
+
+    /// A pseudo-random amount with 4 decimal places, below `whole`.
+    fn amount(&mut self, whole: u64) -> Decimal {
+        let units = 1 + self.below(whole * 10_000 - 1);
+        Decimal::new(units as i64, 4)
+    }
+
+    fn tid(&mut self) -> u32 {
+        self.next_tid += 1;
+        self.next_tid - 1
+    }
+
+    fn row(&mut self, ttype: &str, cid: u16, tid: u32, amount: Option<Decimal>) {
+        let amount = amount.map(|amount| amount.to_string()).unwrap_or_default();
+        self.csv
+            .push_str(&format!("{},{},{},{}", ttype, cid, tid, amount));
+        if self.memos {
+            const MEMOS: [&str; 4] = ["🦑 squid", "naïve, café", "\"\"quoted\"\"", "日本語"];
+            let memo = MEMOS[self.below(MEMOS.len() as u64) as usize];
+            self.csv.push_str(&format!(",\"{}\"", memo));
+        }
+        self.csv.push('\n');
+    }
+
+    // NOTE: Every row opens an account for its client, even a rejected one.
+
+    fn deposit(&mut self, cid: u16, tid: u32, amount: Decimal) {
+        self.row("deposit", cid, tid, Some(amount));
+        let account = self.accounts.entry(cid).or_default();
+        account.available += amount;
+        account.deposits.insert(tid, (amount, Stage::Processed));
+    }
+
+    fn withdraw(&mut self, cid: u16, tid: u32, amount: Decimal) {
+        self.row("withdrawal", cid, tid, Some(amount));
+        let account = self.accounts.entry(cid).or_default();
+        if account.available >= amount {
+            account.available -= amount;
+        } else {
+            self.rejections += 1;
+        }
+    }
+
+    fn dispute(&mut self, cid: u16, tid: u32) {
+        self.row("dispute", cid, tid, None);
+        let account = self.accounts.entry(cid).or_default();
+        match account.deposits.get_mut(&tid) {
+            Some((amount, stage)) if *stage == Stage::Processed => {
+                account.available -= *amount;
+                account.held += *amount;
+                *stage = Stage::Disputed;
+            }
+            _ => self.rejections += 1,
+        }
+    }
+
+    fn resolve(&mut self, cid: u16, tid: u32) {
+        self.row("resolve", cid, tid, None);
+        let account = self.accounts.entry(cid).or_default();
+        match account.deposits.get_mut(&tid) {
+            Some((amount, stage)) if *stage == Stage::Disputed => {
+                account.available += *amount;
+                account.held -= *amount;
+                *stage = Stage::Resolved;
+            }
+            _ => self.rejections += 1,
+        }
+    }
+
+    fn dispute_before_deposit(&mut self) {
+        for cid in 1..=1 + self.below(8) as u16 {
+            let tid = self.tid();
+            let amount = self.amount(100);
+            self.dispute(cid, tid);
+            self.resolve(cid, tid);
+            self.deposit(cid, tid, amount);
+            if self.below(2) == 0 {
+                self.dispute(cid, tid);
+            }
+        }
+    }
+
+    fn duplicate_tids_across_clients(&mut self) {
+        for _ in 0..1 + self.below(8) {
+            let tid = self.tid();
+            let clients = 2 + self.below(3) as u16;
+            for cid in 1..=clients {
+                let amount = self.amount(100);
+                self.deposit(cid, tid, amount);
+            }
+            let disputed = 1 + self.below(clients as u64) as u16;
+            self.dispute(disputed, tid);
+            self.dispute(clients + 1, tid);
+        }
+    }
+
+    fn huge_amounts(&mut self) {
+        // NOTE: 20 whole digits and 4 decimal places, so that a few of them
+        //       add up without exceeding the range of a `Decimal`.
+        let huge = Decimal::new(999_999_999_999_999_999, 0) * Decimal::new(100, 0);
+        for cid in 1..=1 + self.below(4) as u16 {
+            for _ in 0..1 + self.below(4) {
+                let tid = self.tid();
+                let amount = huge - self.amount(1_000);
+                self.deposit(cid, tid, amount);
+            }
+            let tid = self.tid();
+            let amount = huge * Decimal::new(5, 0);
+            self.withdraw(cid, tid, amount);
+            let tid = self.tid();
+            let amount = self.amount(1_000);
+            self.withdraw(cid, tid, amount);
+        }
+    }
+
+    fn dense_interleaving(&mut self) {
+        let clients = 2 + self.below(30) as u16;
+        for _ in 0..200 + self.below(300) {
+            let cid = 1 + self.below(clients as u64) as u16;
+            let deposits: Vec<u32> = self
+                .accounts
+                .get(&cid)
+                .map(|account| account.deposits.keys().copied().collect())
+                .unwrap_or_default();
+            match self.below(10) {
+                0..=3 => {
+                    let (tid, amount) = (self.tid(), self.amount(50));
+                    self.deposit(cid, tid, amount);
+                }
+                4..=6 => {
+                    let (tid, amount) = (self.tid(), self.amount(60));
+                    self.withdraw(cid, tid, amount);
+                }
+                7 | 8 if !deposits.is_empty() => {
+                    let tid = deposits[self.below(deposits.len() as u64) as usize];
+                    self.dispute(cid, tid);
+                }
+                _ if !deposits.is_empty() => {
+                    let tid = deposits[self.below(deposits.len() as u64) as usize];
+                    self.resolve(cid, tid);
+                }
+                _ => {
+                    // NOTE: A dispute of a transaction that never existed.
+                    let tid = self.tid();
+                    self.dispute(cid, tid);
+                }
+            }
+        }
+    }
+
+    fn expected_output(&self) -> String {
+        let mut output = String::from("client,available,held,total,locked\n");
+        for (cid, account) in self.accounts.iter() {
+            let total = account.available + account.held;
+            output.push_str(&format!(
+                "{},{},{},{},false\n",
+                cid,
+                Currency(account.available).to_output_string(),
+                Currency(account.held).to_output_string(),
+                Currency(total).to_output_string(),
+            ));
+        }
+        output
+    }
+}
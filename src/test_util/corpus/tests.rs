@@ -0,0 +1,35 @@
+use super::*;
+use crate::test_util::temp::TempPath;
+
+#[tokio::test]
+async fn generated_cases_match_the_model() -> AppResult<()> {
+    for seed in 1..=20 {
+        for case in Case::generate_all(seed) {
+            case.check().await?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn generation_is_deterministic() {
+    for pattern in Pattern::ALL.iter() {
+        assert_eq!(Case::generate(*pattern, 7), Case::generate(*pattern, 7));
+    }
+    assert_ne!(
+        Case::generate(Pattern::DenseInterleaving, 7).csv,
+        Case::generate(Pattern::DenseInterleaving, 8).csv
+    );
+}
+
+#[tokio::test]
+async fn write_fixture_files() -> AppResult<()> {
+    let dir = TempPath::dir("corpus")?;
+    let case = Case::generate(Pattern::HugeAmounts, 3);
+    case.write_fixture(&dir).await?;
+    let csv = std::fs::read_to_string(dir.join("huge_amounts-3.csv"))?;
+    let expected = std::fs::read_to_string(dir.join("huge_amounts-3.expected.csv"))?;
+    assert_eq!(csv, case.csv);
+    assert_eq!(expected, case.expected_output);
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! This module defines `TempPath`, a unique path in the temporary directory
+//! for the files and directories that a test writes.
+//!
+//! Tests run concurrently, both within and across test binaries, so each
+//! `TempPath` is made unique by the process id and a counter. Whatever is
+//! at the path is removed once the `TempPath` is dropped, whether the test
+//! passed or not.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A unique path in the temporary directory that is removed on drop.
+#[derive(Debug)]
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    /// A unique path that ends in `name`, e.g. `accounts.csv.gz`. Nothing
+    /// is created at it.
+    pub fn new(name: &str) -> Self {
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let unique = format!("giant-squid-{}-{}-{}", std::process::id(), count, name);
+        Self {
+            path: std::env::temp_dir().join(unique),
+        }
+    }
+
+    /// A unique path that ends in `name`, with an empty directory at it.
+    pub fn dir(name: &str) -> std::io::Result<Self> {
+        let dir = Self::new(name);
+        std::fs::create_dir_all(&dir.path)?;
+        Ok(dir)
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        // NOTE: The path may not exist, e.g. if the test failed early.
+        let _ = if self.path.is_dir() {
+            std::fs::remove_dir_all(&self.path)
+        } else {
+            std::fs::remove_file(&self.path)
+        };
+    }
+}
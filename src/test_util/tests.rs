@@ -0,0 +1,46 @@
+use super::*;
+
+#[tokio::test]
+async fn fluent_deposit_and_withdraw() {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(1, 1, "10.0")
+        .await
+        .withdraw(1, 2, "2.5")
+        .await
+        .expect_accepted()
+        .expect_available(1, "7.5")
+        .expect_held(1, "0")
+        .expect_total(1, "7.5")
+        .expect_locked(1, false)
+        .expect_no_account(2);
+    harness
+        .withdraw(1, 3, "100.0")
+        .await
+        .expect_rejected(TransactionError::AccountHasInsufficientFundsAvailable {
+            cid: ClientId(1),
+        })
+        .expect_available(1, "7.5");
+}
+
+#[tokio::test]
+async fn fluent_dispute_and_chargeback() {
+    let mut harness = TestHarness::new();
+    harness.deposit(2, 1, "3.0").await.dispute(2, 1).await;
+    harness.expect_available(2, "0").expect_held(2, "3.0");
+    harness.resolve(2, 1).await.chargeback(2, 1).await;
+    harness
+        .expect_accepted()
+        .expect_total(2, "0")
+        .expect_locked(2, true);
+}
+
+#[tokio::test]
+#[should_panic(expected = "available funds of client 1")]
+async fn failed_expectation_panics() {
+    let mut harness = TestHarness::new();
+    harness
+        .deposit(1, 1, "1.0")
+        .await
+        .expect_available(1, "2.0");
+}
@@ -0,0 +1,170 @@
+//! This module defines an optional trailer for the account summary, so that
+//! truncated or corrupted summaries can be detected by their consumers.
+//!
+//! The trailer is the last line of the summary. It's a `#`-prefixed comment,
+//! so `CSV` readers that skip comments can still read the summary:
+//!
+//! `#trailer rows=2 hashes=<client>,<available>,<held>,<total>,<locked> version=1.0.0`
+//!
+//! Each hash is the 64-bit FNV-1a hash of the values in its column, in row
+//! order, each followed by a newline. FNV-1a is used rather than the hasher
+//! of the standard library because its output is stable across platforms
+//! and Rust versions.
+
+#[cfg(test)]
+mod tests;
+
+use crate::core::Transactor;
+use crate::error::{AppError, AppResult};
+use crate::store::AccountStore;
+use std::fmt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+const TRAILER_PREFIX: &str = "#trailer ";
+const NUM_COLUMNS: usize = 5;
+
+/// A fingerprint of the rows of an account summary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trailer {
+    /// The number of rows, excluding the header.
+    pub rows: usize,
+    /// The hash of each column.
+    pub hashes: [u64; NUM_COLUMNS],
+    /// The version of the engine that produced the summary.
+    pub version: String,
+}
+
+impl Trailer {
+    /// Compute the trailer for the rows of an account summary, as produced
+    /// by this version of the engine.
+    pub fn compute<'r, I, R>(records: I) -> Self
+    where
+        I: IntoIterator<Item = &'r R>,
+        R: AsRef<[String]> + 'r,
+    {
+        let mut rows = 0;
+        let mut hashes = [FNV_OFFSET_BASIS; NUM_COLUMNS];
+        for record in records {
+            rows += 1;
+            for (hash, field) in hashes.iter_mut().zip(record.as_ref()) {
+                *hash = fnv1a(*hash, field.as_bytes());
+                *hash = fnv1a(*hash, b"\n");
+            }
+        }
+        Self {
+            rows,
+            hashes,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Parse a trailer line. Returns `None` if `line` isn't a trailer.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut rows = None;
+        let mut hashes = None;
+        let mut version = None;
+        for part in line.strip_prefix(TRAILER_PREFIX)?.split_whitespace() {
+            let (key, value) = part.split_at(part.find('=')?);
+            let value = &value[1..];
+            match key {
+                "rows" => rows = value.parse().ok(),
+                "hashes" => {
+                    let parsed: Vec<u64> = value
+                        .split(',')
+                        .map(|hash| u64::from_str_radix(hash, 16).ok())
+                        .collect::<Option<_>>()?;
+                    let mut array = [0; NUM_COLUMNS];
+                    if parsed.len() != NUM_COLUMNS {
+                        return None;
+                    }
+                    array.copy_from_slice(&parsed);
+                    hashes = Some(array);
+                }
+                "version" => version = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Some(Self {
+            rows: rows?,
+            hashes: hashes?,
+            version: version?,
+        })
+    }
+}
+
+impl fmt::Display for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hashes: Vec<String> = self.hashes.iter().map(|h| format!("{:016x}", h)).collect();
+        write!(
+            f,
+            "{}rows={} hashes={} version={}",
+            TRAILER_PREFIX,
+            self.rows,
+            hashes.join(","),
+            self.version
+        )
+    }
+}
+
+/// Verify the trailer of an account summary, if it has one. Returns the
+/// trailer, or `None` if there is none. The engine version in the trailer
+/// is informational only, and doesn't need to match this one.
+pub fn verify_trailer(summary: &str) -> AppResult<Option<Trailer>> {
+    let mut lines: Vec<&str> = summary.lines().filter(|l| !l.trim().is_empty()).collect();
+    let last = match lines.pop() {
+        Some(last) if last.starts_with(TRAILER_PREFIX) => last,
+        _ => return Ok(None),
+    };
+    let trailer = Trailer::parse(last).ok_or_else(|| AppError::MalformedTrailer {
+        line: last.to_string(),
+    })?;
+    let records: Vec<Vec<String>> = lines
+        .iter()
+        .skip(1) // The header
+        .map(|line| {
+            line.split(',')
+                .map(|field| field.trim().to_string())
+                .collect()
+        })
+        .collect();
+    let actual = Trailer {
+        version: trailer.version.clone(),
+        ..Trailer::compute(records.iter())
+    };
+    if actual != trailer {
+        return Err(AppError::TrailerMismatch {
+            expected: trailer.to_string(),
+            actual: actual.to_string(),
+        });
+    }
+    Ok(Some(trailer))
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Write the account summary as `CSV` to `writer`, followed by
+    /// a trailer line.
+    pub async fn write_output_with_trailer<W>(&self, writer: W) -> AppResult<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let records: Vec<[String; NUM_COLUMNS]> =
+            self.account_summaries().map(|s| s.fields()).collect();
+        let mut writer = self.serialize_output(writer).await?;
+        let trailer = format!("{}\n", Trailer::compute(records.iter()));
+        writer.write_all(trailer.as_bytes()).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Continue the FNV-1a `hash` over `bytes`.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
@@ -0,0 +1,60 @@
+use super::*;
+use crate::compare::read_account_rows;
+use crate::core::{ClientId, Currency, Transaction, TransactionId, TransactionType};
+
+async fn summary_with_trailer() -> AppResult<String> {
+    let mut transactor = Transactor::new();
+    for (cid, tid) in [(1, 1), (2, 2)].iter() {
+        let deposit = Transaction {
+            ttype: TransactionType::Deposit,
+            cid: ClientId(*cid),
+            tid: TransactionId(*tid),
+            amount: Some(Currency::from_str("1.5")?),
+        };
+        transactor.process_transaction(deposit).await?;
+    }
+    let mut output = vec![];
+    transactor.write_output_with_trailer(&mut output).await?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[tokio::test]
+async fn verify_intact_summary() -> AppResult<()> {
+    let summary = summary_with_trailer().await?;
+    let trailer = verify_trailer(&summary)?.unwrap();
+    assert_eq!(trailer.rows, 2);
+    assert_eq!(trailer.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(read_account_rows(summary.as_bytes()).await?.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn detect_truncated_summary() -> AppResult<()> {
+    let summary = summary_with_trailer().await?;
+    let truncated: Vec<&str> = summary.lines().filter(|l| !l.starts_with("2,")).collect();
+    let truncated = truncated.join("\n");
+    assert!(matches!(
+        verify_trailer(&truncated),
+        Err(AppError::TrailerMismatch { .. })
+    ));
+    assert!(read_account_rows(truncated.as_bytes()).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn detect_corrupted_summary() -> AppResult<()> {
+    let summary = summary_with_trailer().await?;
+    let corrupted = summary.replacen("1.5000", "1.4000", 1);
+    assert!(matches!(
+        verify_trailer(&corrupted),
+        Err(AppError::TrailerMismatch { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn summary_without_trailer() -> AppResult<()> {
+    let summary = "client,available,held,total,locked\n1,1.0000,0.0000,1.0000,false\n";
+    assert_eq!(verify_trailer(summary)?, None);
+    Ok(())
+}
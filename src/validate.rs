@@ -0,0 +1,271 @@
+//! This module defines a validate-only pass over `CSV` input, which checks
+//! every row without applying any of them to the accounts.
+//!
+//! Unlike processing, where the first malformed row is fatal, validation
+//! reads the input to the end and collects every issue it finds, along with
+//! its line number. A `Validator` can be fed multiple inputs in order; its
+//! duplicate transaction id check spans all of them, as processing does.
+
+#[cfg(test)]
+mod tests;
+
+use crate::compression::{decompress, Compression};
+use crate::config::Config;
+use crate::core::{ClientId, Currency, TransactionId, TransactionType, Transactor};
+use crate::error::AppResult;
+use crate::store::AccountStore;
+use csv_async::{AsyncReaderBuilder, StringRecord};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use tokio::io::AsyncRead;
+use tokio_stream::StreamExt;
+
+/// A problem with a row of `CSV` input, found by a `Validator`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The row can't be read as `CSV` at all, e.g. because it isn't valid
+    /// UTF-8.
+    MalformedRow { line: u64, reason: String },
+    /// The `column` of the row is missing, or holds a `value` that can't be
+    /// parsed.
+    InvalidField {
+        line: u64,
+        column: &'static str,
+        value: String,
+    },
+    /// The type of the row is neither a transaction type nor an alias of
+    /// one.
+    UnknownTransactionType { line: u64, name: String },
+    /// The row is a deposit or a withdrawal without an amount.
+    MissingAmount { line: u64, tid: TransactionId },
+    /// The row is a deposit or a withdrawal of client `cid` with the same
+    /// `tid` as the one on line `first_line`.
+    DuplicateTransactionId {
+        line: u64,
+        cid: ClientId,
+        tid: TransactionId,
+        first_line: u64,
+    },
+}
+
+impl ValidationIssue {
+    /// The line number of the row with the issue.
+    #[rustfmt::skip]
+    pub fn line(&self) -> u64 {
+        match self {
+            Self::MalformedRow { line, .. }           => *line,
+            Self::InvalidField { line, .. }           => *line,
+            Self::UnknownTransactionType { line, .. } => *line,
+            Self::MissingAmount { line, .. }          => *line,
+            Self::DuplicateTransactionId { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line())?;
+        match self {
+            Self::MalformedRow { reason, .. } => write!(f, "malformed row: {}", reason),
+            Self::InvalidField { column, value, .. } => {
+                write!(f, "invalid {} `{}`", column, value)
+            }
+            Self::UnknownTransactionType { name, .. } => {
+                write!(f, "unknown transaction type `{}`", name)
+            }
+            Self::MissingAmount { tid, .. } => write!(f, "tx {} has no amount", tid),
+            Self::DuplicateTransactionId {
+                cid,
+                tid,
+                first_line,
+                ..
+            } => write!(
+                f,
+                "tx {} of client {} was first seen on line {}",
+                tid, cid, first_line
+            ),
+        }
+    }
+}
+
+/// The outcome of validating one or more inputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The number of rows read, including those with issues.
+    pub rows: u64,
+    /// The issues found, in the order of the rows they were found in.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    #[inline(always)]
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Checks `CSV` inputs for the problems that processing would reject or
+/// fail on, without processing them.
+#[derive(Debug, Default)]
+pub struct Validator {
+    aliases: BTreeMap<String, TransactionType>,
+    compression: Option<Compression>,
+    /// The line numbers of the deposits and withdrawals seen so far.
+    /// `TransactionId`s are only unique per client.
+    first_lines: BTreeMap<(ClientId, TransactionId), u64>,
+    report: ValidationReport,
+}
+
+impl Validator {
+    /// A `Validator` that accepts the transaction type aliases of `config`,
+    /// and reads files with its compression.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            aliases: config.type_aliases.clone(),
+            compression: config.compression,
+            ..Self::default()
+        }
+    }
+
+    /// Validate the `CSV` file at `filepath`.
+    pub async fn validate_csv_file(&mut self, filepath: PathBuf) -> AppResult<()> {
+        let file = tokio::fs::File::open(&filepath).await?;
+        let compression = self
+            .compression
+            .unwrap_or_else(|| Compression::detect(&filepath));
+        self.validate_csv_reader(decompress(file, compression))
+            .await
+    }
+
+    /// Validate the `CSV` data produced by `reader`. It's read in the same
+    /// way as by `Transactor::process_csv_reader()`.
+    pub async fn validate_csv_reader<R>(&mut self, reader: R) -> AppResult<()>
+    where
+        R: AsyncRead + Unpin + Send + Sync,
+    {
+        let mut reader = AsyncReaderBuilder::new()
+            .trim(csv_async::Trim::All)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .create_reader(reader);
+        let headers = reader.headers().await?.clone();
+        let column = |name: &str| headers.iter().position(|header| header == name);
+        let columns = [
+            column("type"),
+            column("client"),
+            column("tx"),
+            column("amount"),
+        ];
+        let mut records = reader.into_records();
+        while let Some(record) = records.next().await {
+            self.report.rows += 1;
+            match record {
+                Ok(record) => self.validate_record(&record, columns),
+                Err(e) => {
+                    let line = e.position().map_or(0, csv_async::Position::line);
+                    let reason = e.to_string();
+                    let issue = ValidationIssue::MalformedRow { line, reason };
+                    self.report.issues.push(issue);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate a single `record`, given the indices of the `type`,
+    /// `client`, `tx` and `amount` columns.
+    fn validate_record(&mut self, record: &StringRecord, columns: [Option<usize>; 4]) {
+        let line = record.position().map_or(0, csv_async::Position::line);
+        let field = |idx: Option<usize>| idx.and_then(|idx| record.get(idx)).unwrap_or("");
+        let [type_column, client_column, tx_column, amount_column] = columns;
+        let name = field(type_column);
+        let ttype = TransactionType::from_name(name).or_else(|| self.aliases.get(name).copied());
+        let issues = &mut self.report.issues;
+        if ttype.is_none() {
+            let name = name.to_string();
+            issues.push(ValidationIssue::UnknownTransactionType { line, name });
+        }
+        let client = field(client_column);
+        let cid = match client.parse::<u16>() {
+            Ok(cid) => Some(ClientId::from(cid)),
+            Err(_) => {
+                let value = client.to_string();
+                issues.push(ValidationIssue::InvalidField {
+                    line,
+                    column: "client",
+                    value,
+                });
+                None
+            }
+        };
+        let tx = field(tx_column);
+        let tid = match tx.parse::<u32>() {
+            Ok(tid) => Some(TransactionId::from(tid)),
+            Err(_) => {
+                let value = tx.to_string();
+                issues.push(ValidationIssue::InvalidField {
+                    line,
+                    column: "tx",
+                    value,
+                });
+                None
+            }
+        };
+        let amount = field(amount_column);
+        // NOTE: Processing parses the amount of every row that has one,
+        //       so a bad amount is fatal even on a dispute.
+        if !amount.is_empty() && Currency::from_str(amount).is_err() {
+            let value = amount.to_string();
+            issues.push(ValidationIssue::InvalidField {
+                line,
+                column: "amount",
+                value,
+            });
+        }
+        let has_amount = matches!(
+            ttype,
+            Some(TransactionType::Deposit) | Some(TransactionType::Withdrawal)
+        );
+        if let (true, Some(tid)) = (has_amount, tid) {
+            if amount.is_empty() {
+                issues.push(ValidationIssue::MissingAmount { line, tid });
+            }
+            if let Some(cid) = cid {
+                match self.first_lines.get(&(cid, tid)) {
+                    Some(&first_line) => issues.push(ValidationIssue::DuplicateTransactionId {
+                        line,
+                        cid,
+                        tid,
+                        first_line,
+                    }),
+                    None => {
+                        self.first_lines.insert((cid, tid), line);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The issues found in the inputs validated so far.
+    #[inline(always)]
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.report.issues
+    }
+
+    /// The report of all inputs validated so far.
+    #[inline(always)]
+    pub fn finish(self) -> ValidationReport {
+        self.report
+    }
+}
+
+impl<S: AccountStore> Transactor<S> {
+    /// Validate the `CSV` file at `filepath` with the configuration of the
+    /// `Transactor`, without changing the state of any account.
+    pub async fn validate_csv_file(&self, filepath: PathBuf) -> AppResult<ValidationReport> {
+        let mut validator = Validator::new(&self.config);
+        validator.validate_csv_file(filepath).await?;
+        Ok(validator.finish())
+    }
+}
@@ -0,0 +1,107 @@
+use super::*;
+use crate::core::ClientId;
+use crate::test_util::temp::TempPath;
+
+async fn validate(csv: &str, config: &Config) -> AppResult<ValidationReport> {
+    let mut validator = Validator::new(config);
+    validator.validate_csv_reader(csv.as_bytes()).await?;
+    Ok(validator.finish())
+}
+
+#[tokio::test]
+async fn valid_input_has_no_issues() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               withdrawal,1,2,0.5\n\
+               dispute,1,1,\n\
+               resolve,1,1\n";
+    let report = validate(csv, &Config::default()).await?;
+    assert!(report.is_valid(), "issues: {:?}", report.issues);
+    assert_eq!(report.rows, 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn every_issue_is_reported_with_its_line() -> AppResult<()> {
+    let csv = "type,client,tx,amount\n\
+               deposit,1,1,1.0\n\
+               transfer,1,2,1.0\n\
+               deposit,1,3,abc\n\
+               withdrawal,1,4,\n\
+               deposit,x,5,1.0\n\
+               deposit,1,1,2.0\n\
+               deposit,2,1,2.0\n";
+    let report = validate(csv, &Config::default()).await?;
+    let tid = TransactionId::from;
+    assert_eq!(
+        report.issues,
+        vec![
+            ValidationIssue::UnknownTransactionType {
+                line: 3,
+                name: "transfer".to_string()
+            },
+            ValidationIssue::InvalidField {
+                line: 4,
+                column: "amount",
+                value: "abc".to_string()
+            },
+            ValidationIssue::MissingAmount {
+                line: 5,
+                tid: tid(4)
+            },
+            ValidationIssue::InvalidField {
+                line: 6,
+                column: "client",
+                value: "x".to_string()
+            },
+            // NOTE: Transaction 1 of client 2 isn't a duplicate.
+            ValidationIssue::DuplicateTransactionId {
+                line: 7,
+                cid: ClientId(1),
+                tid: tid(1),
+                first_line: 2
+            },
+        ]
+    );
+    assert_eq!(report.rows, 7);
+    assert_eq!(
+        report.issues[4].to_string(),
+        "line 7: tx 1 of client 1 was first seen on line 2"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn aliases_of_the_config_are_accepted() -> AppResult<()> {
+    let mut config = Config::default();
+    config
+        .type_aliases
+        .insert("credit".to_string(), TransactionType::Deposit);
+    let csv = "type,client,tx,amount\ncredit,1,1,1.0\n";
+    assert!(validate(csv, &config).await?.is_valid());
+    Ok(())
+}
+
+#[tokio::test]
+async fn duplicates_are_found_across_inputs() -> AppResult<()> {
+    let mut validator = Validator::new(&Config::default());
+    let csv = "type,client,tx,amount\ndeposit,1,1,1.0\n";
+    validator.validate_csv_reader(csv.as_bytes()).await?;
+    validator.validate_csv_reader(csv.as_bytes()).await?;
+    let report = validator.finish();
+    assert_eq!(report.rows, 2);
+    assert_eq!(report.issues.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn validation_leaves_the_accounts_alone() -> AppResult<()> {
+    let path = TempPath::new("validate.csv");
+    std::fs::write(&path, "type,client,tx,amount\ndeposit,1,1,2.0\n")?;
+    let transactor = Transactor::new();
+    let report = transactor.validate_csv_file(path.to_path_buf()).await?;
+    assert_eq!(report.rows, 1);
+    assert_eq!(transactor.len_accounts(), 0);
+    assert_eq!(transactor.stats()?.rows_processed, 0);
+    Ok(())
+}